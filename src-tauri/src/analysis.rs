@@ -0,0 +1,573 @@
+//! Core analysis entry points, usable without the Tauri runtime.
+//!
+//! The `#[tauri::command]` functions in `lib.rs` are thin adapters over these,
+//! translating [`AnalysisError`] into whatever shape the frontend expects.
+
+use crate::backend::DemuxError;
+use crate::ff::{extract_streams, Ffmpeg, FfmpegError, Ffprobe, FfprobeError};
+use crate::image::ImageError;
+use crate::manifest::ManifestError;
+use crate::media::{self, is_media_file};
+use crate::models::{
+    BitrateData, Confidence, Detection, DetectionMethod, File, FileCandidacy, ImageAnalysis,
+    LoudnessAnalysis, ManifestAnalysis, MediaType, QcReport, QualityAnalysis, SceneScore,
+    StreamResult, Thumbnail, ThumbnailFormat, TsAnalysis, Waveform,
+};
+use crate::process::ProcessError;
+use crate::qc::QcSettings;
+use crate::remote::{self, NetworkError, NetworkSettings, RemoteHeader};
+use crate::scan::{self, ScanError, ScanSettings};
+use crate::strings::transform_filename;
+use crate::tools::{Tool, ToolError};
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+use tracing::{debug, error, info, instrument, warn};
+
+#[derive(Debug, Error)]
+pub enum AnalysisError {
+    #[error("File does not exist")]
+    NotFound,
+    #[error("Not a file (directory or other)")]
+    NotFile,
+    #[error("Not a media file (detected as {0:?})")]
+    NotMedia(MediaType),
+    #[error(transparent)]
+    Tool(#[from] ToolError),
+    #[error("Could not analyze media file: {0}")]
+    Probe(#[from] FfprobeError),
+    #[error("Could not decode media file: {0}")]
+    Decode(#[from] FfmpegError),
+    #[error("Could not parse container: {0}")]
+    Demux(#[from] DemuxError),
+    #[error("Could not read manifest: {0}")]
+    Manifest(#[from] ManifestError),
+    #[error("Could not read image: {0}")]
+    Image(#[from] ImageError),
+    #[error(transparent)]
+    Network(#[from] NetworkError),
+    #[error("Could not search directory: {0}")]
+    Scan(#[from] ScanError),
+    #[error("No media files found in directory")]
+    EmptyDirectory,
+    #[error("No frame data could be extracted from file")]
+    NoFrames,
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("No video stream with known dimensions")]
+    NoVideoStream,
+}
+
+impl AnalysisError {
+    /// Stable machine-readable identifier, used as `error_type` by the frontend.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AnalysisError::NotFound => "not_found",
+            AnalysisError::NotFile => "not_file",
+            AnalysisError::NotMedia(_) => "not_media",
+            AnalysisError::Tool(
+                ToolError::NotFound {
+                    tool: Tool::Ffmpeg, ..
+                }
+                | ToolError::Unsupported {
+                    tool: Tool::Ffmpeg, ..
+                },
+            ) => "ffmpeg_unavailable",
+            AnalysisError::Tool(_) => "ffprobe_unavailable",
+            AnalysisError::Probe(FfprobeError::Process(ProcessError::Timeout { .. }))
+            | AnalysisError::Decode(FfmpegError::Process(ProcessError::Timeout { .. })) => {
+                "timeout"
+            }
+            AnalysisError::Probe(_) | AnalysisError::Demux(_) | AnalysisError::Decode(_) => {
+                "analysis_failed"
+            }
+            AnalysisError::Manifest(_) => "invalid_manifest",
+            AnalysisError::Image(ImageError::Unsupported) => "unsupported_format",
+            AnalysisError::Image(_) => "invalid_image",
+            AnalysisError::Network(err) => err.kind(),
+            AnalysisError::Scan(_) => "invalid_pattern",
+            AnalysisError::EmptyDirectory => "empty_directory",
+            AnalysisError::NoFrames => "no_frames",
+            AnalysisError::Io(_) => "io_error",
+            AnalysisError::NoVideoStream => "no_video_stream",
+        }
+    }
+}
+
+/// Returns the final path component as a string, or `"unknown"` when it has none.
+pub fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Ensures the path exists and is a regular file.
+fn ensure_file(path: &Path) -> Result<(), AnalysisError> {
+    if !path.exists() {
+        return Err(AnalysisError::NotFound);
+    }
+
+    if !path.is_file() {
+        return Err(AnalysisError::NotFile);
+    }
+
+    Ok(())
+}
+
+/// Determines whether a path is something we can analyze, with the details of how its
+/// media type was determined.
+///
+/// With `ffprobe`, files that neither magic numbers nor the extension identify are
+/// classified by a quick format probe.
+#[instrument(skip(ffprobe, path), fields(path = %path.display()))]
+pub fn detect_file(ffprobe: Option<&Ffprobe>, path: &Path) -> Result<Detection, AnalysisError> {
+    let truncated_name = transform_filename(&file_name(path), 15);
+
+    if let Err(err) = ensure_file(path) {
+        warn!(filename = %truncated_name, error = %err, "File is not analyzable");
+        return Err(err);
+    }
+
+    // Detect media type using magic numbers and fallback to extensions
+    let mut detection = media::detect(path);
+    if detection.media_type == MediaType::Unknown {
+        if let Some(probed) = ffprobe.and_then(|ffprobe| detect_with_ffprobe(ffprobe, path)) {
+            detection = probed;
+        }
+    }
+    let media_type = detection.media_type.clone();
+    debug!(filename = %truncated_name, media_type = ?media_type, "Media type detected");
+
+    if is_media_file(&media_type) {
+        Ok(detection)
+    } else {
+        debug!(filename = %truncated_name, media_type = ?media_type, "Non-media file detected");
+        Err(AnalysisError::NotMedia(media_type))
+    }
+}
+
+/// Classifies a file ffprobe can demux, for containers without a magic number we know
+/// (raw `.h264`, `.ivf`, `.y4m`, `.mxf`, ...).
+fn detect_with_ffprobe(ffprobe: &Ffprobe, path: &Path) -> Option<Detection> {
+    let truncated_name = transform_filename(&file_name(path), 15);
+    let probe = ffprobe
+        .probe_format(path)
+        .inspect_err(|err| debug!(filename = %truncated_name, error = %err, "Format probe failed"))
+        .ok()?;
+    let media_type = probe.media_type()?;
+
+    debug!(
+        filename = %truncated_name,
+        format = ?probe.format_name(),
+        media_type = ?media_type,
+        "Media type detected by ffprobe"
+    );
+    Some(Detection {
+        media_type,
+        mime_type: None,
+        method: DetectionMethod::Ffprobe,
+        confidence: Confidence::Medium,
+        mismatch: None,
+    })
+}
+
+/// Builds the drop-overlay candidacy entry for a single path, falling back to `ffprobe`
+/// for files that aren't otherwise recognized.
+pub fn analyze_file(ffprobe: Option<&Ffprobe>, path: &Path) -> File {
+    file_entry(path, detect_file(ffprobe, path))
+}
+
+fn file_entry(path: &Path, detection: Result<Detection, AnalysisError>) -> File {
+    let filename = file_name(path);
+    let size = std::fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0) as u32;
+
+    let candidacy = match detection {
+        Ok(detection) => {
+            info!(filename = %transform_filename(&filename, 15), media_type = ?detection.media_type, "Valid media file detected");
+            FileCandidacy::Success {
+                file_type: detection.media_type.clone(),
+                detection,
+            }
+        }
+        Err(err) => FileCandidacy::Error {
+            reason: err.to_string(),
+        },
+    };
+
+    File {
+        path: path.to_string_lossy().into_owned(),
+        filename,
+        size,
+        candidacy,
+    }
+}
+
+/// Builds candidacy entries for every media file under a dropped directory.
+///
+/// A directory that yields nothing, or whose filters are invalid, becomes a single error
+/// entry so the drop isn't silently ignored. Unlike dropped files, walked files are never
+/// probed with ffprobe: folders are full of sidecars and metadata nothing recognizes, and
+/// probing each in turn would stall the drop.
+#[instrument(skip(dir, settings), fields(dir = %dir.display()))]
+pub fn analyze_directory(dir: &Path, settings: &ScanSettings) -> Vec<File> {
+    let error_entry = |err: AnalysisError| File {
+        path: dir.to_string_lossy().into_owned(),
+        filename: file_name(dir),
+        size: 0,
+        candidacy: FileCandidacy::Error {
+            reason: err.to_string(),
+        },
+    };
+
+    // Each file is detected once, both to filter the walk and for its entry
+    let mut entries = Vec::new();
+    let walked = scan::walk_files(dir, settings, |path| match detect_file(None, path) {
+        Ok(detection) => {
+            entries.push(file_entry(path, Ok(detection)));
+            true
+        }
+        Err(_) => false,
+    });
+
+    match walked {
+        Ok(_) if entries.is_empty() => vec![error_entry(AnalysisError::EmptyDirectory)],
+        Ok(_) => {
+            info!(file_count = entries.len(), "Expanded directory");
+            entries
+        }
+        Err(err) => {
+            warn!(error = %err, "Could not expand directory");
+            vec![error_entry(AnalysisError::from(err))]
+        }
+    }
+}
+
+/// Runs ffprobe against a media file and collects its streams.
+#[instrument(skip(ffprobe, path), fields(path = %path.display()))]
+pub fn probe_file(ffprobe: &Ffprobe, path: &Path) -> Result<StreamResult, AnalysisError> {
+    let filename = file_name(path);
+    let truncated_name = transform_filename(&filename, 15);
+
+    let media_type = detect_file(Some(ffprobe), path)?.media_type;
+    let size = std::fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    debug!(filename = %truncated_name, size = size, "File metadata retrieved");
+
+    info!(filename = %truncated_name, media_type = ?media_type, "Analyzing media file with ffprobe");
+    let info = ffprobe.probe(path).map_err(|err| {
+        error!(filename = %truncated_name, error = %err, "Failed to analyze media file with ffprobe");
+        AnalysisError::from(err)
+    })?;
+
+    let streams = extract_streams(&info);
+    let duration = info
+        .format
+        .duration
+        .and_then(|dur_str| dur_str.parse::<f64>().ok());
+
+    info!(
+        filename = %truncated_name,
+        stream_count = streams.len(),
+        duration = ?duration,
+        "Successfully analyzed media file"
+    );
+
+    Ok(StreamResult {
+        filename,
+        path: path.to_string_lossy().into_owned(),
+        media_type,
+        duration,
+        size,
+        streams,
+    })
+}
+
+/// Fetches the start of a remote resource and determines whether we can analyze it.
+pub fn classify_url(
+    url: &str,
+    network: &NetworkSettings,
+) -> Result<(MediaType, RemoteHeader), AnalysisError> {
+    detect_url(url, network).map(|(detection, header)| (detection.media_type, header))
+}
+
+/// Like [`classify_url`], but with the details of how the media type was determined.
+#[instrument(skip(url, network), fields(url = %url))]
+pub fn detect_url(
+    url: &str,
+    network: &NetworkSettings,
+) -> Result<(Detection, RemoteHeader), AnalysisError> {
+    let parsed = remote::parse_url(url)?;
+    let truncated_name = transform_filename(&remote::url_file_name(&parsed), 15);
+
+    let header = remote::fetch_header(&parsed, network).inspect_err(|err| {
+        warn!(filename = %truncated_name, error = %err, "Remote file is not reachable");
+    })?;
+
+    // Magic numbers first, then the extension in the URL path; query strings are ignored
+    let detection = media::detect_from_bytes(&header.bytes, &remote::url_file_name(&parsed));
+    let media_type = detection.media_type.clone();
+    debug!(
+        filename = %truncated_name,
+        media_type = ?media_type,
+        content_type = ?header.content_type,
+        "Media type detected"
+    );
+
+    if is_media_file(&media_type) {
+        Ok((detection, header))
+    } else {
+        Err(AnalysisError::NotMedia(media_type))
+    }
+}
+
+/// Builds the drop-overlay candidacy entry for an `http(s)://` URL.
+pub fn analyze_url(url: &str, network: &NetworkSettings) -> File {
+    let filename = remote::display_name(url);
+
+    let (size, candidacy) = match detect_url(url, network) {
+        Ok((detection, header)) => {
+            info!(filename = %transform_filename(&filename, 15), media_type = ?detection.media_type, "Valid remote media file detected");
+            (
+                header.size.unwrap_or(0) as u32,
+                FileCandidacy::Success {
+                    file_type: detection.media_type.clone(),
+                    detection,
+                },
+            )
+        }
+        Err(err) => (
+            0,
+            FileCandidacy::Error {
+                reason: err.to_string(),
+            },
+        ),
+    };
+
+    File {
+        path: url.to_string(),
+        filename,
+        size,
+        candidacy,
+    }
+}
+
+/// Runs ffprobe against an `http(s)://` URL and collects its streams.
+#[instrument(skip(ffprobe, url, network), fields(url = %url))]
+pub fn probe_url(
+    ffprobe: &Ffprobe,
+    url: &str,
+    network: &NetworkSettings,
+) -> Result<StreamResult, AnalysisError> {
+    let filename = remote::url_file_name(&remote::parse_url(url)?);
+    let (media_type, header) = classify_url(url, network)?;
+    let truncated_name = transform_filename(&filename, 15);
+
+    info!(filename = %truncated_name, media_type = ?media_type, "Analyzing remote media file with ffprobe");
+    let info = ffprobe.probe_url(url, network).map_err(|err| {
+        error!(filename = %truncated_name, error = %err, "Failed to analyze remote media file with ffprobe");
+        AnalysisError::from(err)
+    })?;
+
+    let streams = extract_streams(&info);
+    let duration = info
+        .format
+        .duration
+        .and_then(|dur_str| dur_str.parse::<f64>().ok());
+    // Servers that don't report a length leave ffprobe's view of the format as the only source
+    let size = header
+        .size
+        .or_else(|| info.format.size.parse::<u64>().ok())
+        .unwrap_or(0);
+
+    Ok(StreamResult {
+        filename,
+        path: url.to_string(),
+        media_type,
+        duration,
+        size,
+        streams,
+    })
+}
+
+/// Extracts per-frame packet sizes from the first video stream, natively where possible.
+#[instrument(skip(ffprobe, path), fields(path = %path.display()))]
+pub fn extract_bitrate_data(ffprobe: &Ffprobe, path: &Path) -> Result<BitrateData, AnalysisError> {
+    let filename = file_name(path);
+
+    if !path.exists() {
+        error!(filename = %filename, "File does not exist");
+        return Err(AnalysisError::NotFound);
+    }
+
+    let data = crate::backend::extract_bitrate_data(ffprobe, path).inspect_err(|err| {
+        warn!(filename = %filename, error = %err, "No frame data extracted");
+    })?;
+
+    info!(
+        filename = %filename,
+        frame_count = data.frames.len(),
+        "Successfully extracted bitrate data"
+    );
+
+    Ok(data)
+}
+
+/// Analyzes an MPEG transport stream's packets, programs and timing.
+#[instrument(skip(path), fields(path = %path.display()))]
+pub fn analyze_transport_stream(path: &Path) -> Result<TsAnalysis, AnalysisError> {
+    let truncated_name = transform_filename(&file_name(path), 15);
+    ensure_file(path)?;
+
+    crate::ts::analyze(path, crate::ts::DEFAULT_INTERVAL).map_err(|err| {
+        warn!(filename = %truncated_name, error = %err, "Transport stream analysis failed");
+        AnalysisError::from(err)
+    })
+}
+
+/// Reads an image's dimensions, pixel format and metadata, plus frame timing if animated.
+#[instrument(skip(path), fields(path = %path.display()))]
+pub fn analyze_image(path: &Path) -> Result<ImageAnalysis, AnalysisError> {
+    let truncated_name = transform_filename(&file_name(path), 15);
+    ensure_file(path)?;
+
+    crate::image::analyze(path).map_err(|err| {
+        warn!(filename = %truncated_name, error = %err, "Image analysis failed");
+        AnalysisError::from(err)
+    })
+}
+
+/// Measures EBU R128 loudness of the `audio_stream`-th audio stream (counting from 0).
+#[instrument(skip(ffmpeg, path), fields(path = %path.display()))]
+pub fn analyze_loudness(
+    ffmpeg: &Ffmpeg,
+    path: &Path,
+    audio_stream: u32,
+) -> Result<LoudnessAnalysis, AnalysisError> {
+    let truncated_name = transform_filename(&file_name(path), 15);
+    ensure_file(path)?;
+
+    crate::loudness::analyze(ffmpeg, path, audio_stream).inspect_err(|err| {
+        warn!(filename = %truncated_name, error = %err, "Loudness analysis failed");
+    })
+}
+
+/// Extracts min/max waveform peaks of the `audio_stream`-th audio stream, with
+/// `resolution` buckets per second.
+#[instrument(skip(ffmpeg, path), fields(path = %path.display()))]
+pub fn extract_waveform(
+    ffmpeg: &Ffmpeg,
+    path: &Path,
+    audio_stream: u32,
+    resolution: u32,
+) -> Result<Waveform, AnalysisError> {
+    let truncated_name = transform_filename(&file_name(path), 15);
+    ensure_file(path)?;
+
+    crate::waveform::extract(ffmpeg, path, audio_stream, resolution).inspect_err(|err| {
+        warn!(filename = %truncated_name, error = %err, "Waveform extraction failed");
+    })
+}
+
+/// Scores every frame of the first video stream for how much it differs from the last.
+#[instrument(skip(ffmpeg, path), fields(path = %path.display()))]
+pub fn score_scenes(ffmpeg: &Ffmpeg, path: &Path) -> Result<Vec<SceneScore>, AnalysisError> {
+    let truncated_name = transform_filename(&file_name(path), 15);
+    ensure_file(path)?;
+
+    crate::scene::score(ffmpeg, path).inspect_err(|err| {
+        warn!(filename = %truncated_name, error = %err, "Scene detection failed");
+    })
+}
+
+/// Looks for black segments and silent gaps using the given thresholds.
+#[instrument(skip(ffprobe, ffmpeg, path), fields(path = %path.display()))]
+pub fn check_quality(
+    ffprobe: &Ffprobe,
+    ffmpeg: &Ffmpeg,
+    path: &Path,
+    settings: &QcSettings,
+) -> Result<QcReport, AnalysisError> {
+    let truncated_name = transform_filename(&file_name(path), 15);
+    ensure_file(path)?;
+
+    crate::qc::run(ffprobe, ffmpeg, path, settings).inspect_err(|err| {
+        warn!(filename = %truncated_name, error = %err, "QC detection failed");
+    })
+}
+
+/// Scores `distorted` against `reference` with PSNR, SSIM and, when available, VMAF.
+#[instrument(skip(ffmpeg, distorted, reference), fields(path = %distorted.display()))]
+pub fn compare_quality(
+    ffmpeg: &Ffmpeg,
+    distorted: &Path,
+    reference: &Path,
+) -> Result<QualityAnalysis, AnalysisError> {
+    let truncated_name = transform_filename(&file_name(distorted), 15);
+    ensure_file(distorted)?;
+    ensure_file(reference)?;
+
+    crate::quality::compare(ffmpeg, distorted, reference).inspect_err(|err| {
+        warn!(filename = %truncated_name, error = %err, "Quality comparison failed");
+    })
+}
+
+/// Renders the frame at `timestamp` seconds into the thumbnail directory `root`.
+#[instrument(skip(ffmpeg, path, root), fields(path = %path.display()))]
+pub fn thumbnail_at(
+    ffmpeg: &Ffmpeg,
+    path: &Path,
+    root: &Path,
+    timestamp: f64,
+    format: ThumbnailFormat,
+    width: u32,
+) -> Result<Thumbnail, AnalysisError> {
+    let truncated_name = transform_filename(&file_name(path), 15);
+    ensure_file(path)?;
+
+    let dir = crate::thumbnails::file_dir(root, path, ffmpeg)?;
+    crate::thumbnails::at_timestamp(ffmpeg, path, &dir, timestamp, format, width).inspect_err(
+        |err| warn!(filename = %truncated_name, error = %err, "Thumbnail extraction failed"),
+    )
+}
+
+/// Renders every keyframe into the thumbnail directory `root`, tied to the frame
+/// numbers of `bitrate`.
+#[instrument(skip(ffmpeg, path, root, bitrate), fields(path = %path.display()))]
+pub fn keyframe_thumbnails(
+    ffmpeg: &Ffmpeg,
+    path: &Path,
+    root: &Path,
+    bitrate: &BitrateData,
+    format: ThumbnailFormat,
+    width: u32,
+) -> Result<Vec<Thumbnail>, AnalysisError> {
+    let truncated_name = transform_filename(&file_name(path), 15);
+    ensure_file(path)?;
+
+    let dir = crate::thumbnails::file_dir(root, path, ffmpeg)?;
+    crate::thumbnails::for_keyframes(ffmpeg, path, &dir, bitrate, format, width).inspect_err(
+        |err| warn!(filename = %truncated_name, error = %err, "Thumbnail extraction failed"),
+    )
+}
+
+/// Compares an HLS or DASH manifest's declared bitrates against its local segments.
+///
+/// ffprobe is optional: without it, segment sizes are still measured but keyframes are
+/// only found in containers a native backend can read.
+#[instrument(skip(ffprobe, path), fields(path = %path.display()))]
+pub fn analyze_manifest(
+    ffprobe: Option<&Ffprobe>,
+    path: &Path,
+) -> Result<ManifestAnalysis, AnalysisError> {
+    let truncated_name = transform_filename(&file_name(path), 15);
+    ensure_file(path)?;
+
+    crate::manifest::analyze(path, ffprobe).map_err(|err| {
+        warn!(filename = %truncated_name, error = %err, "Manifest analysis failed");
+        AnalysisError::from(err)
+    })
+}
@@ -0,0 +1,143 @@
+//! Pluggable sources of per-frame bitrate data.
+//!
+//! Native demuxers read sample sizes and timestamps straight from container index
+//! structures, which is far faster than having ffprobe walk every frame. Anything they
+//! don't recognize, or fail to parse, falls back to the ffprobe backend.
+
+mod animation;
+mod matroska;
+mod mp4;
+mod probe;
+
+pub use animation::AnimationBackend;
+pub use matroska::MatroskaBackend;
+pub use mp4::Mp4Backend;
+pub use probe::{parse_compact_line, FfprobeBackend};
+
+use crate::analysis::AnalysisError;
+use crate::ff::Ffprobe;
+use crate::models::{BitrateData, BitrateFrame};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+/// Number of leading bytes handed to [`AnalysisBackend::supports`].
+const SNIFF_LEN: usize = 64;
+
+#[derive(Debug, Error)]
+pub enum DemuxError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Malformed container: {0}")]
+    Malformed(String),
+    #[error("Unsupported container layout: {0}")]
+    Unsupported(String),
+}
+
+/// A way of extracting per-frame sizes from a media file.
+pub trait AnalysisBackend {
+    fn name(&self) -> &'static str;
+
+    /// Whether this backend understands the file, judged from its first bytes.
+    fn supports(&self, header: &[u8]) -> bool;
+
+    /// Extracts frame sizes for the first video stream.
+    fn extract_bitrate_data(&self, path: &Path) -> Result<BitrateData, AnalysisError>;
+}
+
+/// A demuxed video sample, in whatever order the container stores them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub size: u64,
+    /// Presentation timestamp in seconds.
+    pub pts: Option<f64>,
+    pub keyframe: bool,
+}
+
+/// Sorts samples into presentation order and numbers them as frames.
+///
+/// Samples without a timestamp keep their relative position.
+pub fn into_frames(mut samples: Vec<Sample>) -> Vec<BitrateFrame> {
+    if samples.iter().all(|sample| sample.pts.is_some()) {
+        samples.sort_by(|a, b| {
+            a.pts
+                .partial_cmp(&b.pts)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+
+    samples
+        .into_iter()
+        .enumerate()
+        .map(|(index, sample)| BitrateFrame {
+            frame_num: index as u32,
+            packet_size: sample.size,
+            timestamp: sample.pts,
+            keyframe: sample.keyframe,
+        })
+        .collect()
+}
+
+/// Builds [`BitrateData`] from demuxed samples, rejecting empty results.
+pub fn bitrate_data(id: String, samples: Vec<Sample>) -> Result<BitrateData, AnalysisError> {
+    if samples.is_empty() {
+        return Err(AnalysisError::NoFrames);
+    }
+
+    Ok(BitrateData {
+        id,
+        frames: into_frames(samples),
+    })
+}
+
+fn read_header(path: &Path) -> io::Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(SNIFF_LEN);
+    File::open(path)?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut header)?;
+    Ok(header)
+}
+
+/// Native backends in order of preference.
+pub fn native_backends() -> Vec<Box<dyn AnalysisBackend>> {
+    vec![
+        Box::new(Mp4Backend),
+        Box::new(MatroskaBackend),
+        Box::new(AnimationBackend),
+    ]
+}
+
+/// Extracts bitrate data with the first native backend that supports the file.
+///
+/// Returns `None` when no native backend recognizes the file.
+pub fn extract_native(path: &Path) -> Option<Result<BitrateData, AnalysisError>> {
+    let header = match read_header(path) {
+        Ok(header) => header,
+        Err(err) => {
+            debug!(error = %err, "Could not read header for native extraction");
+            return None;
+        }
+    };
+
+    let backend = native_backends()
+        .into_iter()
+        .find(|backend| backend.supports(&header))?;
+    info!(backend = backend.name(), "Extracting bitrate data natively");
+    Some(backend.extract_bitrate_data(path).inspect_err(|err| {
+        warn!(backend = backend.name(), error = %err, "Native extraction failed");
+    }))
+}
+
+/// Extracts bitrate data natively where possible, falling back to ffprobe when no
+/// native backend supports the file or the native parse fails.
+pub fn extract_bitrate_data(ffprobe: &Ffprobe, path: &Path) -> Result<BitrateData, AnalysisError> {
+    match extract_native(path) {
+        Some(Ok(data)) => return Ok(data),
+        Some(Err(_)) => debug!("Falling back to ffprobe"),
+        None => debug!("No native backend supports file, using ffprobe"),
+    }
+
+    FfprobeBackend::new(ffprobe.clone()).extract_bitrate_data(path)
+}
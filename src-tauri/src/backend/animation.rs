@@ -0,0 +1,46 @@
+//! Frame timelines for animated GIF, APNG and WebP.
+//!
+//! ffprobe reports these as a single video stream, but its packet sizes and timestamps
+//! for them are unreliable. The image parsers already walk every frame, so their sizes
+//! and delays are turned into samples with timestamps accumulated from the delays.
+
+use super::{bitrate_data, AnalysisBackend, Sample};
+use crate::analysis::AnalysisError;
+use crate::image;
+use crate::models::BitrateData;
+use std::path::Path;
+
+pub struct AnimationBackend;
+
+impl AnalysisBackend for AnimationBackend {
+    fn name(&self) -> &'static str {
+        "animation"
+    }
+
+    fn supports(&self, header: &[u8]) -> bool {
+        image::is_animatable(header)
+    }
+
+    fn extract_bitrate_data(&self, path: &Path) -> Result<BitrateData, AnalysisError> {
+        let analysis = image::analyze(path)?;
+        // Still images have no timeline of their own; leave them to ffprobe
+        let animation = analysis.animation.ok_or(AnalysisError::NoFrames)?;
+
+        let mut elapsed_ms = 0u64;
+        let samples = animation
+            .frames
+            .iter()
+            .map(|frame| {
+                let pts = elapsed_ms as f64 / 1000.0;
+                elapsed_ms += u64::from(frame.delay_ms);
+                Sample {
+                    size: frame.size,
+                    pts: Some(pts),
+                    // Frames covering the whole canvas don't build on the previous one
+                    keyframe: frame.width == analysis.width && frame.height == analysis.height,
+                }
+            })
+            .collect();
+        bitrate_data(analysis.id, samples)
+    }
+}
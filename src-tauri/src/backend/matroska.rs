@@ -0,0 +1,429 @@
+//! Matroska/WebM demuxing from block headers.
+//!
+//! Only the first few bytes of each block are read; frame payloads are seeked over, so
+//! even very large files are walked quickly without buffering media data.
+
+use super::{bitrate_data, AnalysisBackend, DemuxError, Sample};
+use crate::analysis::{file_name, AnalysisError};
+use crate::bytes::{ByteReader, Truncated};
+use crate::models::BitrateData;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek};
+use std::path::Path;
+use tracing::{debug, instrument};
+
+const EBML_MAGIC: [u8; 4] = [0x1A, 0x45, 0xDF, 0xA3];
+
+const EBML: u32 = 0x1A45_DFA3;
+const SEGMENT: u32 = 0x1853_8067;
+const SEEK_HEAD: u32 = 0x114D_9B74;
+const INFO: u32 = 0x1549_A966;
+const TIMESTAMP_SCALE: u32 = 0x2A_D7B1;
+const TRACKS: u32 = 0x1654_AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const TRACK_TYPE: u32 = 0x83;
+const CLUSTER: u32 = 0x1F43_B675;
+const CLUSTER_TIMESTAMP: u32 = 0xE7;
+const SIMPLE_BLOCK: u32 = 0xA3;
+const BLOCK_GROUP: u32 = 0xA0;
+const BLOCK: u32 = 0xA1;
+const REFERENCE_BLOCK: u32 = 0xFB;
+const CUES: u32 = 0x1C53_BB6B;
+const CHAPTERS: u32 = 0x1043_A770;
+const TAGS: u32 = 0x1254_C367;
+const ATTACHMENTS: u32 = 0x1941_A469;
+
+/// `TrackType` value for video tracks.
+const VIDEO_TRACK: u64 = 1;
+/// Default `TimestampScale`: timestamps in milliseconds.
+const DEFAULT_TIMESTAMP_SCALE: u64 = 1_000_000;
+/// Upper bound on metadata elements (`Info`, `Tracks`) we buffer in memory.
+const MAX_METADATA_ELEMENT: u64 = 16 * 1024 * 1024;
+
+/// Elements that may follow a cluster of unknown size, ending it.
+const LEVEL_ONE: [u32; 8] = [
+    CLUSTER,
+    CUES,
+    INFO,
+    TRACKS,
+    SEEK_HEAD,
+    CHAPTERS,
+    TAGS,
+    ATTACHMENTS,
+];
+
+pub struct MatroskaBackend;
+
+impl AnalysisBackend for MatroskaBackend {
+    fn name(&self) -> &'static str {
+        "matroska"
+    }
+
+    fn supports(&self, header: &[u8]) -> bool {
+        header.starts_with(&EBML_MAGIC)
+    }
+
+    fn extract_bitrate_data(&self, path: &Path) -> Result<BitrateData, AnalysisError> {
+        let samples = read_video_samples(path)?;
+        bitrate_data(file_name(path), samples)
+    }
+}
+
+/// Total length of a variable-size integer, from its first byte.
+fn vint_length(first: u8) -> Option<usize> {
+    (first != 0).then(|| first.leading_zeros() as usize + 1)
+}
+
+/// Decodes an element size, returning `None` for the reserved "unknown size" value.
+fn decode_size(bytes: &[u8]) -> Option<u64> {
+    let len = bytes.len();
+    let marker_mask = 0xFFu8.checked_shr(len as u32).unwrap_or(0);
+    let value = bytes[1..]
+        .iter()
+        .fold(u64::from(bytes[0] & marker_mask), |value, &byte| {
+            (value << 8) | u64::from(byte)
+        });
+    let unknown = (1u64 << (7 * len)) - 1;
+    (value != unknown).then_some(value)
+}
+
+fn decode_id(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| (value << 8) | u32::from(byte))
+}
+
+fn read_uint(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .take(8)
+        .fold(0, |value, &byte| (value << 8) | u64::from(byte))
+}
+
+/// Reads an element header from an in-memory buffer.
+fn slice_header(reader: &mut ByteReader) -> Result<(u32, Option<u64>), DemuxError> {
+    let first = reader.rest().first().copied().ok_or(Truncated)?;
+    let id_len = vint_length(first)
+        .filter(|&len| len <= 4)
+        .ok_or_else(|| DemuxError::Malformed("invalid element ID".to_string()))?;
+    let id = decode_id(reader.bytes(id_len)?);
+
+    let first = reader.rest().first().copied().ok_or(Truncated)?;
+    let size_len = vint_length(first)
+        .ok_or_else(|| DemuxError::Malformed("invalid element size".to_string()))?;
+    Ok((id, decode_size(reader.bytes(size_len)?)))
+}
+
+/// Iterates child elements of a fully buffered master element as `(id, payload)`.
+fn children(data: &[u8]) -> Result<Vec<(u32, &[u8])>, DemuxError> {
+    let mut reader = ByteReader::new(data);
+    let mut elements = Vec::new();
+
+    while !reader.is_empty() {
+        let (id, size) = slice_header(&mut reader)?;
+        let size = size.ok_or_else(|| {
+            DemuxError::Unsupported("unknown-size element inside metadata".to_string())
+        })?;
+        let payload = reader.bytes(usize::try_from(size).map_err(|_| Truncated)?)?;
+        elements.push((id, payload));
+    }
+
+    Ok(elements)
+}
+
+/// Returns the track number of the first video track.
+fn find_video_track(tracks: &[u8]) -> Result<Option<u64>, DemuxError> {
+    for (id, entry) in children(tracks)? {
+        if id != TRACK_ENTRY {
+            continue;
+        }
+
+        let (mut number, mut kind) = (None, None);
+        for (id, payload) in children(entry)? {
+            match id {
+                TRACK_NUMBER => number = Some(read_uint(payload)),
+                TRACK_TYPE => kind = Some(read_uint(payload)),
+                _ => {}
+            }
+        }
+
+        if kind == Some(VIDEO_TRACK) {
+            return Ok(number);
+        }
+    }
+
+    Ok(None)
+}
+
+/// A seekable element stream that tracks its absolute position.
+struct ElementReader<R> {
+    inner: R,
+    pos: u64,
+}
+
+impl<R: Read + Seek> ElementReader<R> {
+    fn byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        match self.inner.read(&mut byte)? {
+            0 => Ok(None),
+            _ => {
+                self.pos += 1;
+                Ok(Some(byte[0]))
+            }
+        }
+    }
+
+    fn exact(&mut self, buffer: &mut [u8]) -> Result<(), DemuxError> {
+        self.inner.read_exact(buffer).map_err(truncation)?;
+        self.pos += buffer.len() as u64;
+        Ok(())
+    }
+
+    fn vint(&mut self, first: u8, max_len: usize) -> Result<Vec<u8>, DemuxError> {
+        let len = vint_length(first)
+            .filter(|&len| len <= max_len)
+            .ok_or_else(|| {
+                DemuxError::Malformed(format!("invalid variable-size integer at {}", self.pos))
+            })?;
+        let mut bytes = vec![0; len];
+        bytes[0] = first;
+        self.exact(&mut bytes[1..])?;
+        Ok(bytes)
+    }
+
+    /// Reads an element header, or `None` at end of file.
+    fn header(&mut self) -> Result<Option<(u32, Option<u64>)>, DemuxError> {
+        let Some(first) = self.byte()? else {
+            return Ok(None);
+        };
+        let id = decode_id(&self.vint(first, 4)?);
+        let first = self
+            .byte()?
+            .ok_or_else(|| truncation(io::ErrorKind::UnexpectedEof.into()))?;
+        let size = decode_size(&self.vint(first, 8)?);
+        Ok(Some((id, size)))
+    }
+
+    fn skip(&mut self, len: u64) -> Result<(), DemuxError> {
+        let offset = i64::try_from(len)
+            .map_err(|_| DemuxError::Malformed("element too large".to_string()))?;
+        self.inner.seek_relative(offset)?;
+        self.pos += len;
+        Ok(())
+    }
+
+    fn read(&mut self, len: u64) -> Result<Vec<u8>, DemuxError> {
+        if len > MAX_METADATA_ELEMENT {
+            return Err(DemuxError::Unsupported(format!(
+                "metadata element of {len} bytes is too large"
+            )));
+        }
+        let mut buffer = vec![0; len as usize];
+        self.exact(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+fn truncation(err: io::Error) -> DemuxError {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => DemuxError::Malformed("file is truncated".to_string()),
+        _ => DemuxError::Io(err),
+    }
+}
+
+fn known(size: Option<u64>, element: &str) -> Result<u64, DemuxError> {
+    size.ok_or_else(|| DemuxError::Unsupported(format!("{element} of unknown size")))
+}
+
+/// Demuxing state carried across clusters.
+struct Demuxer {
+    timestamp_scale: u64,
+    video_track: Option<u64>,
+    samples: Vec<Sample>,
+}
+
+impl Demuxer {
+    /// Reads a block header of `size` bytes, recording it if it belongs to the video track.
+    ///
+    /// Returns whether the block was recorded.
+    fn block<R: Read + Seek>(
+        &mut self,
+        reader: &mut ElementReader<R>,
+        size: u64,
+        cluster_timestamp: u64,
+        keyframe: Option<bool>,
+    ) -> Result<bool, DemuxError> {
+        let first = reader
+            .byte()?
+            .ok_or_else(|| truncation(io::ErrorKind::UnexpectedEof.into()))?;
+        let track_bytes = reader.vint(first, 8)?;
+        let mut rest = [0; 3];
+        reader.exact(&mut rest)?;
+
+        let header_len = track_bytes.len() as u64 + 3;
+        let payload = size
+            .checked_sub(header_len)
+            .ok_or_else(|| DemuxError::Malformed("block shorter than its header".to_string()))?;
+        reader.skip(payload)?;
+
+        let track = decode_size(&track_bytes);
+        let video_track = self
+            .video_track
+            .ok_or_else(|| DemuxError::Unsupported("cluster before track list".to_string()))?;
+        if track != Some(video_track) {
+            return Ok(false);
+        }
+
+        let relative = i16::from_be_bytes([rest[0], rest[1]]);
+        let timestamp = cluster_timestamp as i64 + i64::from(relative);
+        self.samples.push(Sample {
+            size: payload,
+            pts: Some(timestamp as f64 * self.timestamp_scale as f64 / 1e9),
+            // SimpleBlock carries its own key flag; BlockGroup keyness comes from ReferenceBlock
+            keyframe: keyframe.unwrap_or(rest[2] & 0x80 != 0),
+        });
+        Ok(true)
+    }
+
+    /// Reads one cluster. For unknown-size clusters, returns the header of the
+    /// level-1 element that ended it.
+    fn cluster<R: Read + Seek>(
+        &mut self,
+        reader: &mut ElementReader<R>,
+        size: Option<u64>,
+        limit: u64,
+    ) -> Result<Option<(u32, Option<u64>)>, DemuxError> {
+        let end = size.map_or(limit, |size| reader.pos + size);
+        let mut timestamp = 0;
+
+        while reader.pos < end {
+            let Some((id, child_size)) = reader.header()? else {
+                break;
+            };
+            if size.is_none() && LEVEL_ONE.contains(&id) {
+                return Ok(Some((id, child_size)));
+            }
+
+            let child_size = known(child_size, "cluster child")?;
+            match id {
+                CLUSTER_TIMESTAMP => timestamp = read_uint(&reader.read(child_size)?),
+                SIMPLE_BLOCK => {
+                    self.block(reader, child_size, timestamp, None)?;
+                }
+                BLOCK_GROUP => self.block_group(reader, child_size, timestamp)?,
+                _ => reader.skip(child_size)?,
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn block_group<R: Read + Seek>(
+        &mut self,
+        reader: &mut ElementReader<R>,
+        size: u64,
+        timestamp: u64,
+    ) -> Result<(), DemuxError> {
+        let end = reader.pos + size;
+        let mut recorded = false;
+        let mut referenced = false;
+
+        while reader.pos < end {
+            let Some((id, child_size)) = reader.header()? else {
+                break;
+            };
+            let child_size = known(child_size, "block group child")?;
+            match id {
+                BLOCK => recorded = self.block(reader, child_size, timestamp, Some(true))?,
+                REFERENCE_BLOCK => {
+                    referenced = true;
+                    reader.skip(child_size)?;
+                }
+                _ => reader.skip(child_size)?,
+            }
+        }
+
+        // A block referencing other blocks is not a keyframe
+        if recorded && referenced {
+            if let Some(sample) = self.samples.last_mut() {
+                sample.keyframe = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[instrument(skip(path), fields(path = %path.display()))]
+pub fn read_video_samples(path: &Path) -> Result<Vec<Sample>, DemuxError> {
+    let file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let mut reader = ElementReader {
+        inner: BufReader::new(file),
+        pos: 0,
+    };
+
+    match reader.header()? {
+        Some((EBML, Some(size))) => reader.skip(size)?,
+        _ => return Err(DemuxError::Malformed("missing EBML header".to_string())),
+    }
+
+    let segment_end = match reader.header()? {
+        Some((SEGMENT, size)) => size
+            .map_or(file_len, |size| reader.pos + size)
+            .min(file_len),
+        _ => return Err(DemuxError::Malformed("missing Segment".to_string())),
+    };
+
+    let mut demuxer = Demuxer {
+        timestamp_scale: DEFAULT_TIMESTAMP_SCALE,
+        video_track: None,
+        samples: Vec::new(),
+    };
+    let mut pending = None;
+
+    while reader.pos < segment_end {
+        let header = match pending.take() {
+            Some(header) => Some(header),
+            None => reader.header()?,
+        };
+        let Some((id, size)) = header else {
+            break;
+        };
+
+        match id {
+            INFO => {
+                let info = reader.read(known(size, "Info")?)?;
+                for (id, payload) in children(&info)? {
+                    if id == TIMESTAMP_SCALE {
+                        demuxer.timestamp_scale = read_uint(payload);
+                    }
+                }
+            }
+            TRACKS => {
+                let tracks = reader.read(known(size, "Tracks")?)?;
+                demuxer.video_track = find_video_track(&tracks)?;
+                if demuxer.video_track.is_none() {
+                    return Err(DemuxError::Unsupported("no video track".to_string()));
+                }
+            }
+            CLUSTER => pending = demuxer.cluster(&mut reader, size, segment_end)?,
+            // Cues, tags and the like sit after the clusters; nothing more to find
+            CUES | TAGS if demuxer.video_track.is_some() && !demuxer.samples.is_empty() => break,
+            _ => reader.skip(known(size, "top-level element")?)?,
+        }
+    }
+
+    debug!(
+        timestamp_scale = demuxer.timestamp_scale,
+        video_track = ?demuxer.video_track,
+        sample_count = demuxer.samples.len(),
+        "Parsed Matroska segment"
+    );
+
+    if demuxer.video_track.is_none() {
+        return Err(DemuxError::Unsupported("no track list".to_string()));
+    }
+
+    Ok(demuxer.samples)
+}
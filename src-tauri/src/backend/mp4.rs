@@ -0,0 +1,599 @@
+//! ISO base media (MP4/MOV) demuxing from the sample tables.
+//!
+//! Progressive files describe every sample in `moov/trak/mdia/minf/stbl`
+//! (`stsz`/`stz2`, `stts`, `ctts`, `stss`). Fragmented files carry an empty
+//! sample table and describe samples per `moof/traf/trun` instead; both are handled.
+
+use super::{bitrate_data, AnalysisBackend, DemuxError, Sample};
+use crate::analysis::{file_name, AnalysisError};
+use crate::bytes::{ByteReader, Truncated};
+use crate::models::BitrateData;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use tracing::{debug, instrument};
+
+/// Upper bound on `moov`/`moof` boxes we are willing to buffer in memory.
+const MAX_METADATA_BOX: u64 = 256 * 1024 * 1024;
+
+/// Upper bound on sample counts the size of their box doesn't limit: a `trun` whose
+/// samples all take default values, or an `stsz` with one size for every sample.
+const MAX_IMPLICIT_SAMPLES: usize = 1 << 20;
+
+/// `sample_is_non_sync_sample` in ISO/IEC 14496-12 sample flags.
+const NON_SYNC_SAMPLE: u32 = 0x0001_0000;
+
+impl From<Truncated> for DemuxError {
+    fn from(_: Truncated) -> Self {
+        DemuxError::Malformed("box is truncated".to_string())
+    }
+}
+
+pub struct Mp4Backend;
+
+impl AnalysisBackend for Mp4Backend {
+    fn name(&self) -> &'static str {
+        "mp4"
+    }
+
+    fn supports(&self, header: &[u8]) -> bool {
+        matches!(
+            header.get(4..8),
+            Some(b"ftyp" | b"moov" | b"mdat" | b"wide" | b"free" | b"skip")
+        )
+    }
+
+    fn extract_bitrate_data(&self, path: &Path) -> Result<BitrateData, AnalysisError> {
+        let samples = read_video_samples(path)?;
+        bitrate_data(file_name(path), samples)
+    }
+}
+
+/// Child boxes as `(type, payload)` pairs.
+type Boxes<'a> = Vec<([u8; 4], &'a [u8])>;
+
+/// Splits a slice into its child boxes.
+fn child_boxes(data: &[u8]) -> Result<Boxes<'_>, DemuxError> {
+    let mut reader = ByteReader::new(data);
+    let mut boxes = Vec::new();
+
+    // Some writers pad containers with a zero terminator
+    while reader.remaining() >= 8 {
+        let size = u64::from(reader.u32_be()?);
+        let kind = reader.array::<4>()?;
+        let payload_len = match size {
+            0 => reader.remaining() as u64,
+            1 => reader
+                .u64_be()?
+                .checked_sub(16)
+                .ok_or_else(|| DemuxError::Malformed("invalid box size".to_string()))?,
+            size => size
+                .checked_sub(8)
+                .ok_or_else(|| DemuxError::Malformed("invalid box size".to_string()))?,
+        };
+
+        let payload = reader.bytes(usize::try_from(payload_len).map_err(|_| Truncated)?)?;
+        boxes.push((kind, payload));
+    }
+
+    Ok(boxes)
+}
+
+fn find_box<'a>(data: &'a [u8], kind: &[u8; 4]) -> Result<Option<&'a [u8]>, DemuxError> {
+    Ok(child_boxes(data)?
+        .into_iter()
+        .find(|(found, _)| found == kind)
+        .map(|(_, payload)| payload))
+}
+
+/// Follows a path of nested boxes, e.g. `[b"mdia", b"minf", b"stbl"]`.
+fn find_path<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Result<Option<&'a [u8]>, DemuxError> {
+    let mut current = data;
+    for kind in path {
+        match find_box(current, kind)? {
+            Some(payload) => current = payload,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(current))
+}
+
+/// Reads a full box header, returning `(version, flags)`.
+fn full_box(reader: &mut ByteReader) -> Result<(u8, u32), Truncated> {
+    let version = reader.u8()?;
+    let flags = reader.u24_be()?;
+    Ok((version, flags))
+}
+
+/// Top-level boxes we care about, buffered from the file.
+struct TopLevel {
+    file_len: u64,
+    moov: Option<Vec<u8>>,
+    moofs: Vec<Vec<u8>>,
+}
+
+/// Walks top-level boxes, buffering `moov` and every `moof` while seeking over media data.
+fn read_top_level(path: &Path) -> Result<TopLevel, DemuxError> {
+    let mut file = BufReader::new(File::open(path)?);
+    let file_len = file.get_ref().metadata()?.len();
+    let mut top = TopLevel {
+        file_len,
+        moov: None,
+        moofs: Vec::new(),
+    };
+
+    let mut offset = 0;
+    while offset + 8 <= file_len {
+        file.seek(SeekFrom::Start(offset))?;
+        let mut header = [0; 8];
+        file.read_exact(&mut header)?;
+
+        let size = u64::from(u32::from_be_bytes(header[..4].try_into().expect("4 bytes")));
+        let kind: [u8; 4] = header[4..].try_into().expect("4 bytes");
+        let (header_len, box_len) = match size {
+            0 => (8, file_len - offset),
+            1 => {
+                let mut large = [0; 8];
+                file.read_exact(&mut large)?;
+                (16, u64::from_be_bytes(large))
+            }
+            size => (8, size),
+        };
+
+        if box_len < header_len || box_len > file_len - offset {
+            return Err(DemuxError::Malformed(format!(
+                "box {} at offset {offset} has invalid size {box_len}",
+                String::from_utf8_lossy(&kind)
+            )));
+        }
+
+        if matches!(&kind, b"moov" | b"moof") {
+            let payload_len = box_len - header_len;
+            if payload_len > MAX_METADATA_BOX {
+                return Err(DemuxError::Unsupported(format!(
+                    "{} box of {payload_len} bytes is too large",
+                    String::from_utf8_lossy(&kind)
+                )));
+            }
+
+            let mut payload = vec![0; payload_len as usize];
+            file.read_exact(&mut payload)
+                .map_err(|err| match err.kind() {
+                    io::ErrorKind::UnexpectedEof => {
+                        DemuxError::Malformed("file is truncated".to_string())
+                    }
+                    _ => DemuxError::Io(err),
+                })?;
+
+            if &kind == b"moov" {
+                top.moov = Some(payload);
+            } else {
+                top.moofs.push(payload);
+            }
+        }
+
+        offset = offset
+            .checked_add(box_len)
+            .ok_or_else(|| DemuxError::Malformed("box size overflows".to_string()))?;
+    }
+
+    Ok(top)
+}
+
+/// Sample-table state for the chosen video track.
+struct Track {
+    id: u32,
+    timescale: u32,
+    samples: Vec<Sample>,
+}
+
+/// Defaults from `mvex/trex` used when fragments omit per-sample values.
+#[derive(Default, Clone, Copy)]
+struct TrackDefaults {
+    duration: u32,
+    size: u32,
+    flags: u32,
+}
+
+#[instrument(skip(path), fields(path = %path.display()))]
+pub fn read_video_samples(path: &Path) -> Result<Vec<Sample>, DemuxError> {
+    let top = read_top_level(path)?;
+    let moov = top
+        .moov
+        .ok_or_else(|| DemuxError::Malformed("no moov box".to_string()))?;
+
+    let mut track = find_video_track(&moov, top.file_len)?
+        .ok_or_else(|| DemuxError::Unsupported("no video track".to_string()))?;
+    debug!(
+        track_id = track.id,
+        timescale = track.timescale,
+        table_samples = track.samples.len(),
+        fragments = top.moofs.len(),
+        "Parsed moov"
+    );
+
+    if !top.moofs.is_empty() {
+        let defaults = track_defaults(&moov, track.id)?;
+        let mut decode_time = 0;
+        for moof in &top.moofs {
+            read_fragment(moof, &mut track, defaults, &mut decode_time, top.file_len)?;
+        }
+    }
+
+    Ok(track.samples)
+}
+
+fn find_video_track(moov: &[u8], file_len: u64) -> Result<Option<Track>, DemuxError> {
+    for (kind, trak) in child_boxes(moov)? {
+        if &kind != b"trak" {
+            continue;
+        }
+
+        let Some(hdlr) = find_path(trak, &[b"mdia", b"hdlr"])? else {
+            continue;
+        };
+        let mut reader = ByteReader::new(hdlr);
+        full_box(&mut reader)?;
+        reader.skip(4)?; // pre_defined
+        if &reader.array::<4>()? != b"vide" {
+            continue;
+        }
+
+        let id = match find_box(trak, b"tkhd")? {
+            Some(tkhd) => {
+                let mut reader = ByteReader::new(tkhd);
+                let (version, _) = full_box(&mut reader)?;
+                reader.skip(if version == 1 { 16 } else { 8 })?;
+                reader.u32_be()?
+            }
+            None => return Err(DemuxError::Malformed("track without tkhd".to_string())),
+        };
+
+        let mdhd = find_path(trak, &[b"mdia", b"mdhd"])?
+            .ok_or_else(|| DemuxError::Malformed("track without mdhd".to_string()))?;
+        let mut reader = ByteReader::new(mdhd);
+        let (version, _) = full_box(&mut reader)?;
+        reader.skip(if version == 1 { 16 } else { 8 })?;
+        let timescale = reader.u32_be()?;
+        if timescale == 0 {
+            return Err(DemuxError::Malformed("zero timescale".to_string()));
+        }
+
+        let samples = match find_path(trak, &[b"mdia", b"minf", b"stbl"])? {
+            Some(stbl) => read_sample_table(stbl, timescale, file_len)?,
+            None => Vec::new(),
+        };
+
+        return Ok(Some(Track {
+            id,
+            timescale,
+            samples,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Reads sample sizes, rejecting tables that describe more data than `file_len`.
+fn read_sample_sizes(stbl: &[u8], file_len: u64) -> Result<Vec<u64>, DemuxError> {
+    if let Some(stsz) = find_box(stbl, b"stsz")? {
+        let mut reader = ByteReader::new(stsz);
+        full_box(&mut reader)?;
+        let sample_size = reader.u32_be()?;
+        let count = reader.u32_be()? as usize;
+
+        if sample_size != 0 {
+            if count > MAX_IMPLICIT_SAMPLES || count as u64 > file_len / u64::from(sample_size) {
+                return Err(DemuxError::Malformed(format!(
+                    "stsz describes {count} samples of {sample_size} bytes"
+                )));
+            }
+            return Ok(vec![u64::from(sample_size); count]);
+        }
+        if count > reader.remaining() / 4 {
+            return Err(Truncated.into());
+        }
+        return (0..count)
+            .map(|_| Ok(u64::from(reader.u32_be()?)))
+            .collect();
+    }
+
+    if let Some(stz2) = find_box(stbl, b"stz2")? {
+        let mut reader = ByteReader::new(stz2);
+        full_box(&mut reader)?;
+        reader.skip(3)?; // reserved
+        let field_size = reader.u8()?;
+        let count = reader.u32_be()? as usize;
+
+        return match field_size {
+            4 => {
+                let packed = reader.bytes(count.div_ceil(2))?;
+                Ok((0..count)
+                    .map(|index| {
+                        let byte = packed[index / 2];
+                        u64::from(if index % 2 == 0 {
+                            byte >> 4
+                        } else {
+                            byte & 0x0F
+                        })
+                    })
+                    .collect())
+            }
+            8 => Ok(reader
+                .bytes(count)?
+                .iter()
+                .map(|&size| u64::from(size))
+                .collect()),
+            16 => (0..count)
+                .map(|_| Ok(u64::from(reader.u16_be()?)))
+                .collect(),
+            other => Err(DemuxError::Malformed(format!(
+                "invalid stz2 field size {other}"
+            ))),
+        };
+    }
+
+    Ok(Vec::new())
+}
+
+/// Expands a run-length table of `(count, value)` entries.
+fn read_runs(data: &[u8], total: usize) -> Result<Vec<(u32, u32)>, DemuxError> {
+    let mut reader = ByteReader::new(data);
+    full_box(&mut reader)?;
+    let entry_count = reader.u32_be()? as usize;
+    if entry_count > reader.remaining() / 8 {
+        return Err(Truncated.into());
+    }
+
+    let mut runs = Vec::with_capacity(entry_count);
+    let mut covered = 0usize;
+    for _ in 0..entry_count {
+        let count = reader.u32_be()?;
+        let value = reader.u32_be()?;
+        covered = covered.saturating_add(count as usize);
+        runs.push((count, value));
+        if covered >= total {
+            break;
+        }
+    }
+    Ok(runs)
+}
+
+fn read_sample_table(
+    stbl: &[u8],
+    timescale: u32,
+    file_len: u64,
+) -> Result<Vec<Sample>, DemuxError> {
+    let sizes = read_sample_sizes(stbl, file_len)?;
+    let count = sizes.len();
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    // Decode timestamps from sample durations
+    let mut decode_times = Vec::with_capacity(count);
+    if let Some(stts) = find_box(stbl, b"stts")? {
+        let mut time = 0u64;
+        for (run, delta) in read_runs(stts, count)? {
+            for _ in 0..run {
+                if decode_times.len() == count {
+                    break;
+                }
+                decode_times.push(time);
+                time = time.saturating_add(u64::from(delta));
+            }
+        }
+    }
+
+    // Composition offsets; version 1 tables are signed, and version 0 writers
+    // routinely store negative offsets too, so always interpret as signed
+    let mut offsets = Vec::new();
+    if let Some(ctts) = find_box(stbl, b"ctts")? {
+        for (run, offset) in read_runs(ctts, count)? {
+            // The last run may claim more samples than the table has
+            let run = (run as usize).min(count - offsets.len());
+            offsets.extend(std::iter::repeat_n(offset as i32, run));
+        }
+    }
+
+    // Without a sync sample table every sample is a sync sample
+    let sync_samples = match find_box(stbl, b"stss")? {
+        Some(stss) => {
+            let mut reader = ByteReader::new(stss);
+            full_box(&mut reader)?;
+            let entry_count = reader.u32_be()? as usize;
+            if entry_count > reader.remaining() / 4 {
+                return Err(Truncated.into());
+            }
+            let mut set = HashSet::with_capacity(entry_count);
+            for _ in 0..entry_count {
+                set.insert(reader.u32_be()?);
+            }
+            Some(set)
+        }
+        None => None,
+    };
+
+    Ok(sizes
+        .into_iter()
+        .enumerate()
+        .map(|(index, size)| {
+            let pts = decode_times.get(index).map(|&dts| {
+                let offset = offsets.get(index).copied().unwrap_or(0);
+                (dts as i64 + i64::from(offset)) as f64 / f64::from(timescale)
+            });
+            let keyframe = sync_samples
+                .as_ref()
+                .is_none_or(|set| set.contains(&(index as u32 + 1)));
+            Sample {
+                size,
+                pts,
+                keyframe,
+            }
+        })
+        .collect())
+}
+
+fn track_defaults(moov: &[u8], track_id: u32) -> Result<TrackDefaults, DemuxError> {
+    let Some(mvex) = find_box(moov, b"mvex")? else {
+        return Ok(TrackDefaults::default());
+    };
+
+    for (kind, trex) in child_boxes(mvex)? {
+        if &kind != b"trex" {
+            continue;
+        }
+        let mut reader = ByteReader::new(trex);
+        full_box(&mut reader)?;
+        if reader.u32_be()? != track_id {
+            continue;
+        }
+        reader.skip(4)?; // default_sample_description_index
+        return Ok(TrackDefaults {
+            duration: reader.u32_be()?,
+            size: reader.u32_be()?,
+            flags: reader.u32_be()?,
+        });
+    }
+
+    Ok(TrackDefaults::default())
+}
+
+/// Appends the samples a `moof` describes for `track`, advancing the running decode time.
+fn read_fragment(
+    moof: &[u8],
+    track: &mut Track,
+    defaults: TrackDefaults,
+    decode_time: &mut u64,
+    file_len: u64,
+) -> Result<(), DemuxError> {
+    for (kind, traf) in child_boxes(moof)? {
+        if &kind != b"traf" {
+            continue;
+        }
+
+        let tfhd = find_box(traf, b"tfhd")?
+            .ok_or_else(|| DemuxError::Malformed("traf without tfhd".to_string()))?;
+        let mut reader = ByteReader::new(tfhd);
+        let (_, flags) = full_box(&mut reader)?;
+        if reader.u32_be()? != track.id {
+            continue;
+        }
+
+        let mut fragment_defaults = defaults;
+        if flags & 0x01 != 0 {
+            reader.skip(8)?; // base_data_offset
+        }
+        if flags & 0x02 != 0 {
+            reader.skip(4)?; // sample_description_index
+        }
+        if flags & 0x08 != 0 {
+            fragment_defaults.duration = reader.u32_be()?;
+        }
+        if flags & 0x10 != 0 {
+            fragment_defaults.size = reader.u32_be()?;
+        }
+        if flags & 0x20 != 0 {
+            fragment_defaults.flags = reader.u32_be()?;
+        }
+
+        if let Some(tfdt) = find_box(traf, b"tfdt")? {
+            let mut reader = ByteReader::new(tfdt);
+            let (version, _) = full_box(&mut reader)?;
+            *decode_time = if version == 1 {
+                reader.u64_be()?
+            } else {
+                u64::from(reader.u32_be()?)
+            };
+        }
+
+        for (kind, trun) in child_boxes(traf)? {
+            if &kind == b"trun" {
+                read_track_run(trun, track, fragment_defaults, decode_time, file_len)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_track_run(
+    trun: &[u8],
+    track: &mut Track,
+    defaults: TrackDefaults,
+    decode_time: &mut u64,
+    file_len: u64,
+) -> Result<(), DemuxError> {
+    let mut reader = ByteReader::new(trun);
+    let (version, flags) = full_box(&mut reader)?;
+    let sample_count = reader.u32_be()? as usize;
+
+    if flags & 0x001 != 0 {
+        reader.skip(4)?; // data_offset
+    }
+    let first_sample_flags = if flags & 0x004 != 0 {
+        Some(reader.u32_be()?)
+    } else {
+        None
+    };
+
+    let per_sample = [0x100, 0x200, 0x400, 0x800]
+        .iter()
+        .filter(|&&bit| flags & bit != 0)
+        .count();
+    if per_sample > 0 {
+        if sample_count > reader.remaining() / (per_sample * 4) {
+            return Err(Truncated.into());
+        }
+    } else if sample_count > MAX_IMPLICIT_SAMPLES
+        || sample_count as u64 > file_len / u64::from(defaults.size.max(1))
+    {
+        // Nothing in the box bounds the count, so check it against the media it claims
+        return Err(DemuxError::Malformed(format!(
+            "trun describes {sample_count} samples of {} bytes",
+            defaults.size
+        )));
+    }
+
+    track.samples.reserve(sample_count);
+    for index in 0..sample_count {
+        let duration = if flags & 0x100 != 0 {
+            reader.u32_be()?
+        } else {
+            defaults.duration
+        };
+        let size = if flags & 0x200 != 0 {
+            reader.u32_be()?
+        } else {
+            defaults.size
+        };
+        let mut sample_flags = if flags & 0x400 != 0 {
+            reader.u32_be()?
+        } else {
+            defaults.flags
+        };
+        if index == 0 {
+            sample_flags = first_sample_flags.unwrap_or(sample_flags);
+        }
+        let offset = if flags & 0x800 != 0 {
+            let raw = reader.u32_be()?;
+            if version == 0 {
+                i64::from(raw)
+            } else {
+                i64::from(raw as i32)
+            }
+        } else {
+            0
+        };
+
+        track.samples.push(Sample {
+            size: u64::from(size),
+            pts: Some((*decode_time as i64 + offset) as f64 / f64::from(track.timescale)),
+            keyframe: sample_flags & NON_SYNC_SAMPLE == 0,
+        });
+        *decode_time = decode_time.saturating_add(u64::from(duration));
+    }
+
+    Ok(())
+}
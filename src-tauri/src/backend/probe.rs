@@ -0,0 +1,119 @@
+use super::{bitrate_data, AnalysisBackend, Sample};
+use crate::analysis::{file_name, AnalysisError};
+use crate::capabilities::FrameSizeSource;
+use crate::ff::Ffprobe;
+use crate::models::BitrateData;
+use std::path::Path;
+use tracing::{debug, error, info, instrument};
+
+/// Extracts frame sizes by having ffprobe walk the first video stream.
+///
+/// Works for anything ffprobe can demux, so it is the fallback for every other backend.
+pub struct FfprobeBackend {
+    ffprobe: Ffprobe,
+}
+
+impl FfprobeBackend {
+    pub fn new(ffprobe: Ffprobe) -> Self {
+        Self { ffprobe }
+    }
+}
+
+/// Field names to request for each size source: size, timestamp and key flag.
+fn entry_fields(source: FrameSizeSource) -> (&'static str, &'static str, &'static str) {
+    match source {
+        FrameSizeSource::Frames => ("pkt_size", "best_effort_timestamp_time", "key_frame"),
+        FrameSizeSource::Packets => ("size", "pts_time", "flags"),
+    }
+}
+
+/// Parses one line of `-of compact=p=0` output, e.g. `key_frame=1|best_effort_timestamp_time=0.04|pkt_size=1234`.
+pub fn parse_compact_line(line: &str, source: FrameSizeSource) -> Option<Sample> {
+    let (size_field, pts_field, key_field) = entry_fields(source);
+    let (mut size, mut pts, mut keyframe) = (None, None, false);
+
+    for pair in line.trim().split('|') {
+        let Some((key, value)) = pair.split_once('=') else {
+            continue;
+        };
+
+        if key == size_field {
+            size = value.parse::<u64>().ok();
+        } else if key == pts_field {
+            pts = value.parse::<f64>().ok();
+        } else if key == key_field {
+            keyframe = match source {
+                FrameSizeSource::Frames => value == "1",
+                FrameSizeSource::Packets => value.starts_with('K'),
+            };
+        }
+    }
+
+    size.map(|size| Sample {
+        size,
+        pts,
+        keyframe,
+    })
+}
+
+impl AnalysisBackend for FfprobeBackend {
+    fn name(&self) -> &'static str {
+        "ffprobe"
+    }
+
+    fn supports(&self, _header: &[u8]) -> bool {
+        true
+    }
+
+    #[instrument(skip(self, path), fields(path = %path.display()))]
+    fn extract_bitrate_data(&self, path: &Path) -> Result<BitrateData, AnalysisError> {
+        let filename = file_name(path);
+
+        // Run ffprobe to get frame packet sizes
+        // -v quiet: suppress ffprobe info
+        // -select_streams v:0: only first video stream
+        // -show_entries frame=... (or packet=... on newer releases): only sizes, timestamps and key flags
+        // -of compact=p=0: one `key=value|...` line per entry, without section names
+        let source = self.ffprobe.capabilities().frame_size_source();
+        let (size_field, pts_field, key_field) = entry_fields(source);
+        let section = match source {
+            FrameSizeSource::Frames => "frame",
+            FrameSizeSource::Packets => "packet",
+        };
+        let entries = format!("{section}={size_field},{pts_field},{key_field}");
+        info!(filename = %filename, entries = %entries, "Running ffprobe to extract frame data");
+
+        let output = self
+            .ffprobe
+            .output(
+                &[
+                    "-v",
+                    "quiet",
+                    "-select_streams",
+                    "v:0",
+                    "-show_entries",
+                    &entries,
+                    "-of",
+                    "compact=p=0",
+                ],
+                path,
+            )
+            .map_err(|err| {
+                error!(error = %err, "ffprobe command failed");
+                AnalysisError::from(err)
+            })?;
+
+        let stdout = String::from_utf8_lossy(&output);
+        debug!(
+            line_count = stdout.lines().count(),
+            "Parsing ffprobe output"
+        );
+
+        let samples = stdout
+            .lines()
+            .filter_map(|line| parse_compact_line(line, source))
+            .collect();
+
+        bitrate_data(filename, samples)
+    }
+}
@@ -0,0 +1,86 @@
+//! Bounds-checked reading of binary structures from byte slices.
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("unexpected end of data")]
+pub struct Truncated;
+
+/// A forward-only cursor over a byte slice.
+#[derive(Debug, Clone)]
+pub struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Everything not yet consumed.
+    pub fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], Truncated> {
+        let end = self.pos.checked_add(len).ok_or(Truncated)?;
+        let bytes = self.data.get(self.pos..end).ok_or(Truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    pub fn skip(&mut self, len: usize) -> Result<(), Truncated> {
+        self.bytes(len).map(|_| ())
+    }
+
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N], Truncated> {
+        Ok(self.bytes(N)?.try_into().expect("length checked"))
+    }
+
+    pub fn u8(&mut self) -> Result<u8, Truncated> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub fn u16_be(&mut self) -> Result<u16, Truncated> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    pub fn u24_be(&mut self) -> Result<u32, Truncated> {
+        let [a, b, c] = self.array()?;
+        Ok(u32::from_be_bytes([0, a, b, c]))
+    }
+
+    pub fn u32_be(&mut self) -> Result<u32, Truncated> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    pub fn u64_be(&mut self) -> Result<u64, Truncated> {
+        Ok(u64::from_be_bytes(self.array()?))
+    }
+
+    pub fn u16_le(&mut self) -> Result<u16, Truncated> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u24_le(&mut self) -> Result<u32, Truncated> {
+        let [a, b, c] = self.array()?;
+        Ok(u32::from_le_bytes([a, b, c, 0]))
+    }
+
+    pub fn u32_le(&mut self) -> Result<u32, Truncated> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+}
@@ -0,0 +1,239 @@
+//! On-disk cache of analysis results.
+//!
+//! Entries are keyed by the file's canonical path, size and modification time plus the
+//! version of the tool that produced them, so any change to the input or the toolchain
+//! simply misses. Least recently used entries are evicted once the size limit is exceeded.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, instrument, warn};
+use ts_rs::TS;
+
+/// Bumped whenever cached model types change shape, invalidating every existing entry.
+const CACHE_FORMAT: u32 = 2;
+const ENTRY_EXTENSION: &str = "json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+#[serde(default)]
+pub struct CacheSettings {
+    pub enabled: bool,
+    /// Total size the cache directory may grow to before old entries are evicted.
+    #[ts(type = "number")]
+    pub max_bytes: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_bytes: 512 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, TS)]
+pub struct CacheStats {
+    pub entries: u32,
+    #[ts(type = "number")]
+    pub bytes: u64,
+}
+
+/// Identity of an input file at a point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    /// Derives a key from the file's identity and the producing tool's version.
+    pub fn for_file(path: &Path, tool_version: &str) -> io::Result<Self> {
+        let canonical = fs::canonicalize(path)?;
+        let metadata = fs::metadata(&canonical)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let mut hasher = Sha256::new();
+        hasher.update(CACHE_FORMAT.to_le_bytes());
+        hasher.update(canonical.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(metadata.len().to_le_bytes());
+        hasher.update(modified.as_nanos().to_le_bytes());
+        hasher.update(tool_version.as_bytes());
+
+        let digest = hasher.finalize();
+        Ok(Self(
+            digest.iter().map(|byte| format!("{byte:02x}")).collect(),
+        ))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnalysisCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl AnalysisCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Self { dir, max_bytes }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, key: &CacheKey, kind: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}-{kind}.{ENTRY_EXTENSION}", key.as_str()))
+    }
+
+    /// Reads a cached `kind` result for `key`, discarding entries that no longer deserialize.
+    pub fn get<T: DeserializeOwned>(&self, key: &CacheKey, kind: &str) -> Option<T> {
+        let path = self.entry_path(key, kind);
+        let contents = fs::read(&path).ok()?;
+
+        match serde_json::from_slice(&contents) {
+            Ok(value) => {
+                // Refresh the timestamp so eviction sees this entry as recently used
+                if let Ok(file) = fs::File::options().write(true).open(&path) {
+                    let _ = file.set_modified(SystemTime::now());
+                }
+                Some(value)
+            }
+            Err(err) => {
+                warn!(path = %path.display(), error = %err, "Discarding unreadable cache entry");
+                let _ = fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    /// Stores a `kind` result for `key`, then evicts old entries if over the size limit.
+    pub fn put<T: Serialize>(&self, key: &CacheKey, kind: &str, value: &T) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let path = self.entry_path(key, kind);
+        let contents = serde_json::to_vec(value).map_err(io::Error::other)?;
+
+        // Write then rename so a crash never leaves a truncated entry behind
+        let partial = path.with_extension("partial");
+        fs::write(&partial, contents)?;
+        fs::rename(&partial, &path)?;
+
+        self.evict()
+    }
+
+    /// Returns the cached result if present, otherwise computes and stores it.
+    ///
+    /// Failures are never cached, and cache I/O errors only ever cost a recomputation.
+    #[instrument(skip(self, path, tool_version, compute), fields(path = %path.display()))]
+    pub fn get_or_compute<T, E>(
+        &self,
+        path: &Path,
+        tool_version: &str,
+        kind: &str,
+        compute: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let key = match CacheKey::for_file(path, tool_version) {
+            Ok(key) => key,
+            Err(err) => {
+                debug!(error = %err, "Cannot derive cache key, computing directly");
+                return compute();
+            }
+        };
+
+        if let Some(value) = self.get(&key, kind) {
+            info!(kind = %kind, "Cache hit");
+            return Ok(value);
+        }
+
+        debug!(kind = %kind, "Cache miss");
+        let value = compute()?;
+        if let Err(err) = self.put(&key, kind, &value) {
+            warn!(error = %err, "Failed to write cache entry");
+        }
+        Ok(value)
+    }
+
+    fn entries(&self) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let mut entries = Vec::new();
+        for entry in read_dir {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(ENTRY_EXTENSION) {
+                continue;
+            }
+
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                entries.push((path, metadata.len(), metadata.modified()?));
+            }
+        }
+        Ok(entries)
+    }
+
+    pub fn stats(&self) -> io::Result<CacheStats> {
+        let entries = self.entries()?;
+        Ok(CacheStats {
+            entries: entries.len() as u32,
+            bytes: entries.iter().map(|(_, size, _)| size).sum(),
+        })
+    }
+
+    /// Removes least recently used entries until the cache fits within its size limit.
+    fn evict(&self) -> io::Result<()> {
+        let mut entries = self.entries()?;
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        if total <= self.max_bytes {
+            return Ok(());
+        }
+
+        entries.sort_by_key(|(_, _, modified)| *modified);
+        for (path, size, _) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            fs::remove_file(&path)?;
+            total -= size;
+            debug!(path = %path.display(), size = size, "Evicted cache entry");
+        }
+        Ok(())
+    }
+
+    /// Deletes every entry, returning what was removed.
+    pub fn clear(&self) -> io::Result<CacheStats> {
+        let entries = self.entries()?;
+        let mut removed = CacheStats::default();
+
+        for (path, size, _) in entries {
+            fs::remove_file(&path)?;
+            removed.entries += 1;
+            removed.bytes += size;
+        }
+
+        info!(
+            entries = removed.entries,
+            bytes = removed.bytes,
+            "Cleared analysis cache"
+        );
+        Ok(removed)
+    }
+}
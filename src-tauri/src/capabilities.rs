@@ -0,0 +1,230 @@
+//! Version and build feature detection for ffprobe and ffmpeg.
+//!
+//! ffprobe's output has drifted across releases, so we parse `-version` and
+//! `-buildconf` once when the binary is resolved and pick compatible arguments from that.
+
+use crate::process::{self, ProcessLimits};
+use crate::remote::NetworkSettings;
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use tracing::{debug, warn};
+
+/// Oldest ffprobe release whose JSON output our stream model can deserialize.
+pub const MINIMUM_VERSION: FfmpegVersion = FfmpegVersion::Release {
+    major: 4,
+    minor: 0,
+    patch: 0,
+};
+
+/// A parsed FFmpeg suite version.
+///
+/// Development snapshots (`N-112345-g…`, `git-…`, dated nightly builds) sort after every release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FfmpegVersion {
+    Release { major: u32, minor: u32, patch: u32 },
+    Development,
+}
+
+impl FfmpegVersion {
+    pub const fn release(major: u32, minor: u32) -> Self {
+        FfmpegVersion::Release {
+            major,
+            minor,
+            patch: 0,
+        }
+    }
+
+    /// Parses the version token from `-version`, e.g. `6.1.1-3ubuntu5`, `n7.0` or `N-113000-gabc`.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim();
+
+        if version.starts_with("N-") || version.starts_with("git") {
+            return Some(FfmpegVersion::Development);
+        }
+
+        let version = version.strip_prefix('n').unwrap_or(version);
+        let numeric = version
+            .split(['-', '+', '~', '_'])
+            .next()
+            .filter(|numeric| !numeric.is_empty())?;
+
+        let mut parts = numeric.split('.').map(str::parse::<u32>);
+        let major = parts.next()?.ok()?;
+
+        // Nightly builds are commonly versioned by date, e.g. `2024-03-04-git-e30369bc1c`
+        if major >= 1000 {
+            return Some(FfmpegVersion::Development);
+        }
+
+        let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+        let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+
+        Some(FfmpegVersion::Release {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+impl fmt::Display for FfmpegVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FfmpegVersion::Release {
+                major,
+                minor,
+                patch: 0,
+            } => write!(f, "{major}.{minor}"),
+            FfmpegVersion::Release {
+                major,
+                minor,
+                patch,
+            } => write!(f, "{major}.{minor}.{patch}"),
+            FfmpegVersion::Development => f.write_str("development build"),
+        }
+    }
+}
+
+/// Where per-frame sizes are read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameSizeSource {
+    /// `-show_entries frame=pkt_size`, in presentation order.
+    Frames,
+    /// `-show_entries packet=size`, for releases that no longer report `pkt_size` on frames.
+    Packets,
+}
+
+/// What the installed ffprobe supports.
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+    /// `None` when the version string could not be understood; treated as a recent build.
+    pub version: Option<FfmpegVersion>,
+    /// `./configure` flags the binary was built with, e.g. `--enable-libvmaf`.
+    pub configuration: Vec<String>,
+}
+
+impl Capabilities {
+    pub fn new(version: Option<FfmpegVersion>, configuration: Vec<String>) -> Self {
+        Self {
+            version,
+            configuration,
+        }
+    }
+
+    /// Queries `-buildconf` of the binary at `path`, whose `-version` reported `version`.
+    pub fn detect(path: &Path, version: &str) -> Self {
+        let parsed = FfmpegVersion::parse(version);
+        if parsed.is_none() {
+            warn!(version = %version, "Unrecognized ffprobe version, assuming a recent build");
+        }
+
+        let mut command = Command::new(path);
+        command.args(["-hide_banner", "-buildconf"]);
+
+        let configuration = match process::run(command, &ProcessLimits::quick()) {
+            Ok(output) if output.status.success() => {
+                parse_buildconf(&String::from_utf8_lossy(&output.stdout))
+            }
+            Ok(output) => {
+                warn!(status = %output.status, "ffprobe -buildconf failed");
+                Vec::new()
+            }
+            Err(err) => {
+                warn!(error = %err, "Failed to run ffprobe -buildconf");
+                Vec::new()
+            }
+        };
+
+        debug!(
+            version = ?parsed,
+            flag_count = configuration.len(),
+            "Detected ffprobe capabilities"
+        );
+
+        Self::new(parsed, configuration)
+    }
+
+    fn at_least(&self, minimum: FfmpegVersion) -> bool {
+        self.version.is_none_or(|version| version >= minimum)
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.at_least(MINIMUM_VERSION)
+    }
+
+    /// Whether the build was configured with `flag`, e.g. `--enable-libvmaf`.
+    pub fn has_configuration_flag(&self, flag: &str) -> bool {
+        self.configuration.iter().any(|entry| entry == flag)
+    }
+
+    /// FFmpeg 7.0 dropped the deprecated `AVFrame.pkt_size`, so frames no longer report it.
+    pub fn frame_size_source(&self) -> FrameSizeSource {
+        if self.at_least(FfmpegVersion::release(7, 0)) {
+            FrameSizeSource::Packets
+        } else {
+            FrameSizeSource::Frames
+        }
+    }
+
+    /// Sections to request for stream probing.
+    ///
+    /// Starting with 7.0, stream side data is printed in a richer shape than our JSON model
+    /// accepts, and we never use it, so those releases only get the sections we read.
+    pub fn stream_probe_args(&self) -> &'static [&'static str] {
+        if self.at_least(FfmpegVersion::release(7, 0)) {
+            &[
+                "-show_entries",
+                "format:format_tags:stream:stream_tags:stream_disposition",
+            ]
+        } else {
+            &["-show_format", "-show_streams"]
+        }
+    }
+
+    /// Output options that pass every decoded frame through with its own timestamp.
+    ///
+    /// `-fps_mode` replaced `-vsync` in 5.1; older releases only know the latter.
+    pub fn passthrough_args(&self) -> [&'static str; 2] {
+        if self.at_least(FfmpegVersion::release(5, 1)) {
+            ["-fps_mode", "passthrough"]
+        } else {
+            ["-vsync", "passthrough"]
+        }
+    }
+
+    /// Input options for reading over HTTP(S).
+    ///
+    /// `-rw_timeout` is in microseconds. `-reconnect_on_network_error` only exists since
+    /// 4.4; older releases reject unknown options, so it is left out for them.
+    pub fn network_args(&self, network: &NetworkSettings) -> Vec<String> {
+        let timeout = u64::from(network.timeout_secs.max(1)) * 1_000_000;
+        let mut args = vec!["-rw_timeout".to_string(), timeout.to_string()];
+
+        if network.reconnect {
+            let mut reconnect = vec![
+                "-reconnect",
+                "1",
+                "-reconnect_streamed",
+                "1",
+                "-reconnect_delay_max",
+                "5",
+            ];
+            if self.at_least(FfmpegVersion::release(4, 4)) {
+                reconnect.extend(["-reconnect_on_network_error", "1"]);
+            }
+            args.extend(reconnect.into_iter().map(str::to_string));
+        }
+
+        args
+    }
+}
+
+/// Extracts configure flags from `-buildconf` output.
+pub fn parse_buildconf(output: &str) -> Vec<String> {
+    output
+        .split_whitespace()
+        .filter(|word| word.starts_with("--"))
+        .map(str::to_string)
+        .collect()
+}
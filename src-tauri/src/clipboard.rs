@@ -0,0 +1,76 @@
+//! Turning pasted clipboard text into analysis inputs.
+//!
+//! File managers put different things on the clipboard when files are copied: plain
+//! paths (one per line, sometimes quoted), `file://` URIs in `text/uri-list` form, or
+//! a mix. Everything is normalized to plain paths and URLs, so a pasted entry goes
+//! through exactly the same analysis as a dropped one.
+
+use percent_encoding::percent_decode_str;
+use std::path::PathBuf;
+use tracing::{debug, warn};
+use url::Url;
+
+/// Converts a `file://` URI into a local path, decoding percent escapes.
+pub fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    let url = Url::parse(uri).ok().filter(|url| url.scheme() == "file")?;
+    match url.to_file_path() {
+        Ok(path) => Some(path),
+        // `to_file_path` rejects hosts other than `localhost`; keep the path part anyway
+        Err(()) => {
+            let path = percent_decode_str(url.path()).decode_utf8_lossy();
+            Some(PathBuf::from(path.as_ref()))
+        }
+    }
+}
+
+/// Normalizes a single clipboard line, returning `None` for lines that carry no input.
+fn normalize_line(line: &str) -> Option<String> {
+    let line = line.trim();
+    // `text/uri-list` allows comment lines
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    // Windows "Copy as path" wraps each path in double quotes
+    let line = line
+        .strip_prefix('"')
+        .and_then(|line| line.strip_suffix('"'))
+        .or_else(|| {
+            line.strip_prefix('\'')
+                .and_then(|line| line.strip_suffix('\''))
+        })
+        .unwrap_or(line)
+        .trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    let is_file_uri = line
+        .get(..7)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("file://"));
+    if is_file_uri {
+        return match file_uri_to_path(line) {
+            Some(path) => Some(path.to_string_lossy().into_owned()),
+            None => {
+                warn!(uri = %line, "Could not convert file URI to a path");
+                Some(line.to_string())
+            }
+        };
+    }
+
+    // Paths and `http(s)://` URLs are passed through as-is
+    Some(line.to_string())
+}
+
+/// Splits clipboard text into paths and URLs, dropping blank lines and duplicates.
+pub fn parse_clipboard_text(text: &str) -> Vec<String> {
+    let mut inputs: Vec<String> = Vec::new();
+    for input in text.lines().filter_map(normalize_line) {
+        if !inputs.contains(&input) {
+            inputs.push(input);
+        }
+    }
+
+    debug!(input_count = inputs.len(), "Parsed clipboard text");
+    inputs
+}
@@ -0,0 +1,119 @@
+//! Resolution- and frame-rate-independent compression metrics.
+//!
+//! Raw bitrate can't compare a 720p30 encode with a 2160p60 one. Dividing frame sizes
+//! from [`BitrateData`] by the pixel count of the video stream gives bits per pixel,
+//! and scaling by the frame rate gives bits per second per megapixel.
+
+use crate::analysis::AnalysisError;
+use crate::models::{BitrateData, EfficiencyAnalysis, EfficiencyFrame, StreamDetail, StreamResult};
+use tracing::{debug, info};
+
+/// Parses an ffprobe frame rate such as `30000/1001` or `25`, rejecting `0/0`.
+pub fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let value = match rate.split_once('/') {
+        Some((num, den)) => num.trim().parse::<f64>().ok()? / den.trim().parse::<f64>().ok()?,
+        None => rate.trim().parse().ok()?,
+    };
+    (value.is_finite() && value > 0.0).then_some(value)
+}
+
+/// Frames per second, preferring the stream header, then frame timestamps, then the
+/// container duration.
+fn frame_rate(header: Option<&str>, bitrate: &BitrateData, duration: Option<f64>) -> Option<f64> {
+    let count = bitrate.frames.len() as f64;
+    let timestamps = || {
+        let first = bitrate.frames.iter().find_map(|frame| frame.timestamp)?;
+        let last = bitrate
+            .frames
+            .iter()
+            .rev()
+            .find_map(|frame| frame.timestamp)?;
+        Some((count - 1.0) / (last - first))
+    };
+    header
+        .and_then(parse_frame_rate)
+        .or_else(|| timestamps().filter(|rate| rate.is_finite() && *rate > 0.0))
+        .or_else(|| duration.map(|duration| count / duration))
+        .filter(|rate| rate.is_finite() && *rate > 0.0)
+}
+
+/// Derives per-frame and overall efficiency from the first video stream's dimensions.
+pub fn compute(
+    streams: &StreamResult,
+    bitrate: &BitrateData,
+) -> Result<EfficiencyAnalysis, AnalysisError> {
+    let (width, height, header_rate) = streams
+        .streams
+        .iter()
+        .find_map(|stream| match stream {
+            StreamDetail::Video {
+                width: Some(width),
+                height: Some(height),
+                frame_rate,
+                ..
+            } if *width > 0 && *height > 0 => Some((*width, *height, frame_rate.as_deref())),
+            _ => None,
+        })
+        .ok_or(AnalysisError::NoVideoStream)?;
+    if bitrate.frames.is_empty() {
+        return Err(AnalysisError::NoFrames);
+    }
+
+    let pixels = f64::from(width) * f64::from(height);
+    let megapixels = pixels / 1_000_000.0;
+    let fps = frame_rate(header_rate, bitrate, streams.duration);
+    debug!(width, height, fps = ?fps, "Computing compression efficiency");
+
+    let frames: Vec<EfficiencyFrame> = bitrate
+        .frames
+        .iter()
+        .map(|frame| {
+            let bits = frame.packet_size as f64 * 8.0;
+            EfficiencyFrame {
+                frame_num: frame.frame_num,
+                timestamp: frame.timestamp,
+                bits_per_pixel: bits / pixels,
+                bits_per_second_per_megapixel: fps.map(|fps| bits * fps / megapixels),
+            }
+        })
+        .collect();
+
+    let count = frames.len() as f64;
+    let total_bits: f64 = bitrate
+        .frames
+        .iter()
+        .map(|frame| frame.packet_size as f64 * 8.0)
+        .sum();
+    let keyframes: Vec<f64> = bitrate
+        .frames
+        .iter()
+        .zip(&frames)
+        .filter(|(frame, _)| frame.keyframe)
+        .map(|(_, frame)| frame.bits_per_pixel)
+        .collect();
+    let bitrate_bps = fps.map(|fps| total_bits * fps / count);
+
+    let analysis = EfficiencyAnalysis {
+        id: bitrate.id.clone(),
+        width,
+        height,
+        frame_rate: fps,
+        bits_per_pixel: total_bits / (pixels * count),
+        max_bits_per_pixel: frames
+            .iter()
+            .map(|frame| frame.bits_per_pixel)
+            .fold(0.0, f64::max),
+        keyframe_bits_per_pixel: (!keyframes.is_empty())
+            .then(|| keyframes.iter().sum::<f64>() / keyframes.len() as f64),
+        bitrate: bitrate_bps,
+        bits_per_second_per_megapixel: bitrate_bps.map(|bps| bps / megapixels),
+        frames,
+    };
+
+    info!(
+        bits_per_pixel = analysis.bits_per_pixel,
+        bitrate = ?analysis.bitrate,
+        "Compression efficiency computed"
+    );
+    Ok(analysis)
+}
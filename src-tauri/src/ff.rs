@@ -1,5 +1,426 @@
-use crate::models::StreamDetail;
-use tracing::{debug, info, instrument};
+use crate::capabilities::{Capabilities, FfmpegVersion, MINIMUM_VERSION};
+use crate::models::{MediaType, StreamDetail};
+use crate::process::{self, ProcessError, ProcessLimits};
+use crate::remote::NetworkSettings;
+use crate::tools::{self, ResolvedTool, Tool, ToolError};
+use serde::Deserialize;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::Command;
+use thiserror::Error;
+use tracing::{debug, error, info, instrument};
+
+#[derive(Debug, Error)]
+pub enum FfprobeError {
+    #[error(transparent)]
+    Process(#[from] ProcessError),
+    #[error("ffprobe failed: {0}")]
+    Failed(String),
+    #[error("Could not parse ffprobe output: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum FfmpegError {
+    #[error(transparent)]
+    Process(#[from] ProcessError),
+    #[error("ffmpeg failed: {0}")]
+    Failed(String),
+}
+
+/// Container and stream types from a quick format probe.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FormatProbe {
+    #[serde(default)]
+    pub streams: Vec<ProbedStream>,
+    pub format: Option<ProbedFormat>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProbedStream {
+    pub codec_type: Option<String>,
+    pub codec_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProbedFormat {
+    pub format_name: Option<String>,
+    pub duration: Option<String>,
+}
+
+impl FormatProbe {
+    pub fn format_name(&self) -> Option<&str> {
+        self.format.as_ref()?.format_name.as_deref()
+    }
+
+    /// Container duration in seconds, when ffprobe could determine one.
+    pub fn duration(&self) -> Option<f64> {
+        self.format.as_ref()?.duration.as_deref()?.parse().ok()
+    }
+
+    pub fn has_stream(&self, codec_type: &str) -> bool {
+        self.streams
+            .iter()
+            .any(|stream| stream.codec_type.as_deref() == Some(codec_type))
+    }
+
+    /// Classifies the probed input by its streams, or `None` if it has no audio or video.
+    pub fn media_type(&self) -> Option<MediaType> {
+        // Still images are demuxed as a single video stream by the image readers
+        let is_image_reader = self
+            .format_name()
+            .is_some_and(|name| name == "image2" || name.ends_with("_pipe"));
+
+        if self.has_stream("video") {
+            Some(if is_image_reader {
+                MediaType::Image
+            } else {
+                MediaType::Video
+            })
+        } else if self.has_stream("audio") {
+            Some(MediaType::Audio)
+        } else if self.has_stream("subtitle") {
+            Some(MediaType::Subtitle)
+        } else {
+            None
+        }
+    }
+}
+
+/// A located and validated ffprobe binary, along with what it supports.
+#[derive(Debug, Clone)]
+pub struct Ffprobe {
+    tool: ResolvedTool,
+    capabilities: Capabilities,
+    limits: ProcessLimits,
+}
+
+impl Ffprobe {
+    pub fn new(tool: ResolvedTool, capabilities: Capabilities) -> Self {
+        Self {
+            tool,
+            capabilities,
+            limits: ProcessLimits::default(),
+        }
+    }
+
+    /// Applies the timeout and output caps used for every subsequent invocation.
+    pub fn with_limits(mut self, limits: ProcessLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Discovers ffprobe, preferring `configured` when it points at a working binary,
+    /// and rejects releases too old for us to parse.
+    pub fn resolve(configured: Option<&Path>) -> Result<Self, ToolError> {
+        let tool = tools::resolve(Tool::Ffprobe, configured)?;
+        let capabilities = Capabilities::detect(&tool.path, &tool.version);
+
+        if !capabilities.is_supported() {
+            error!(version = %tool.version, minimum = %MINIMUM_VERSION, "ffprobe is too old");
+            return Err(ToolError::Unsupported {
+                tool: Tool::Ffprobe,
+                version: tool.version,
+                minimum: MINIMUM_VERSION.to_string(),
+            });
+        }
+
+        Ok(Self::new(tool, capabilities))
+    }
+
+    pub fn tool(&self) -> &ResolvedTool {
+        &self.tool
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.tool.path
+    }
+
+    pub fn version(&self) -> &str {
+        &self.tool.version
+    }
+
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    pub fn limits(&self) -> &ProcessLimits {
+        &self.limits
+    }
+
+    /// Starts a new ffprobe invocation with no arguments.
+    pub fn command(&self) -> Command {
+        Command::new(&self.tool.path)
+    }
+
+    /// Runs ffprobe with `args` followed by `input` (a path or URL), returning stdout on success.
+    pub fn output(
+        &self,
+        args: &[&str],
+        input: impl AsRef<OsStr>,
+    ) -> Result<Vec<u8>, FfprobeError> {
+        let mut command = self.command();
+        command.args(args).arg(input);
+        let output = process::run(command, &self.limits)?;
+
+        if !output.status.success() {
+            return Err(FfprobeError::Failed(output.stderr.trim().to_string()));
+        }
+
+        Ok(output.stdout)
+    }
+
+    /// Runs a JSON format and stream probe of `path`.
+    pub fn probe(&self, path: &Path) -> Result<ffprobe::FfProbe, FfprobeError> {
+        self.probe_input(&[], path)
+    }
+
+    /// Runs a JSON format and stream probe of an `http(s)://` URL, with reconnects and
+    /// I/O timeouts from `network`.
+    pub fn probe_url(
+        &self,
+        url: &str,
+        network: &NetworkSettings,
+    ) -> Result<ffprobe::FfProbe, FfprobeError> {
+        let network_args = self.capabilities.network_args(network);
+        let network_args: Vec<&str> = network_args.iter().map(String::as_str).collect();
+        self.probe_input(&network_args, url)
+    }
+
+    /// Probes only the container format, duration and stream types of `path`, reading as
+    /// little of it as ffprobe allows. Used to classify files other detection doesn't
+    /// recognize.
+    pub fn probe_format(&self, path: &Path) -> Result<FormatProbe, FfprobeError> {
+        let args = [
+            "-v",
+            "error",
+            "-probesize",
+            "1048576",
+            "-print_format",
+            "json",
+            "-show_entries",
+            "format=format_name,duration:stream=codec_type,codec_name",
+        ];
+        let stdout = self.output(&args, path)?;
+        Ok(serde_json::from_slice(&stdout)?)
+    }
+
+    fn probe_input(
+        &self,
+        input_args: &[&str],
+        input: impl AsRef<OsStr>,
+    ) -> Result<ffprobe::FfProbe, FfprobeError> {
+        let mut args = vec!["-v", "error"];
+        args.extend_from_slice(input_args);
+        args.extend_from_slice(&["-print_format", "json"]);
+        args.extend_from_slice(self.capabilities.stream_probe_args());
+
+        let stdout = self.output(&args, input)?;
+        Ok(serde_json::from_slice(&stdout)?)
+    }
+}
+
+/// Suffix for a `metadata`/`ametadata` filter that prints every frame's metadata to stdout.
+pub const PRINT_METADATA: &str = "mode=print:file=pipe\\:1";
+
+/// One frame of `metadata`/`ametadata` output in print mode.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameMetadata {
+    /// Index of the frame in the filter's output.
+    pub frame: u64,
+    pub pts_time: Option<f64>,
+    pub entries: Vec<(String, String)>,
+}
+
+impl FrameMetadata {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The value of `key` as a finite number.
+    pub fn number(&self, key: &str) -> Option<f64> {
+        parse_finite(self.get(key)?)
+    }
+}
+
+fn parse_finite(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+}
+
+/// Escapes `value` for use as a filter option inside a filter graph, which takes two
+/// levels: one for the option list and one for the graph itself.
+pub fn escape_filter_value(value: &str) -> String {
+    let escape = |value: &str, special: &[char]| {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            if special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    let option = escape(value, &['\\', '\'', ':']);
+    escape(&option, &['\\', '\'', '[', ']', ',', ';'])
+}
+
+/// Parses print-mode metadata output, e.g.
+///
+/// ```text
+/// frame:0    pts:0       pts_time:0
+/// lavfi.r128.M=-120.691
+/// lavfi.r128.S=-120.691
+/// ```
+pub fn parse_frame_metadata(output: &str) -> Vec<FrameMetadata> {
+    let mut frames: Vec<FrameMetadata> = Vec::new();
+
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix("frame:") {
+            let mut fields = header.split_whitespace();
+            frames.push(FrameMetadata {
+                frame: fields
+                    .next()
+                    .and_then(|frame| frame.parse().ok())
+                    .unwrap_or(frames.len() as u64),
+                pts_time: fields
+                    .find_map(|field| field.strip_prefix("pts_time:"))
+                    .and_then(parse_finite),
+                entries: Vec::new(),
+            });
+        } else if let (Some(frame), Some((key, value))) = (frames.last_mut(), line.split_once('='))
+        {
+            frame
+                .entries
+                .push((key.to_string(), value.trim().to_string()));
+        }
+    }
+
+    frames
+}
+
+/// A located ffmpeg binary, for analyses that need decoded media and filters.
+///
+/// Unlike ffprobe it is optional: only the analyses built on it fail without one.
+#[derive(Debug, Clone)]
+pub struct Ffmpeg {
+    tool: ResolvedTool,
+    capabilities: Capabilities,
+    limits: ProcessLimits,
+}
+
+impl Ffmpeg {
+    /// Capabilities follow from the version alone; filters are checked with
+    /// [`Ffmpeg::has_filter`] when needed.
+    pub fn new(tool: ResolvedTool) -> Self {
+        let capabilities = Capabilities::new(FfmpegVersion::parse(&tool.version), Vec::new());
+        Self {
+            tool,
+            capabilities,
+            limits: ProcessLimits::decode(),
+        }
+    }
+
+    /// Applies the timeout and output caps used for every subsequent invocation.
+    pub fn with_limits(mut self, limits: ProcessLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Discovers ffmpeg, preferring `configured` when it points at a working binary.
+    pub fn resolve(configured: Option<&Path>) -> Result<Self, ToolError> {
+        Ok(Self::new(tools::resolve(Tool::Ffmpeg, configured)?))
+    }
+
+    pub fn tool(&self) -> &ResolvedTool {
+        &self.tool
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.tool.path
+    }
+
+    pub fn version(&self) -> &str {
+        &self.tool.version
+    }
+
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    pub fn limits(&self) -> &ProcessLimits {
+        &self.limits
+    }
+
+    /// Starts a new non-interactive ffmpeg invocation that only logs errors.
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.tool.path);
+        command.args(["-hide_banner", "-nostdin", "-v", "error"]);
+        command
+    }
+
+    /// Whether the binary was built with the filter `name`, e.g. `libvmaf`.
+    pub fn has_filter(&self, name: &str) -> Result<bool, FfmpegError> {
+        let mut command = self.command();
+        command.arg("-filters");
+        let output = process::run(command, &ProcessLimits::quick())?;
+
+        if !output.status.success() {
+            return Err(FfmpegError::Failed(output.stderr.trim().to_string()));
+        }
+
+        // Lines look like ` ... psnr              VV->V      Calculate the PSNR ...`
+        let found = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|line| line.split_whitespace().nth(1) == Some(name));
+        debug!(filter = %name, found, "Checked ffmpeg filter availability");
+        Ok(found)
+    }
+
+    /// Runs ffmpeg as `<input_args> -i <input> <args>`, returning stdout on success.
+    pub fn output(
+        &self,
+        input_args: &[&str],
+        input: impl AsRef<OsStr>,
+        args: &[impl AsRef<OsStr>],
+    ) -> Result<Vec<u8>, FfmpegError> {
+        let mut command = self.command();
+        command.args(input_args).arg("-i").arg(input).args(args);
+        let output = process::run(command, &self.limits)?;
+
+        if !output.status.success() {
+            return Err(FfmpegError::Failed(output.stderr.trim().to_string()));
+        }
+
+        Ok(output.stdout)
+    }
+
+    /// Like [`Ffmpeg::output`], but hands stdout to `on_stdout` as it arrives instead of
+    /// buffering it, for decoded media too large to hold in memory.
+    pub fn stream(
+        &self,
+        input_args: &[&str],
+        input: impl AsRef<OsStr>,
+        args: &[impl AsRef<OsStr>],
+        on_stdout: impl FnMut(&[u8]) -> Result<(), ProcessError> + Send,
+    ) -> Result<(), FfmpegError> {
+        let mut command = self.command();
+        command.args(input_args).arg("-i").arg(input).args(args);
+        let (status, stderr) = process::stream(command, &self.limits, on_stdout)?;
+
+        if !status.success() {
+            return Err(FfmpegError::Failed(stderr.trim().to_string()));
+        }
+
+        Ok(())
+    }
+}
 
 #[instrument(skip(info), fields(stream_count = info.streams.len()))]
 pub fn extract_streams(info: &ffprobe::FfProbe) -> Vec<StreamDetail> {
@@ -8,7 +429,10 @@ pub fn extract_streams(info: &ffprobe::FfProbe) -> Vec<StreamDetail> {
     let mut audio_count = 0;
     let mut subtitle_count = 0;
 
-    info!(total_streams = info.streams.len(), "Extracting streams from media file");
+    info!(
+        total_streams = info.streams.len(),
+        "Extracting streams from media file"
+    );
 
     for (index, stream) in info.streams.iter().enumerate() {
         match stream.codec_type.as_deref() {
@@ -82,10 +506,7 @@ pub fn extract_streams(info: &ffprobe::FfProbe) -> Vec<StreamDetail> {
                     "Extracted subtitle stream"
                 );
 
-                streams.push(StreamDetail::Subtitle {
-                    codec,
-                    language,
-                });
+                streams.push(StreamDetail::Subtitle { codec, language });
             }
             other => {
                 debug!(
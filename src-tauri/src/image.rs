@@ -0,0 +1,178 @@
+//! Still and animated image analysis.
+//!
+//! ffprobe sees an image as a single video stream, which says little about it. These
+//! parsers walk the file's own structure instead: header fields give dimensions and
+//! pixel format, metadata chunks give ICC and EXIF presence, and for animated
+//! GIF/APNG/WebP every frame's delay and compressed size is recorded.
+
+mod gif;
+mod jpeg;
+mod png;
+mod webp;
+
+use crate::analysis::file_name;
+use crate::bytes::Truncated;
+use crate::models::{Animation, AnimationFrame, ColorType, ExifInfo, ImageAnalysis, ImageFormat};
+use exif::{In, Tag, Value};
+use std::fs;
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+use tracing::{debug, info, instrument, warn};
+
+#[derive(Debug, Error)]
+pub enum ImageError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Malformed image: {0}")]
+    Malformed(String),
+    #[error("Unsupported image format")]
+    Unsupported,
+}
+
+impl From<Truncated> for ImageError {
+    fn from(_: Truncated) -> Self {
+        ImageError::Malformed("unexpected end of data".to_string())
+    }
+}
+
+/// What a format parser extracts, before EXIF is decoded.
+#[derive(Debug)]
+struct ImageInfo<'a> {
+    format: ImageFormat,
+    width: u32,
+    height: u32,
+    color_type: ColorType,
+    bit_depth: u8,
+    has_icc_profile: bool,
+    /// Raw TIFF-structured EXIF data, without any `Exif\0\0` prefix.
+    exif: Option<&'a [u8]>,
+    frames: Vec<AnimationFrame>,
+    plays: u32,
+}
+
+impl<'a> ImageInfo<'a> {
+    fn new(format: ImageFormat, color_type: ColorType, bit_depth: u8) -> Self {
+        Self {
+            format,
+            width: 0,
+            height: 0,
+            color_type,
+            bit_depth,
+            has_icc_profile: false,
+            exif: None,
+            frames: Vec::new(),
+            plays: 1,
+        }
+    }
+}
+
+/// Strips the `Exif\0\0` marker some containers keep in front of the TIFF structure.
+fn strip_exif_marker(data: &[u8]) -> &[u8] {
+    data.strip_prefix(b"Exif\0\0").unwrap_or(data)
+}
+
+fn ascii_field(exif: &exif::Exif, tag: Tag) -> Option<String> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    let Value::Ascii(values) = &field.value else {
+        return None;
+    };
+    let text = String::from_utf8_lossy(values.first()?);
+    let text = text.trim_matches(char::from(0)).trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Decodes the EXIF fields we report, or `None` if the data doesn't parse.
+pub fn parse_exif(data: &[u8]) -> Option<ExifInfo> {
+    let exif = exif::Reader::new()
+        .read_raw(strip_exif_marker(data).to_vec())
+        .inspect_err(|err| warn!(error = %err, "Ignoring unreadable EXIF data"))
+        .ok()?;
+
+    let orientation = exif
+        .get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .and_then(|value| u16::try_from(value).ok());
+
+    Some(ExifInfo {
+        orientation,
+        camera_make: ascii_field(&exif, Tag::Make),
+        camera_model: ascii_field(&exif, Tag::Model),
+        date_time: ascii_field(&exif, Tag::DateTimeOriginal)
+            .or_else(|| ascii_field(&exif, Tag::DateTime)),
+    })
+}
+
+/// Whether the data starts like a format that can carry an animation (GIF, APNG or WebP).
+pub fn is_animatable(header: &[u8]) -> bool {
+    header.starts_with(png::SIGNATURE)
+        || header.starts_with(b"GIF87a")
+        || header.starts_with(b"GIF89a")
+        || is_webp(header)
+}
+
+fn is_webp(header: &[u8]) -> bool {
+    header.len() >= 12 && header.starts_with(b"RIFF") && &header[8..12] == b"WEBP"
+}
+
+/// Analyzes an image held in memory.
+pub fn analyze_bytes(id: String, data: &[u8]) -> Result<ImageAnalysis, ImageError> {
+    let info = if data.starts_with(png::SIGNATURE) {
+        png::parse(data)?
+    } else if data.starts_with(jpeg::SIGNATURE) {
+        jpeg::parse(data)?
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        gif::parse(data)?
+    } else if is_webp(data) {
+        webp::parse(data)?
+    } else {
+        return Err(ImageError::Unsupported);
+    };
+
+    debug!(
+        format = ?info.format,
+        width = info.width,
+        height = info.height,
+        frame_count = info.frames.len(),
+        "Parsed image structure"
+    );
+
+    let animation = (info.frames.len() > 1).then(|| Animation {
+        frame_count: info.frames.len() as u32,
+        plays: info.plays,
+        duration_ms: info
+            .frames
+            .iter()
+            .map(|frame| u64::from(frame.delay_ms))
+            .sum(),
+        frames: info.frames,
+    });
+
+    Ok(ImageAnalysis {
+        id,
+        format: info.format,
+        width: info.width,
+        height: info.height,
+        color_type: info.color_type,
+        bit_depth: info.bit_depth,
+        has_icc_profile: info.has_icc_profile,
+        exif: info.exif.and_then(parse_exif),
+        animation,
+    })
+}
+
+/// Analyzes a PNG/APNG, JPEG, GIF or WebP file.
+#[instrument(skip(path), fields(path = %path.display()))]
+pub fn analyze(path: &Path) -> Result<ImageAnalysis, ImageError> {
+    let data = fs::read(path)?;
+    let analysis = analyze_bytes(file_name(path), &data)?;
+
+    info!(
+        format = ?analysis.format,
+        width = analysis.width,
+        height = analysis.height,
+        animated = analysis.animation.is_some(),
+        "Image analysis completed"
+    );
+    Ok(analysis)
+}
@@ -0,0 +1,100 @@
+//! GIF block walker.
+
+use super::{ImageError, ImageInfo};
+use crate::bytes::{ByteReader, Truncated};
+use crate::models::{AnimationFrame, ColorType, ImageFormat};
+
+const EXTENSION: u8 = 0x21;
+const IMAGE_DESCRIPTOR: u8 = 0x2C;
+const TRAILER: u8 = 0x3B;
+const GRAPHIC_CONTROL: u8 = 0xF9;
+const APPLICATION: u8 = 0xFF;
+
+fn color_table_len(packed: u8) -> usize {
+    if packed & 0x80 == 0 {
+        0
+    } else {
+        3 << ((packed & 0x07) + 1)
+    }
+}
+
+/// Skips a chain of data sub-blocks, up to and including the zero-length terminator.
+fn skip_sub_blocks(reader: &mut ByteReader) -> Result<(), Truncated> {
+    loop {
+        let len = reader.u8()?;
+        if len == 0 {
+            return Ok(());
+        }
+        reader.skip(usize::from(len))?;
+    }
+}
+
+pub(super) fn parse(data: &[u8]) -> Result<ImageInfo<'_>, ImageError> {
+    let mut reader = ByteReader::new(data);
+    reader.skip(6)?; // GIF87a / GIF89a
+    let mut info = ImageInfo::new(ImageFormat::Gif, ColorType::Indexed, 8);
+    info.width = u32::from(reader.u16_le()?);
+    info.height = u32::from(reader.u16_le()?);
+    let packed = reader.u8()?;
+    info.bit_depth = (packed & 0x07) + 1;
+    reader.skip(2)?; // background color, aspect ratio
+    reader.skip(color_table_len(packed))?;
+
+    // Without a NETSCAPE2.0 extension the animation plays once
+    info.plays = 1;
+    let mut pending_delay = 0;
+
+    // A missing trailer is common in the wild, so running out of data ends the walk
+    while let Ok(introducer) = reader.u8() {
+        match introducer {
+            EXTENSION => {
+                let label = reader.u8()?;
+                let len = reader.u8()?;
+                let block = reader.bytes(usize::from(len))?;
+                match label {
+                    GRAPHIC_CONTROL if block.len() >= 3 => {
+                        pending_delay = u32::from(u16::from_le_bytes([block[1], block[2]])) * 10;
+                    }
+                    APPLICATION if block == b"NETSCAPE2.0" => {
+                        let len = reader.u8()?;
+                        let sub = reader.bytes(usize::from(len))?;
+                        if sub.len() >= 3 && sub[0] == 1 {
+                            // The stored count is the number of repeats, with 0 meaning forever
+                            let repeats = u32::from(u16::from_le_bytes([sub[1], sub[2]]));
+                            info.plays = if repeats == 0 { 0 } else { repeats + 1 };
+                        }
+                    }
+                    _ => {}
+                }
+                skip_sub_blocks(&mut reader)?;
+            }
+            IMAGE_DESCRIPTOR => {
+                let start = reader.position() - 1;
+                reader.skip(4)?; // left, top
+                let width = u32::from(reader.u16_le()?);
+                let height = u32::from(reader.u16_le()?);
+                let packed = reader.u8()?;
+                reader.skip(color_table_len(packed))?;
+                reader.skip(1)?; // LZW minimum code size
+                skip_sub_blocks(&mut reader)?;
+                info.frames.push(AnimationFrame {
+                    width,
+                    height,
+                    delay_ms: std::mem::take(&mut pending_delay),
+                    size: (reader.position() - start) as u64,
+                });
+            }
+            TRAILER => break,
+            other => {
+                return Err(ImageError::Malformed(format!(
+                    "unexpected GIF block 0x{other:02x}"
+                )))
+            }
+        }
+    }
+
+    if info.frames.is_empty() {
+        return Err(ImageError::Malformed("no image data".to_string()));
+    }
+    Ok(info)
+}
@@ -0,0 +1,67 @@
+//! JPEG marker segment walker.
+
+use super::{ImageError, ImageInfo};
+use crate::bytes::ByteReader;
+use crate::models::{ColorType, ImageFormat};
+
+pub const SIGNATURE: &[u8] = &[0xFF, 0xD8];
+
+const SOS: u8 = 0xDA;
+const EOI: u8 = 0xD9;
+const APP1: u8 = 0xE1;
+const APP2: u8 = 0xE2;
+
+/// Start-of-frame markers; 0xC4, 0xC8 and 0xCC share the range but mean something else.
+fn is_start_of_frame(marker: u8) -> bool {
+    matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC)
+}
+
+pub(super) fn parse(data: &[u8]) -> Result<ImageInfo<'_>, ImageError> {
+    let mut reader = ByteReader::new(&data[SIGNATURE.len()..]);
+    let mut info = ImageInfo::new(ImageFormat::Jpeg, ColorType::YCbCr, 8);
+    let mut seen_frame = false;
+
+    // Everything we report lives in the headers, so stop at the first scan
+    loop {
+        if reader.u8()? != 0xFF {
+            return Err(ImageError::Malformed("expected a JPEG marker".to_string()));
+        }
+        let mut marker = reader.u8()?;
+        while marker == 0xFF {
+            marker = reader.u8()?; // fill bytes
+        }
+        if marker == SOS || marker == EOI {
+            break;
+        }
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            continue; // no payload
+        }
+
+        let length = usize::from(reader.u16_be()?);
+        let body = reader.bytes(length.saturating_sub(2))?;
+        match marker {
+            APP1 if body.starts_with(b"Exif\0\0") => info.exif = Some(&body[6..]),
+            APP2 if body.starts_with(b"ICC_PROFILE\0") => info.has_icc_profile = true,
+            marker if is_start_of_frame(marker) => {
+                let mut frame = ByteReader::new(body);
+                info.bit_depth = frame.u8()?;
+                info.height = u32::from(frame.u16_be()?);
+                info.width = u32::from(frame.u16_be()?);
+                info.color_type = match frame.u8()? {
+                    1 => ColorType::Gray,
+                    4 => ColorType::Cmyk,
+                    _ => ColorType::YCbCr,
+                };
+                seen_frame = true;
+            }
+            _ => {}
+        }
+    }
+
+    if !seen_frame {
+        return Err(ImageError::Malformed(
+            "missing start-of-frame segment".to_string(),
+        ));
+    }
+    Ok(info)
+}
@@ -0,0 +1,87 @@
+//! PNG and APNG chunk walker.
+
+use super::{ImageError, ImageInfo};
+use crate::bytes::ByteReader;
+use crate::models::{AnimationFrame, ColorType, ImageFormat};
+
+pub const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn color_type(code: u8) -> Result<ColorType, ImageError> {
+    Ok(match code {
+        0 => ColorType::Gray,
+        2 => ColorType::Rgb,
+        3 => ColorType::Indexed,
+        4 => ColorType::GrayAlpha,
+        6 => ColorType::Rgba,
+        other => {
+            return Err(ImageError::Malformed(format!(
+                "unknown PNG color type {other}"
+            )))
+        }
+    })
+}
+
+/// Converts an fcTL delay fraction to milliseconds; a zero denominator means 1/100 s.
+fn delay_ms(num: u16, den: u16) -> u32 {
+    let den = if den == 0 { 100 } else { u32::from(den) };
+    (u32::from(num) * 1000 + den / 2) / den
+}
+
+pub(super) fn parse(data: &[u8]) -> Result<ImageInfo<'_>, ImageError> {
+    let mut reader = ByteReader::new(&data[SIGNATURE.len()..]);
+    let mut info = ImageInfo::new(ImageFormat::Png, ColorType::Rgb, 8);
+    let mut seen_header = false;
+    // The default image counts as the first frame only if an fcTL precedes IDAT
+    let mut frames: Vec<AnimationFrame> = Vec::new();
+
+    while !reader.is_empty() {
+        let length = reader.u32_be()? as usize;
+        let kind = reader.array::<4>()?;
+        let body = reader.bytes(length)?;
+        reader.skip(4)?; // CRC
+
+        let mut chunk = ByteReader::new(body);
+        match &kind {
+            b"IHDR" => {
+                info.width = chunk.u32_be()?;
+                info.height = chunk.u32_be()?;
+                info.bit_depth = chunk.u8()?;
+                info.color_type = color_type(chunk.u8()?)?;
+                seen_header = true;
+            }
+            b"iCCP" => info.has_icc_profile = true,
+            b"eXIf" => info.exif = Some(body),
+            b"acTL" => {
+                chunk.skip(4)?; // num_frames, recounted from fcTL
+                info.plays = chunk.u32_be()?;
+            }
+            b"fcTL" => {
+                chunk.skip(4)?; // sequence number
+                let width = chunk.u32_be()?;
+                let height = chunk.u32_be()?;
+                chunk.skip(8)?; // x and y offset
+                let num = chunk.u16_be()?;
+                let den = chunk.u16_be()?;
+                frames.push(AnimationFrame {
+                    width,
+                    height,
+                    delay_ms: delay_ms(num, den),
+                    size: 0,
+                });
+            }
+            b"IDAT" | b"fdAT" => {
+                if let Some(frame) = frames.last_mut() {
+                    frame.size += length as u64;
+                }
+            }
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+
+    if !seen_header {
+        return Err(ImageError::Malformed("missing IHDR chunk".to_string()));
+    }
+    info.frames = frames;
+    Ok(info)
+}
@@ -0,0 +1,87 @@
+//! WebP RIFF chunk walker.
+
+use super::{strip_exif_marker, ImageError, ImageInfo};
+use crate::bytes::ByteReader;
+use crate::models::{AnimationFrame, ColorType, ImageFormat};
+
+const FLAG_ICC: u8 = 0x20;
+const FLAG_ALPHA: u8 = 0x10;
+
+pub(super) fn parse(data: &[u8]) -> Result<ImageInfo<'_>, ImageError> {
+    let mut reader = ByteReader::new(&data[12..]); // RIFF, size, WEBP
+    let mut info = ImageInfo::new(ImageFormat::WebP, ColorType::Rgb, 8);
+    let mut alpha = false;
+    let mut seen_header = false;
+
+    while reader.remaining() >= 8 {
+        let kind = reader.array::<4>()?;
+        let length = reader.u32_le()? as usize;
+        let body = reader.bytes(length)?;
+        if length % 2 == 1 && !reader.is_empty() {
+            reader.skip(1)?;
+        }
+
+        let mut chunk = ByteReader::new(body);
+        match &kind {
+            b"VP8X" => {
+                let flags = chunk.u8()?;
+                alpha |= flags & FLAG_ALPHA != 0;
+                info.has_icc_profile |= flags & FLAG_ICC != 0;
+                chunk.skip(3)?;
+                info.width = chunk.u24_le()? + 1;
+                info.height = chunk.u24_le()? + 1;
+                seen_header = true;
+            }
+            // A VP8X canvas takes precedence over the bitstream's own size
+            b"VP8 " if !seen_header => {
+                chunk.skip(3)?; // frame tag
+                if chunk.array::<3>()? != [0x9D, 0x01, 0x2A] {
+                    return Err(ImageError::Malformed("bad VP8 start code".to_string()));
+                }
+                info.width = u32::from(chunk.u16_le()? & 0x3FFF);
+                info.height = u32::from(chunk.u16_le()? & 0x3FFF);
+                seen_header = true;
+            }
+            b"VP8L" if !seen_header => {
+                if chunk.u8()? != 0x2F {
+                    return Err(ImageError::Malformed("bad VP8L signature".to_string()));
+                }
+                let bits = chunk.u32_le()?;
+                info.width = (bits & 0x3FFF) + 1;
+                info.height = ((bits >> 14) & 0x3FFF) + 1;
+                alpha |= bits & (1 << 28) != 0;
+                seen_header = true;
+            }
+            b"ALPH" => alpha = true,
+            b"ICCP" => info.has_icc_profile = true,
+            b"EXIF" => info.exif = Some(strip_exif_marker(body)),
+            b"ANIM" => {
+                chunk.skip(4)?; // background color
+                info.plays = u32::from(chunk.u16_le()?);
+            }
+            b"ANMF" => {
+                chunk.skip(6)?; // x and y offset
+                let width = chunk.u24_le()? + 1;
+                let height = chunk.u24_le()? + 1;
+                let delay_ms = chunk.u24_le()?;
+                info.frames.push(AnimationFrame {
+                    width,
+                    height,
+                    delay_ms,
+                    size: length as u64,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    if !seen_header {
+        return Err(ImageError::Malformed(
+            "missing image header chunk".to_string(),
+        ));
+    }
+    if alpha {
+        info.color_type = ColorType::Rgba;
+    }
+    Ok(info)
+}
@@ -1,129 +1,113 @@
+pub mod analysis;
+pub mod backend;
+pub mod bytes;
+pub mod cache;
+pub mod capabilities;
+pub mod clipboard;
+pub mod efficiency;
 pub mod ff;
+pub mod image;
+pub mod loudness;
+pub mod manifest;
 pub mod media;
 pub mod models;
+pub mod process;
+pub mod qc;
+pub mod quality;
+pub mod registry;
+pub mod remote;
+pub mod scan;
+pub mod scene;
+pub mod settings;
+pub mod state;
 pub mod strings;
-
-use ff::extract_streams;
-use media::{detect_media_type, is_media_file};
-use models::{StreamResult, StreamResultError, File, FileCandidacy, BitrateData, BitrateFrame};
-use strings::transform_filename;
+pub mod thumbnails;
+pub mod tools;
+pub mod ts;
+pub mod watch;
+pub mod waveform;
+
+use analysis::AnalysisError;
+use cache::{CacheKey, CacheStats};
+use models::{
+    BitrateData, EfficiencyAnalysis, FfprobeInfo, File, ImageAnalysis, LoudnessAnalysis,
+    ManifestAnalysis, QcReport, QualityAnalysis, SceneAnalysis, StreamResult, StreamResultError,
+    Thumbnail, ThumbnailFormat, TsAnalysis, Waveform,
+};
+use qc::QcSettings;
+use settings::Settings;
+use state::AppState;
 use std::path::Path;
-use std::process::Command;
-use tracing::{debug, error, info, instrument, warn};
+use strings::transform_filename;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tracing::{debug, info, instrument, warn};
+
+/// Logs the full path for the first file in a batch, then only truncated filenames.
+fn log_batch_entry(index: usize, path: &Path) {
+    let filename = analysis::file_name(path);
+    if index == 0 {
+        debug!(full_path = %path.display(), filename = %filename, "Processing first file");
+    } else {
+        let truncated_name = transform_filename(&filename, 15);
+        debug!(filename = %truncated_name, "Processing file");
+    }
+}
 
-// detection, helpers moved to modules above
+fn stream_error(filename: String, err: AnalysisError) -> StreamResultError {
+    StreamResultError {
+        filename: Some(filename),
+        reason: err.to_string(),
+        error_type: err.kind().to_string(),
+    }
+}
 
 #[tauri::command]
-#[instrument(skip(paths), fields(file_count = paths.len()))]
-fn has_streams(paths: Vec<String>) -> Result<Vec<StreamResult>, StreamResultError> {
-    info!(file_count = paths.len(), "Processing files for stream analysis");
-    
+#[instrument(skip(state, paths), fields(file_count = paths.len()))]
+fn has_streams(
+    state: State<'_, AppState>,
+    paths: Vec<String>,
+) -> Result<Vec<StreamResult>, StreamResultError> {
+    info!(
+        file_count = paths.len(),
+        "Processing files for stream analysis"
+    );
+
+    let ffprobe = state.ffprobe().map_err(|err| {
+        let err = AnalysisError::from(err);
+        StreamResultError {
+            filename: None,
+            reason: err.to_string(),
+            error_type: err.kind().to_string(),
+        }
+    })?;
+
+    let network = state.settings().network;
     let results = paths
-        .into_iter()
+        .iter()
         .enumerate()
         .map(|(index, path_str)| {
-            let path = Path::new(&path_str);
-            let filename = path
-                .file_name()
-                .and_then(|name| name.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-            
-            // Log full path only on first occurrence, then use truncated filename
-            if index == 0 {
-                debug!(full_path = %path_str, filename = %filename, "Processing first file");
-            } else {
-                let truncated_name = transform_filename(&filename, 15);
-                debug!(filename = %truncated_name, "Processing file");
+            let path = Path::new(path_str);
+            log_batch_entry(index, path);
+            // Remote resources have no file identity to cache by
+            if remote::is_url(path_str) {
+                return analysis::probe_url(&ffprobe, path_str, &network)
+                    .map_err(|err| stream_error(remote::display_name(path_str), err));
             }
-
-            // Check if file exists
-            if !path.exists() {
-                let truncated_name = transform_filename(&filename, 15);
-                warn!(filename = %truncated_name, "File does not exist");
-                return Err(StreamResultError {
-                    filename: Some(filename),
-                    reason: "File does not exist".to_string(),
-                    error_type: "not_found".to_string(),
-                });
-            }
-
-            // Check if it's a file (not directory)
-            if !path.is_file() {
-                let truncated_name = transform_filename(&filename, 15);
-                warn!(filename = %truncated_name, "Path is not a file");
-                return Err(StreamResultError {
-                    filename: Some(filename),
-                    reason: "Not a file (directory or other)".to_string(),
-                    error_type: "not_file".to_string(),
-                });
-            }
-
-            // Get file size
-            let size = std::fs::metadata(&path_str)
-                .map(|metadata| metadata.len())
-                .unwrap_or(0);
-
-            let truncated_name = transform_filename(&filename, 15);
-            debug!(filename = %truncated_name, size = size, "File metadata retrieved");
-
-            // Detect media type using magic numbers and fallback to extensions
-            let media_type = detect_media_type(path);
-            debug!(filename = %truncated_name, media_type = ?media_type, "Media type detected");
-
-            // Only try to analyze media files with ffprobe
-            if is_media_file(&media_type) {
-                info!(filename = %truncated_name, media_type = ?media_type, "Analyzing media file with ffprobe");
-                
-                // Analyze with ffprobe
-                match ffprobe::ffprobe(&path_str) {
-                    Ok(info) => {
-                        let streams = extract_streams(&info);
-                        let duration = info
-                            .format
-                            .duration
-                            .and_then(|dur_str| dur_str.parse::<f64>().ok());
-
-                        info!(
-                            filename = %truncated_name,
-                            stream_count = streams.len(),
-                            duration = ?duration,
-                            "Successfully analyzed media file"
-                        );
-
-                        Ok(StreamResult {
-                            filename,
-                            path: path_str,
-                            media_type,
-                            duration,
-                            size,
-                            streams,
-                        })
-                    }
-                    Err(err) => {
-                        error!(filename = %truncated_name, error = %err, "Failed to analyze media file with ffprobe");
-                        Err(StreamResultError {
-                            filename: Some(filename),
-                            reason: format!("Could not analyze media file: {err}"),
-                            error_type: "analysis_failed".to_string(),
-                        })
-                    }
-                }
-            } else {
-                debug!(filename = %truncated_name, media_type = ?media_type, "Skipping non-media file");
-                // For non-media files, return an error indicating it's not a media file
-                Err(StreamResultError {
-                    filename: Some(filename),
-                    reason: format!("Not a media file (detected as {media_type:?})"),
-                    error_type: "not_media".to_string(),
+            state
+                .cached(path, ffprobe.version(), "streams", || {
+                    analysis::probe_file(&ffprobe, path)
                 })
-            }
+                .map_err(|err| stream_error(analysis::file_name(path), err))
         })
         .collect::<Result<Vec<_>, _>>();
 
     match &results {
         Ok(streams) => {
-            info!(successful_files = streams.len(), "Successfully processed all files");
+            info!(
+                successful_files = streams.len(),
+                "Successfully processed all files"
+            );
         }
         Err(_) => {
             warn!("Some files failed to process");
@@ -133,174 +117,379 @@ fn has_streams(paths: Vec<String>) -> Result<Vec<StreamResult>, StreamResultErro
     results
 }
 
-#[tauri::command]
-#[instrument(skip(paths), fields(file_count = paths.len()))]
-fn analyze_files(paths: Vec<String>) -> Vec<File> {
-    info!(file_count = paths.len(), "Analyzing files for candidacy");
-    
+/// Builds candidacy entries for local paths and `http(s)://` URLs alike, expanding
+/// directories into the media files inside them.
+fn candidacy_list(state: &AppState, paths: &[String]) -> Vec<File> {
+    let settings = state.settings();
+    // Only needed for dropped files nothing else recognizes, so a missing binary isn't an error
+    let ffprobe = state.ffprobe().ok();
     paths
-        .into_iter()
+        .iter()
         .enumerate()
-        .map(|(index, path_str)| {
-            let path = Path::new(&path_str);
-            let filename = path
-                .file_name()
-                .and_then(|name| name.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-            
-            // Log full path only on first occurrence, then use truncated filename
-            if index == 0 {
-                debug!(full_path = %path_str, filename = %filename, "Processing first file");
+        .flat_map(|(index, path_str)| {
+            let path = Path::new(path_str);
+            log_batch_entry(index, path);
+            if remote::is_url(path_str) {
+                vec![analysis::analyze_url(path_str, &settings.network)]
+            } else if path.is_dir() {
+                analysis::analyze_directory(path, &settings.scan)
             } else {
-                let truncated_name = transform_filename(&filename, 15);
-                debug!(filename = %truncated_name, "Processing file");
+                vec![analysis::analyze_file(ffprobe.as_ref(), path)]
             }
+        })
+        .collect()
+}
 
-            // Get file size
-            let size = std::fs::metadata(&path_str)
-                .map(|metadata| metadata.len())
-                .unwrap_or(0) as u32;
-
-            let truncated_name = transform_filename(&filename, 15);
-            debug!(filename = %truncated_name, size = size, "File metadata retrieved");
-
-            // Check if file exists
-            if !path.exists() {
-                let truncated_name = transform_filename(&filename, 15);
-                warn!(filename = %truncated_name, "File does not exist");
-                return File {
-                    filename,
-                    size,
-                    candidacy: FileCandidacy::Error {
-                        reason: "File does not exist".to_string(),
-                    },
-                };
-            }
+#[tauri::command]
+#[instrument(skip(state, paths), fields(file_count = paths.len()))]
+fn analyze_files(state: State<'_, AppState>, paths: Vec<String>) -> Vec<File> {
+    info!(file_count = paths.len(), "Analyzing files for candidacy");
+    candidacy_list(&state, &paths)
+}
 
-            // Check if it's a file (not directory)
-            if !path.is_file() {
-                let truncated_name = transform_filename(&filename, 15);
-                warn!(filename = %truncated_name, "Path is not a file");
-                return File {
-                    filename,
-                    size,
-                    candidacy: FileCandidacy::Error {
-                        reason: "Not a file (directory or other)".to_string(),
-                    },
-                };
-            }
+/// Reads paths and URLs from the clipboard and analyzes them as if they had been dropped.
+#[tauri::command]
+#[instrument(skip(app, state))]
+fn paste_files(app: AppHandle, state: State<'_, AppState>) -> Result<Vec<File>, String> {
+    let text = app
+        .clipboard()
+        .read_text()
+        .map_err(|err| format!("Failed to read clipboard: {err}"))?;
+
+    let paths = clipboard::parse_clipboard_text(&text);
+    info!(file_count = paths.len(), "Analyzing pasted files for candidacy");
+    Ok(candidacy_list(&state, &paths))
+}
 
-            // Detect media type using magic numbers and fallback to extensions
-            let media_type = detect_media_type(path);
-            debug!(filename = %truncated_name, media_type = ?media_type, "Media type detected");
-
-            // Check if it's a media file
-            if is_media_file(&media_type) {
-                info!(filename = %truncated_name, media_type = ?media_type, "Valid media file detected");
-                File {
-                    filename,
-                    size,
-                    candidacy: FileCandidacy::Success {
-                        file_type: media_type,
-                    },
-                }
-            } else {
-                debug!(filename = %truncated_name, media_type = ?media_type, "Non-media file detected");
-                File {
-                    filename,
-                    size,
-                    candidacy: FileCandidacy::Error {
-                        reason: format!("Not a media file (detected as {media_type:?})"),
-                    },
-                }
-            }
+/// Bitrate data for `path`, through the cache entry [`extract_bitrate_data`] fills.
+fn cached_bitrate_data(state: &AppState, path: &Path) -> Result<BitrateData, String> {
+    let ffprobe = state.ffprobe().map_err(|err| err.to_string())?;
+    state
+        .cached(path, ffprobe.version(), "bitrate", || {
+            analysis::extract_bitrate_data(&ffprobe, path)
         })
-        .collect()
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+#[instrument(skip(state, path), fields(path = %path))]
+fn extract_bitrate_data(state: State<'_, AppState>, path: String) -> Result<BitrateData, String> {
+    cached_bitrate_data(&state, Path::new(&path))
 }
 
+/// Normalizes frame sizes by resolution and frame rate, reusing cached probe results.
 #[tauri::command]
-#[instrument(skip(path), fields(path = %path))]
-fn extract_bitrate_data(path: String) -> Result<BitrateData, String> {
-    info!(path = %path, "Extracting bitrate data from video file");
-
-    let path_obj = Path::new(&path);
-    let filename = path_obj
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("unknown")
-        .to_string();
-
-    // Check if file exists
-    if !path_obj.exists() {
-        error!(filename = %filename, "File does not exist");
-        return Err("File does not exist".to_string());
+#[instrument(skip(state, path), fields(path = %path))]
+fn analyze_efficiency(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<EfficiencyAnalysis, String> {
+    let ffprobe = state.ffprobe().map_err(|err| err.to_string())?;
+    let path = Path::new(&path);
+    let streams = state
+        .cached(path, ffprobe.version(), "streams", || {
+            analysis::probe_file(&ffprobe, path)
+        })
+        .map_err(|err| err.to_string())?;
+    let bitrate = cached_bitrate_data(&state, path)?;
+    efficiency::compute(&streams, &bitrate).map_err(|err| err.to_string())
+}
+
+/// Renders the frame at `timestamp`, or every keyframe when no timestamp is given.
+#[tauri::command]
+#[instrument(skip(state, path), fields(path = %path))]
+fn extract_thumbnails(
+    state: State<'_, AppState>,
+    path: String,
+    timestamp: Option<f64>,
+    width: Option<u32>,
+    format: Option<ThumbnailFormat>,
+    inline: Option<bool>,
+) -> Result<Vec<Thumbnail>, String> {
+    let ffmpeg = state.ffmpeg().map_err(|err| err.to_string())?;
+    let path = Path::new(&path);
+    let root = state.thumbnail_dir();
+    let width = width.unwrap_or(thumbnails::DEFAULT_WIDTH);
+    let format = format.unwrap_or_default();
+
+    let mut rendered = match timestamp {
+        Some(timestamp) => {
+            analysis::thumbnail_at(&ffmpeg, path, &root, timestamp, format, width).map(|t| vec![t])
+        }
+        None => {
+            let bitrate = cached_bitrate_data(&state, path)?;
+            analysis::keyframe_thumbnails(&ffmpeg, path, &root, &bitrate, format, width)
+        }
     }
+    .map_err(|err| err.to_string())?;
+    state.trim_thumbnails(path, &ffmpeg);
 
-    // Run ffprobe to get frame packet sizes
-    // -v quiet: suppress ffprobe info
-    // -select_streams v:0: only first video stream
-    // -show_entries frame=pkt_size: only show packet size
-    // -of csv=p=0: output as CSV without headers
-    info!(filename = %filename, "Running ffprobe to extract frame data");
-
-    let output = Command::new("ffprobe")
-        .args([
-            "-v", "quiet",
-            "-select_streams", "v:0",
-            "-show_entries", "frame=pkt_size",
-            "-of", "csv=p=0",
-            &path
-        ])
-        .output()
-        .map_err(|e| {
-            error!(error = %e, "Failed to execute ffprobe");
-            format!("Failed to execute ffprobe: {e}")
-        })?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        error!(stderr = %stderr, "ffprobe command failed");
-        return Err(format!("ffprobe failed: {stderr}"));
+    if inline.unwrap_or(false) {
+        for thumbnail in &mut rendered {
+            thumbnails::inline(thumbnail, format)
+                .map_err(|err| format!("Failed to read thumbnail: {err}"))?;
+        }
     }
+    Ok(rendered)
+}
+
+#[tauri::command]
+#[instrument(skip(state, path), fields(path = %path))]
+fn detect_scenes(
+    state: State<'_, AppState>,
+    path: String,
+    threshold: Option<f64>,
+) -> Result<SceneAnalysis, String> {
+    let ffmpeg = state.ffmpeg().map_err(|err| err.to_string())?;
+    let path = Path::new(&path);
+    // Scores don't depend on the threshold, so changing it never re-decodes the file
+    let scores = state
+        .cached(path, ffmpeg.version(), "scene_scores", || {
+            analysis::score_scenes(&ffmpeg, path)
+        })
+        .map_err(|err| err.to_string())?;
+    Ok(scene::detect(
+        analysis::file_name(path),
+        scores,
+        threshold.unwrap_or(scene::DEFAULT_THRESHOLD),
+    ))
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    debug!(line_count = stdout.lines().count(), "Parsing ffprobe output");
+#[tauri::command]
+#[instrument(skip(state, path, thresholds), fields(path = %path))]
+fn run_qc(
+    state: State<'_, AppState>,
+    path: String,
+    thresholds: Option<QcSettings>,
+) -> Result<QcReport, String> {
+    let ffprobe = state.ffprobe().map_err(|err| err.to_string())?;
+    let ffmpeg = state.ffmpeg().map_err(|err| err.to_string())?;
+    let thresholds = thresholds.unwrap_or_else(|| state.settings().qc);
+    let path = Path::new(&path);
+    // Intervals depend on every threshold, so each combination gets its own entry
+    let kind = format!(
+        "qc-{}-{}-{}-{}-{}",
+        thresholds.black_min_duration,
+        thresholds.black_picture_ratio,
+        thresholds.black_pixel_threshold,
+        thresholds.silence_min_duration,
+        thresholds.silence_noise_db
+    );
+    state
+        .cached(path, ffmpeg.version(), &kind, || {
+            analysis::check_quality(&ffprobe, &ffmpeg, path, &thresholds)
+        })
+        .map_err(|err| err.to_string())
+}
 
-    let frames: Vec<BitrateFrame> = stdout
-        .lines()
-        .enumerate()
-        .filter_map(|(index, line)| {
-            line.trim().parse::<u64>().ok().map(|packet_size| BitrateFrame {
-                frame_num: index as u32,
-                packet_size,
-            })
+#[tauri::command]
+#[instrument(skip(state, path, reference), fields(path = %path))]
+fn compare_quality(
+    state: State<'_, AppState>,
+    path: String,
+    reference: String,
+) -> Result<QualityAnalysis, String> {
+    let ffmpeg = state.ffmpeg().map_err(|err| err.to_string())?;
+    let path = Path::new(&path);
+    let reference = Path::new(&reference);
+    // Scores depend on both files, so the reference's identity is part of the entry
+    let kind = match CacheKey::for_file(reference, ffmpeg.version()) {
+        Ok(key) => format!("quality-{}", key.as_str()),
+        Err(err) => return Err(AnalysisError::from(err).to_string()),
+    };
+    state
+        .cached(path, ffmpeg.version(), &kind, || {
+            analysis::compare_quality(&ffmpeg, path, reference)
         })
-        .collect();
+        .map_err(|err| err.to_string())
+}
 
-    if frames.is_empty() {
-        warn!(filename = %filename, "No frame data extracted");
-        return Err("No frame data could be extracted from file".to_string());
-    }
+#[tauri::command]
+#[instrument(skip(state, path), fields(path = %path))]
+fn analyze_transport_stream(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<TsAnalysis, String> {
+    let path = Path::new(&path);
+    // Native analysis, so results only depend on this build
+    state
+        .cached(path, env!("CARGO_PKG_VERSION"), "transport_stream", || {
+            analysis::analyze_transport_stream(path)
+        })
+        .map_err(|err| err.to_string())
+}
 
-    info!(
-        filename = %filename,
-        frame_count = frames.len(),
-        "Successfully extracted bitrate data"
-    );
+#[tauri::command]
+#[instrument(skip(state, path), fields(path = %path))]
+fn analyze_image(state: State<'_, AppState>, path: String) -> Result<ImageAnalysis, String> {
+    let path = Path::new(&path);
+    state
+        .cached(path, env!("CARGO_PKG_VERSION"), "image", || {
+            analysis::analyze_image(path)
+        })
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+#[instrument(skip(state, path), fields(path = %path))]
+fn analyze_loudness(
+    state: State<'_, AppState>,
+    path: String,
+    audio_stream: Option<u32>,
+) -> Result<LoudnessAnalysis, String> {
+    let ffmpeg = state.ffmpeg().map_err(|err| err.to_string())?;
+    let path = Path::new(&path);
+    let audio_stream = audio_stream.unwrap_or(0);
+    state
+        .cached(
+            path,
+            ffmpeg.version(),
+            &format!("loudness-{audio_stream}"),
+            || analysis::analyze_loudness(&ffmpeg, path, audio_stream),
+        )
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+#[instrument(skip(state, path), fields(path = %path))]
+fn extract_waveform(
+    state: State<'_, AppState>,
+    path: String,
+    audio_stream: Option<u32>,
+    resolution: Option<u32>,
+) -> Result<Waveform, String> {
+    let ffmpeg = state.ffmpeg().map_err(|err| err.to_string())?;
+    let path = Path::new(&path);
+    let audio_stream = audio_stream.unwrap_or(0);
+    let resolution = resolution
+        .unwrap_or(waveform::DEFAULT_RESOLUTION)
+        .clamp(1, waveform::MAX_RESOLUTION);
+    state
+        .cached(
+            path,
+            ffmpeg.version(),
+            &format!("waveform-{audio_stream}-{resolution}"),
+            || analysis::extract_waveform(&ffmpeg, path, audio_stream, resolution),
+        )
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+#[instrument(skip(state, path), fields(path = %path))]
+fn analyze_manifest(state: State<'_, AppState>, path: String) -> Result<ManifestAnalysis, String> {
+    // Segment sizes don't need ffprobe, so a missing binary only costs keyframe data
+    let ffprobe = state
+        .ffprobe()
+        .inspect_err(|err| warn!(error = %err, "Analyzing manifest without ffprobe"))
+        .ok();
+    analysis::analyze_manifest(ffprobe.as_ref(), Path::new(&path)).map_err(|err| err.to_string())
+}
 
-    Ok(BitrateData {
-        id: filename,
-        frames,
+/// Starts watching a folder, emitting a [`watch::WATCH_EVENT`] for every media file that
+/// settles in it. Returns the canonical folder path.
+#[tauri::command]
+#[instrument(skip(app, state, path), fields(path = %path))]
+fn start_watching(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<String, String> {
+    let handle = app.clone();
+    let folder = state
+        .start_watching(Path::new(&path), move |folder, file| {
+            let state = handle.state::<AppState>();
+            let extract_bitrate = state.settings().watch.extract_bitrate;
+            if let Some(event) = watch::analyze_settled(&state, folder, file, extract_bitrate) {
+                if let Err(err) = handle.emit(watch::WATCH_EVENT, event) {
+                    warn!(error = %err, "Failed to emit watch result");
+                }
+            }
+        })
+        .map_err(|err| err.to_string())?;
+    Ok(folder.display().to_string())
+}
+
+#[tauri::command]
+#[instrument(skip(state, path), fields(path = %path))]
+fn stop_watching(state: State<'_, AppState>, path: String) -> Result<(), String> {
+    state
+        .stop_watching(Path::new(&path))
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn list_watched(state: State<'_, AppState>) -> Vec<String> {
+    state
+        .watched_folders()
+        .iter()
+        .map(|folder| folder.display().to_string())
+        .collect()
+}
+
+#[tauri::command]
+fn get_ffprobe_info(state: State<'_, AppState>) -> Result<FfprobeInfo, String> {
+    let ffprobe = state.ffprobe().map_err(|err| err.to_string())?;
+    Ok(FfprobeInfo {
+        path: ffprobe.path().display().to_string(),
+        version: ffprobe.version().to_string(),
+        source: ffprobe.tool().source,
     })
 }
 
+#[tauri::command]
+fn clear_cache(state: State<'_, AppState>) -> Result<CacheStats, String> {
+    state
+        .clear_cache()
+        .map_err(|err| format!("Failed to clear cache: {err}"))
+}
+
+#[tauri::command]
+fn get_settings(state: State<'_, AppState>) -> Settings {
+    state.settings()
+}
+
+#[tauri::command]
+fn update_settings(state: State<'_, AppState>, settings: Settings) -> Result<(), String> {
+    state
+        .update_settings(settings)
+        .map_err(|err| format!("Failed to save settings: {err}"))
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     info!("Initializing Tauri application");
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![has_streams, analyze_files, extract_bitrate_data])
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .setup(|app| {
+            let config_dir = app.path().app_config_dir().ok();
+            let cache_dir = app.path().app_data_dir().ok().map(|dir| dir.join("cache"));
+            app.manage(AppState::load(config_dir, cache_dir));
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            has_streams,
+            analyze_files,
+            paste_files,
+            extract_bitrate_data,
+            analyze_efficiency,
+            extract_thumbnails,
+            detect_scenes,
+            run_qc,
+            compare_quality,
+            analyze_transport_stream,
+            analyze_image,
+            analyze_loudness,
+            extract_waveform,
+            analyze_manifest,
+            start_watching,
+            stop_watching,
+            list_watched,
+            get_ffprobe_info,
+            clear_cache,
+            get_settings,
+            update_settings
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
-}
\ No newline at end of file
+}
@@ -0,0 +1,95 @@
+//! EBU R128 / ITU-R BS.1770 loudness measurement.
+//!
+//! ffmpeg's `ebur128` filter does the measuring. With `metadata=1` it attaches running
+//! values to every 100 ms block, which `ametadata` prints to stdout, so the time series
+//! and the final integrated values come from a single pass without parsing log output.
+
+use crate::analysis::{file_name, AnalysisError};
+use crate::ff::{parse_frame_metadata, Ffmpeg, PRINT_METADATA};
+use crate::models::{LoudnessAnalysis, LoudnessPoint};
+use std::path::Path;
+use tracing::{debug, info, instrument};
+
+/// Integrated loudness never drops below the absolute gate; ffmpeg reports exactly
+/// this value when every block was gated out.
+const ABSOLUTE_GATE: f64 = -70.0;
+
+/// Converts a linear sample peak to dBTP.
+fn peak_to_db(peak: f64) -> Option<f64> {
+    (peak > 0.0).then(|| 20.0 * peak.log10())
+}
+
+/// Builds the analysis from `ametadata` print output of `ebur128` metadata.
+///
+/// The running `I`, `LRA` and `true_peak` values of the last block are the results for
+/// the whole stream.
+pub fn parse_metadata(id: String, audio_stream: u32, output: &str) -> LoudnessAnalysis {
+    let mut points: Vec<LoudnessPoint> = Vec::new();
+    let (mut integrated, mut loudness_range, mut true_peak) = (None, None, None);
+
+    for frame in parse_frame_metadata(output) {
+        let time = frame
+            .pts_time
+            .unwrap_or_else(|| points.last().map_or(0.0, |point| point.time + 0.1));
+        points.push(LoudnessPoint {
+            time,
+            momentary: frame.number("lavfi.r128.M"),
+            short_term: frame.number("lavfi.r128.S"),
+        });
+
+        if frame.get("lavfi.r128.I").is_some() {
+            integrated = frame
+                .number("lavfi.r128.I")
+                .filter(|lufs| *lufs > ABSOLUTE_GATE);
+        }
+        if frame.get("lavfi.r128.LRA").is_some() {
+            loudness_range = frame.number("lavfi.r128.LRA");
+        }
+        if frame.get("lavfi.r128.true_peak").is_some() {
+            true_peak = frame.number("lavfi.r128.true_peak").and_then(peak_to_db);
+        }
+    }
+
+    LoudnessAnalysis {
+        id,
+        audio_stream,
+        integrated,
+        loudness_range,
+        true_peak,
+        points,
+    }
+}
+
+/// Measures the loudness of the `audio_stream`-th audio stream of `path`.
+#[instrument(skip(ffmpeg, path), fields(path = %path.display()))]
+pub fn analyze(
+    ffmpeg: &Ffmpeg,
+    path: &Path,
+    audio_stream: u32,
+) -> Result<LoudnessAnalysis, AnalysisError> {
+    let map = format!("0:a:{audio_stream}");
+    let filter = format!("ebur128=metadata=1:peak=true,ametadata={PRINT_METADATA}");
+    let output = ffmpeg.output(
+        &[],
+        path,
+        &["-map", &map, "-af", &filter, "-f", "null", "-"],
+    )?;
+
+    let stdout = String::from_utf8_lossy(&output);
+    debug!(
+        line_count = stdout.lines().count(),
+        "Parsing ebur128 metadata"
+    );
+    let analysis = parse_metadata(file_name(path), audio_stream, &stdout);
+    if analysis.points.is_empty() {
+        return Err(AnalysisError::NoFrames);
+    }
+
+    info!(
+        integrated = ?analysis.integrated,
+        loudness_range = ?analysis.loudness_range,
+        true_peak = ?analysis.true_peak,
+        "Loudness analysis completed"
+    );
+    Ok(analysis)
+}
@@ -0,0 +1,386 @@
+//! HLS and DASH manifest analysis against locally stored segments.
+//!
+//! Manifests are parsed into a common list of variants and segments, then every segment
+//! is measured from disk: its size gives the actual bitrate to compare against the
+//! declared `BANDWIDTH`, and its frames (via the bitrate backends) give keyframe
+//! positions for checking alignment across renditions.
+
+mod dash;
+mod hls;
+
+use crate::analysis::{file_name, AnalysisError};
+use crate::backend;
+use crate::ff::Ffprobe;
+use crate::models::{
+    BitrateData, ManifestAnalysis, ManifestKind, SegmentAnalysis, VariantAnalysis,
+};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tracing::{debug, info, instrument, warn};
+
+/// Largest difference between first keyframes of a segment across renditions that
+/// still counts as aligned, in seconds.
+pub const ALIGNMENT_TOLERANCE: f64 = 0.01;
+
+#[derive(Debug, Error)]
+pub enum ManifestError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid XML: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("Invalid manifest: {0}")]
+    Invalid(String),
+}
+
+/// Where a manifest entry points.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    Local(PathBuf),
+    Remote(String),
+}
+
+impl Location {
+    /// Resolves `uri` relative to this location, which is treated as a directory.
+    pub fn join(&self, uri: &str) -> Location {
+        if let Some(path) = uri.strip_prefix("file://") {
+            return Location::Local(PathBuf::from(path));
+        }
+        if uri.contains("://") {
+            return Location::Remote(uri.to_string());
+        }
+
+        // Query strings are meaningless for local files
+        let uri = uri.split(['?', '#']).next().unwrap_or(uri);
+        match self {
+            Location::Local(dir) => Location::Local(dir.join(uri)),
+            Location::Remote(base) => {
+                let prefix = base.rsplit_once('/').map_or(base.as_str(), |(dir, _)| dir);
+                Location::Remote(format!("{prefix}/{uri}"))
+            }
+        }
+    }
+
+    /// Resolves `uri` relative to the directory containing this location.
+    pub fn sibling(&self, uri: &str) -> Location {
+        match self {
+            Location::Local(path) => {
+                Location::Local(path.parent().unwrap_or(Path::new("")).to_path_buf()).join(uri)
+            }
+            Location::Remote(_) => self.join(uri),
+        }
+    }
+}
+
+/// A byte range within a resource; `length` of `None` means "to the end".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub offset: u64,
+    pub length: Option<u64>,
+}
+
+/// A resource referenced by a manifest, optionally limited to a byte range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resource {
+    /// The reference as written in the manifest, for display.
+    pub uri: String,
+    pub location: Location,
+    pub range: Option<ByteRange>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub resource: Resource,
+    /// Initialization data that must precede the segment for it to be decodable.
+    pub init: Option<Resource>,
+    pub start: f64,
+    pub duration: f64,
+}
+
+/// One rendition as declared by the manifest.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Variant {
+    pub id: String,
+    pub bandwidth: Option<u64>,
+    pub average_bandwidth: Option<u64>,
+    pub resolution: Option<String>,
+    pub codecs: Option<String>,
+    pub segments: Vec<Segment>,
+    /// Why the variant's segments could not be listed, if they could not.
+    pub error: Option<String>,
+}
+
+/// Works out whether `path` is an HLS playlist or a DASH MPD.
+pub fn detect_kind(path: &Path, contents: &str) -> Option<ManifestKind> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
+    match extension.as_deref() {
+        Some("m3u8" | "m3u") => return Some(ManifestKind::Hls),
+        Some("mpd") => return Some(ManifestKind::Dash),
+        _ => {}
+    }
+
+    let contents = contents.trim_start_matches('\u{feff}').trim_start();
+    if contents.starts_with("#EXTM3U") {
+        Some(ManifestKind::Hls)
+    } else if contents.contains("<MPD") {
+        Some(ManifestKind::Dash)
+    } else {
+        None
+    }
+}
+
+/// Parses a manifest into variants without touching any segments.
+pub fn parse(path: &Path) -> Result<(ManifestKind, Vec<Variant>), ManifestError> {
+    let contents = fs::read_to_string(path)?;
+    let kind = detect_kind(path, &contents)
+        .ok_or_else(|| ManifestError::Invalid("not an HLS or DASH manifest".to_string()))?;
+    let location = Location::Local(path.to_path_buf());
+
+    let variants = match kind {
+        ManifestKind::Hls => hls::parse(&contents, &location, &file_name(path))?,
+        ManifestKind::Dash => dash::parse(&contents, &location)?,
+    };
+    Ok((kind, variants))
+}
+
+/// Copies `resource` (or its byte range) into `out`.
+fn copy_resource(resource: &Resource, out: &mut impl Write) -> io::Result<u64> {
+    let Location::Local(path) = &resource.location else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "remote resources are not supported",
+        ));
+    };
+
+    let mut file = fs::File::open(path)?;
+    match resource.range {
+        Some(range) => {
+            file.seek(SeekFrom::Start(range.offset))?;
+            match range.length {
+                Some(length) => io::copy(&mut file.take(length), out),
+                None => io::copy(&mut file, out),
+            }
+        }
+        None => io::copy(&mut file, out),
+    }
+}
+
+/// Size in bytes of a resource, without reading it.
+fn resource_size(resource: &Resource) -> io::Result<u64> {
+    let Location::Local(path) = &resource.location else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "remote segments are not supported",
+        ));
+    };
+
+    let file_size = fs::metadata(path)?.len();
+    Ok(match resource.range {
+        Some(ByteRange {
+            length: Some(length),
+            ..
+        }) => length,
+        Some(ByteRange { offset, .. }) => file_size.saturating_sub(offset),
+        None => file_size,
+    })
+}
+
+/// Extracts frames from a segment, prepending its init segment or cutting out its
+/// byte range into a temporary file when needed.
+fn segment_frames(
+    segment: &Segment,
+    ffprobe: Option<&Ffprobe>,
+) -> Result<BitrateData, AnalysisError> {
+    let extract = |path: &Path| match ffprobe {
+        Some(ffprobe) => backend::extract_bitrate_data(ffprobe, path),
+        None => backend::extract_native(path).unwrap_or(Err(AnalysisError::NoFrames)),
+    };
+
+    if segment.init.is_none() && segment.resource.range.is_none() {
+        if let Location::Local(path) = &segment.resource.location {
+            return extract(path);
+        }
+    }
+
+    let suffix = match &segment.resource.location {
+        Location::Local(path) => path
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default(),
+        Location::Remote(_) => String::new(),
+    };
+    let mut joined = tempfile::Builder::new()
+        .prefix("byte-me-segment")
+        .suffix(&suffix)
+        .tempfile()
+        .map_err(|err| AnalysisError::from(backend::DemuxError::Io(err)))?;
+
+    let copy = |joined: &mut tempfile::NamedTempFile| -> io::Result<()> {
+        if let Some(init) = &segment.init {
+            copy_resource(init, joined)?;
+        }
+        copy_resource(&segment.resource, joined)?;
+        joined.flush()
+    };
+    copy(&mut joined).map_err(|err| AnalysisError::from(backend::DemuxError::Io(err)))?;
+
+    extract(joined.path())
+}
+
+fn measure_segment(index: usize, segment: &Segment, ffprobe: Option<&Ffprobe>) -> SegmentAnalysis {
+    let mut analysis = SegmentAnalysis {
+        index: index as u32,
+        uri: segment.resource.uri.clone(),
+        start: segment.start,
+        duration: segment.duration,
+        size: 0,
+        bitrate: None,
+        starts_with_keyframe: None,
+        first_keyframe: None,
+        error: None,
+    };
+
+    match resource_size(&segment.resource) {
+        Ok(size) => {
+            analysis.size = size;
+            analysis.bitrate =
+                (segment.duration > 0.0).then(|| size as f64 * 8.0 / segment.duration);
+        }
+        Err(err) => {
+            warn!(uri = %segment.resource.uri, error = %err, "Segment is not readable");
+            analysis.error = Some(err.to_string());
+            return analysis;
+        }
+    }
+
+    // Frame data is a bonus: sizes alone still give the measured bitrate
+    match segment_frames(segment, ffprobe) {
+        Ok(data) => {
+            analysis.starts_with_keyframe = data.frames.first().map(|frame| frame.keyframe);
+            analysis.first_keyframe = data
+                .frames
+                .iter()
+                .find(|frame| frame.keyframe)
+                .and_then(|frame| frame.timestamp);
+        }
+        Err(err) => {
+            debug!(uri = %segment.resource.uri, error = %err, "No frame data for segment");
+        }
+    }
+
+    analysis
+}
+
+fn measure_variant(variant: Variant, ffprobe: Option<&Ffprobe>) -> VariantAnalysis {
+    let segments: Vec<SegmentAnalysis> = variant
+        .segments
+        .iter()
+        .enumerate()
+        .map(|(index, segment)| measure_segment(index, segment, ffprobe))
+        .collect();
+
+    let measured: Vec<&SegmentAnalysis> = segments
+        .iter()
+        .filter(|segment| segment.error.is_none() && segment.duration > 0.0)
+        .collect();
+    let total_duration: f64 = measured.iter().map(|segment| segment.duration).sum();
+    let total_bits: f64 = measured
+        .iter()
+        .map(|segment| segment.size as f64 * 8.0)
+        .sum();
+
+    let measured_average = (total_duration > 0.0).then(|| total_bits / total_duration);
+    let measured_peak = measured
+        .iter()
+        .filter_map(|segment| segment.bitrate)
+        .reduce(f64::max);
+
+    VariantAnalysis {
+        id: variant.id,
+        declared_bandwidth: variant.bandwidth,
+        average_bandwidth: variant.average_bandwidth,
+        resolution: variant.resolution,
+        codecs: variant.codecs,
+        measured_average,
+        measured_peak,
+        bandwidth_ratio: measured_peak
+            .zip(variant.bandwidth)
+            .filter(|(_, declared)| *declared > 0)
+            .map(|(peak, declared)| peak / declared as f64),
+        segments,
+        error: variant.error,
+    }
+}
+
+/// Finds segment indices whose boundaries don't land on the same keyframe in every
+/// rendition. Returns `None` when fewer than two renditions have keyframe data.
+pub fn misaligned_segments(variants: &[VariantAnalysis]) -> Option<Vec<u32>> {
+    let with_keyframes: Vec<&VariantAnalysis> = variants
+        .iter()
+        .filter(|variant| {
+            variant
+                .segments
+                .iter()
+                .any(|segment| segment.starts_with_keyframe.is_some())
+        })
+        .collect();
+    if with_keyframes.len() < 2 {
+        return None;
+    }
+
+    let count = with_keyframes
+        .iter()
+        .map(|variant| variant.segments.len())
+        .max()
+        .unwrap_or(0);
+    let misaligned = (0..count)
+        .filter(|&index| {
+            let segments: Vec<&SegmentAnalysis> = with_keyframes
+                .iter()
+                .filter_map(|variant| variant.segments.get(index))
+                .collect();
+            let starts_on_key = segments
+                .iter()
+                .all(|segment| segment.starts_with_keyframe != Some(false));
+
+            let keyframes: Vec<f64> = segments
+                .iter()
+                .filter_map(|segment| segment.first_keyframe)
+                .collect();
+            let spread = keyframes.iter().copied().reduce(f64::max).unwrap_or(0.0)
+                - keyframes.iter().copied().reduce(f64::min).unwrap_or(0.0);
+
+            !starts_on_key || spread > ALIGNMENT_TOLERANCE
+        })
+        .map(|index| index as u32)
+        .collect();
+
+    Some(misaligned)
+}
+
+/// Parses the manifest at `path` and measures every locally available segment.
+///
+/// Without ffprobe, keyframes are only found in segments a native backend can read.
+#[instrument(skip(path, ffprobe), fields(path = %path.display()))]
+pub fn analyze(path: &Path, ffprobe: Option<&Ffprobe>) -> Result<ManifestAnalysis, ManifestError> {
+    let (kind, variants) = parse(path)?;
+    info!(kind = ?kind, variants = variants.len(), "Parsed manifest");
+
+    let variants: Vec<VariantAnalysis> = variants
+        .into_iter()
+        .map(|variant| measure_variant(variant, ffprobe))
+        .collect();
+    let misaligned = misaligned_segments(&variants);
+
+    Ok(ManifestAnalysis {
+        id: file_name(path),
+        kind,
+        keyframes_aligned: misaligned.as_ref().map(|indices| indices.is_empty()),
+        misaligned_segments: misaligned.unwrap_or_default(),
+        variants,
+    })
+}
@@ -0,0 +1,467 @@
+//! DASH MPD parsing (ISO/IEC 23009-1).
+//!
+//! Supports `SegmentTemplate` (with or without a `SegmentTimeline`), `SegmentList` and
+//! single-file representations. Only the first `Period` is analyzed.
+
+use super::{ByteRange, Location, ManifestError, Resource, Segment, Variant};
+use roxmltree::{Document, Node};
+use tracing::debug;
+
+/// Upper bound on segments enumerated from a template, in total across its timeline.
+const MAX_TEMPLATE_SEGMENTS: u64 = 100_000;
+
+/// Widest `%0Nd` padding honored in a template; `u64` never needs more than 20 digits.
+const MAX_TEMPLATE_WIDTH: usize = 20;
+
+/// Parses an ISO 8601 duration such as `PT1M30.5S` into seconds.
+pub fn parse_duration(value: &str) -> Option<f64> {
+    let rest = value.trim().strip_prefix('P')?;
+    let (date, time) = rest.split_once('T').unwrap_or((rest, ""));
+
+    let mut seconds = 0.0;
+    let mut accumulate = |part: &str, units: &[(char, f64)]| -> Option<()> {
+        let mut number = String::new();
+        for c in part.chars() {
+            if c.is_ascii_digit() || c == '.' {
+                number.push(c);
+                continue;
+            }
+            let scale = units.iter().find(|(unit, _)| *unit == c)?.1;
+            seconds += number.parse::<f64>().ok()? * scale;
+            number.clear();
+        }
+        number.is_empty().then_some(())
+    };
+
+    accumulate(
+        date,
+        &[
+            ('Y', 365.0 * 86400.0),
+            ('M', 30.0 * 86400.0),
+            ('D', 86400.0),
+        ],
+    )?;
+    accumulate(time, &[('H', 3600.0), ('M', 60.0), ('S', 1.0)])?;
+    Some(seconds)
+}
+
+/// Parses an HTTP-style `first-last` byte range.
+fn parse_range(value: &str) -> Option<ByteRange> {
+    let (first, last) = value.split_once('-')?;
+    let offset = first.trim().parse().ok()?;
+    let last: Option<u64> = match last.trim() {
+        "" => None,
+        last => Some(last.parse().ok()?),
+    };
+    Some(ByteRange {
+        offset,
+        length: last.map(|last| last.saturating_sub(offset).saturating_add(1)),
+    })
+}
+
+/// Expands `$RepresentationID$`, `$Bandwidth$`, `$Number$` and `$Time$` identifiers,
+/// including printf-style widths such as `$Number%05d$`.
+pub fn fill_template(
+    template: &str,
+    representation: &str,
+    bandwidth: Option<u64>,
+    number: u64,
+    time: u64,
+) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut parts = template.split('$');
+    out.push_str(parts.next().unwrap_or_default());
+
+    // Identifiers sit between pairs of `$`, so odd parts are identifiers
+    let mut identifier = true;
+    for part in parts {
+        if !identifier {
+            out.push_str(part);
+            identifier = true;
+            continue;
+        }
+        identifier = false;
+
+        let (name, format) = part.split_once('%').unwrap_or((part, ""));
+        let width = format
+            .strip_prefix('0')
+            .and_then(|format| format.strip_suffix('d'))
+            .and_then(|width| width.parse::<usize>().ok())
+            .unwrap_or(0)
+            .min(MAX_TEMPLATE_WIDTH);
+        let value = match name {
+            "" => {
+                out.push('$');
+                continue;
+            }
+            "RepresentationID" => {
+                out.push_str(representation);
+                continue;
+            }
+            "Bandwidth" => bandwidth.unwrap_or(0),
+            "Number" => number,
+            "Time" => time,
+            other => {
+                // Unknown identifiers are left untouched
+                out.push('$');
+                out.push_str(other);
+                out.push('$');
+                continue;
+            }
+        };
+        out.push_str(&format!("{value:0width$}"));
+    }
+
+    out
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+/// Resolves the `BaseURL` chain down to `node`.
+fn base_location(base: &Location, node: Node) -> Location {
+    match child(node, "BaseURL").and_then(|url| url.text()) {
+        Some(url) => base.join(url.trim()),
+        None => base.clone(),
+    }
+}
+
+/// The nearest segment-addressing element of `name`, from most to least specific.
+fn inherited<'a, 'input>(scopes: &[Node<'a, 'input>], name: &str) -> Vec<Node<'a, 'input>> {
+    scopes
+        .iter()
+        .filter_map(|scope| child(*scope, name))
+        .collect()
+}
+
+/// Looks up an attribute across inherited elements, most specific first.
+fn attribute<'a>(elements: &[Node<'a, '_>], name: &str) -> Option<&'a str> {
+    elements.iter().find_map(|element| element.attribute(name))
+}
+
+fn number<T: std::str::FromStr>(value: Option<&str>) -> Option<T> {
+    value.and_then(|value| value.trim().parse().ok())
+}
+
+struct Context<'a> {
+    id: &'a str,
+    bandwidth: Option<u64>,
+    base: Location,
+    period_duration: Option<f64>,
+}
+
+impl Context<'_> {
+    fn resource(&self, uri: &str, range: Option<ByteRange>) -> Resource {
+        Resource {
+            uri: uri.to_string(),
+            location: self.base.join(uri),
+            range,
+        }
+    }
+}
+
+pub fn parse(contents: &str, location: &Location) -> Result<Vec<Variant>, ManifestError> {
+    let document = Document::parse(contents)?;
+    let mpd = document.root_element();
+    if mpd.tag_name().name() != "MPD" {
+        return Err(ManifestError::Invalid(
+            "root element is not MPD".to_string(),
+        ));
+    }
+
+    let period = child(mpd, "Period")
+        .ok_or_else(|| ManifestError::Invalid("MPD has no Period".to_string()))?;
+    if children(mpd, "Period").count() > 1 {
+        debug!("Only the first Period is analyzed");
+    }
+
+    let period_duration = period
+        .attribute("duration")
+        .and_then(parse_duration)
+        .or_else(|| {
+            let total = parse_duration(mpd.attribute("mediaPresentationDuration")?)?;
+            let start = period.attribute("start").and_then(parse_duration);
+            Some(total - start.unwrap_or(0.0))
+        });
+
+    let mpd_dir = location.sibling("");
+    let period_base = base_location(&base_location(&mpd_dir, mpd), period);
+
+    let mut video = Vec::new();
+    let mut other = Vec::new();
+    for adaptation in children(period, "AdaptationSet") {
+        let adaptation_base = base_location(&period_base, adaptation);
+        for representation in children(adaptation, "Representation") {
+            let mime = representation
+                .attribute("mimeType")
+                .or_else(|| adaptation.attribute("mimeType"))
+                .unwrap_or_default();
+            let is_video = adaptation.attribute("contentType") == Some("video")
+                || mime.starts_with("video/")
+                || representation.attribute("width").is_some();
+
+            let variant = parse_representation(
+                [representation, adaptation, period],
+                &adaptation_base,
+                period_duration,
+            );
+            if is_video {
+                video.push(variant);
+            } else {
+                other.push(variant);
+            }
+        }
+    }
+
+    // Bitrate ladders and keyframe alignment only make sense across video renditions
+    Ok(if video.is_empty() { other } else { video })
+}
+
+fn parse_representation(
+    scopes: [Node; 3],
+    adaptation_base: &Location,
+    period_duration: Option<f64>,
+) -> Variant {
+    let [representation, adaptation, _] = scopes;
+    let id = representation.attribute("id").unwrap_or("unnamed");
+    let bandwidth = number(representation.attribute("bandwidth"));
+    let width = representation
+        .attribute("width")
+        .or_else(|| adaptation.attribute("width"));
+    let height = representation
+        .attribute("height")
+        .or_else(|| adaptation.attribute("height"));
+
+    let context = Context {
+        id,
+        bandwidth,
+        base: base_location(adaptation_base, representation),
+        period_duration,
+    };
+
+    let mut variant = Variant {
+        id: id.to_string(),
+        bandwidth,
+        resolution: width.zip(height).map(|(w, h)| format!("{w}x{h}")),
+        codecs: representation
+            .attribute("codecs")
+            .or_else(|| adaptation.attribute("codecs"))
+            .map(str::to_string),
+        ..Variant::default()
+    };
+
+    let templates = inherited(&scopes, "SegmentTemplate");
+    let lists = inherited(&scopes, "SegmentList");
+    let segments = if !templates.is_empty() {
+        template_segments(&templates, &context)
+    } else if !lists.is_empty() {
+        list_segments(&lists, &context)
+    } else {
+        // The whole representation is one file at its BaseURL
+        match &context.base {
+            Location::Local(path) if path.is_file() => Ok(vec![Segment {
+                resource: Resource {
+                    uri: path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                    location: context.base.clone(),
+                    range: None,
+                },
+                init: None,
+                start: 0.0,
+                duration: period_duration.unwrap_or(0.0),
+            }]),
+            _ => Err("Representation has no segment information".to_string()),
+        }
+    };
+
+    match segments {
+        Ok(segments) => variant.segments = segments,
+        Err(err) => variant.error = Some(err),
+    }
+    debug!(id = %variant.id, segments = variant.segments.len(), "Parsed representation");
+    variant
+}
+
+/// Segment times and numbers as `(time, duration)` in timescale units.
+fn timeline(element: Node, period_end: Option<u64>) -> Vec<(u64, u64)> {
+    let mut entries = Vec::new();
+    let mut time = 0u64;
+
+    let elements: Vec<Node> = children(element, "S").collect();
+    for (index, entry) in elements.iter().enumerate() {
+        if let Some(start) = number(entry.attribute("t")) {
+            time = start;
+        }
+        let Some(duration) = number::<u64>(entry.attribute("d")).filter(|d| *d > 0) else {
+            continue;
+        };
+        let repeat: i64 = number(entry.attribute("r")).unwrap_or(0);
+
+        // A negative repeat runs until the next S element or the end of the period
+        let end = if repeat < 0 {
+            elements
+                .get(index + 1)
+                .and_then(|next| number(next.attribute("t")))
+                .or(period_end)
+        } else {
+            None
+        };
+        let count = match (repeat, end) {
+            (repeat, _) if repeat >= 0 => repeat as u64 + 1,
+            (_, Some(end)) => end.saturating_sub(time).div_ceil(duration),
+            (_, None) => 1,
+        };
+
+        for _ in 0..count {
+            if entries.len() as u64 >= MAX_TEMPLATE_SEGMENTS {
+                debug!(limit = MAX_TEMPLATE_SEGMENTS, "Segment timeline truncated");
+                return entries;
+            }
+            entries.push((time, duration));
+            let Some(next) = time.checked_add(duration) else {
+                return entries;
+            };
+            time = next;
+        }
+    }
+
+    entries
+}
+
+/// Start of the `index`th fixed-duration segment, saturating on absurd manifests.
+fn segment_time(offset: u64, index: u64, duration: u64) -> u64 {
+    index.saturating_mul(duration).saturating_add(offset)
+}
+
+fn template_segments(templates: &[Node], context: &Context) -> Result<Vec<Segment>, String> {
+    let media = attribute(templates, "media").ok_or("SegmentTemplate has no media")?;
+    let timescale = number::<u64>(attribute(templates, "timescale"))
+        .filter(|scale| *scale > 0)
+        .unwrap_or(1);
+    let start_number = number::<u64>(attribute(templates, "startNumber")).unwrap_or(1);
+    let offset = number::<u64>(attribute(templates, "presentationTimeOffset")).unwrap_or(0);
+    let period_end = context
+        .period_duration
+        .map(|duration| offset.saturating_add((duration * timescale as f64).round() as u64));
+
+    let init = attribute(templates, "initialization").map(|template| {
+        let uri = fill_template(template, context.id, context.bandwidth, 0, 0);
+        context.resource(&uri, None)
+    });
+
+    let entries = match templates
+        .iter()
+        .find_map(|template| child(*template, "SegmentTimeline"))
+    {
+        Some(element) => timeline(element, period_end),
+        None => {
+            let duration = number::<u64>(attribute(templates, "duration"))
+                .filter(|duration| *duration > 0)
+                .ok_or("SegmentTemplate has neither a timeline nor a duration")?;
+            let count = match context.period_duration {
+                Some(period) => ((period * timescale as f64) / duration as f64).ceil() as u64,
+                // Without a known duration, enumerate until a segment is missing
+                None => (0..MAX_TEMPLATE_SEGMENTS)
+                    .take_while(|&index| {
+                        let uri = fill_template(
+                            media,
+                            context.id,
+                            context.bandwidth,
+                            start_number.saturating_add(index),
+                            segment_time(offset, index, duration),
+                        );
+                        matches!(context.base.join(&uri), Location::Local(path) if path.exists())
+                    })
+                    .count() as u64,
+            };
+            (0..count.min(MAX_TEMPLATE_SEGMENTS))
+                .map(|index| (segment_time(offset, index, duration), duration))
+                .collect()
+        }
+    };
+
+    Ok(entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, (time, duration))| {
+            let uri = fill_template(
+                media,
+                context.id,
+                context.bandwidth,
+                start_number.saturating_add(index as u64),
+                time,
+            );
+            Segment {
+                resource: context.resource(&uri, None),
+                init: init.clone(),
+                start: time.saturating_sub(offset) as f64 / timescale as f64,
+                duration: duration as f64 / timescale as f64,
+            }
+        })
+        .collect())
+}
+
+fn list_segments(lists: &[Node], context: &Context) -> Result<Vec<Segment>, String> {
+    let list = lists[0];
+    let timescale = number::<u64>(attribute(lists, "timescale"))
+        .filter(|scale| *scale > 0)
+        .unwrap_or(1);
+    let duration = number::<u64>(attribute(lists, "duration"))
+        .map(|duration| duration as f64 / timescale as f64)
+        .unwrap_or(0.0);
+
+    let init = lists
+        .iter()
+        .find_map(|list| child(*list, "Initialization"))
+        .map(|element| {
+            let range = element.attribute("range").and_then(parse_range);
+            match element.attribute("sourceURL") {
+                Some(uri) => context.resource(uri, range),
+                // Without a URL, initialization data lives in the representation's file
+                None => Resource {
+                    uri: String::new(),
+                    location: context.base.clone(),
+                    range,
+                },
+            }
+        });
+
+    let segments: Vec<Segment> = children(list, "SegmentURL")
+        .enumerate()
+        .map(|(index, element)| {
+            let range = element.attribute("mediaRange").and_then(parse_range);
+            let resource = match element.attribute("media") {
+                Some(uri) => context.resource(uri, range),
+                None => Resource {
+                    uri: format!("#{index}"),
+                    location: context.base.clone(),
+                    range,
+                },
+            };
+            Segment {
+                resource,
+                init: init.clone(),
+                start: index as f64 * duration,
+                duration,
+            }
+        })
+        .collect();
+
+    if segments.is_empty() {
+        return Err("SegmentList has no SegmentURL entries".to_string());
+    }
+    Ok(segments)
+}
@@ -0,0 +1,179 @@
+//! HLS playlist parsing (RFC 8216).
+
+use super::{ByteRange, Location, ManifestError, Resource, Segment, Variant};
+use std::collections::HashMap;
+use std::fs;
+use tracing::{debug, warn};
+
+/// Parses an `ATTRIBUTE=value,...` list, unquoting quoted values.
+pub fn parse_attributes(list: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = list.trim();
+
+    while !rest.is_empty() {
+        let Some((key, after)) = rest.split_once('=') else {
+            break;
+        };
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((value, after)) => (value, after),
+                None => (quoted, ""),
+            },
+            None => after
+                .split_once(',')
+                .map_or((after, ""), |(value, after)| (value, after)),
+        };
+
+        attributes.insert(key.trim().to_string(), value.to_string());
+        rest = after.trim_start_matches(',').trim_start();
+    }
+
+    attributes
+}
+
+/// Parses `length[@offset]`; without an offset the range continues from `next_offset`.
+fn parse_byte_range(value: &str, next_offset: u64) -> Option<ByteRange> {
+    let (length, offset) = match value.split_once('@') {
+        Some((length, offset)) => (length, offset.trim().parse().ok()?),
+        None => (value, next_offset),
+    };
+    Some(ByteRange {
+        offset,
+        length: Some(length.trim().parse().ok()?),
+    })
+}
+
+fn check_header(contents: &str) -> Result<(), ManifestError> {
+    let first = contents
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty());
+    if first != Some("#EXTM3U") {
+        return Err(ManifestError::Invalid("missing #EXTM3U header".to_string()));
+    }
+    Ok(())
+}
+
+/// Parses a playlist at `location`; master playlists have each variant playlist loaded.
+pub fn parse(
+    contents: &str,
+    location: &Location,
+    name: &str,
+) -> Result<Vec<Variant>, ManifestError> {
+    check_header(contents)?;
+
+    if !contents.contains("#EXT-X-STREAM-INF") {
+        return Ok(vec![Variant {
+            id: name.to_string(),
+            segments: parse_media(contents, location),
+            ..Variant::default()
+        }]);
+    }
+
+    let mut variants = Vec::new();
+    let mut pending: Option<HashMap<String, String>> = None;
+    for line in contents.lines().map(str::trim) {
+        if let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+            pending = Some(parse_attributes(attributes));
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some(attributes) = pending.take() else {
+            continue;
+        };
+
+        let number = |key: &str| attributes.get(key).and_then(|value| value.parse().ok());
+        let mut variant = Variant {
+            id: line.to_string(),
+            bandwidth: number("BANDWIDTH"),
+            average_bandwidth: number("AVERAGE-BANDWIDTH"),
+            resolution: attributes.get("RESOLUTION").cloned(),
+            codecs: attributes.get("CODECS").cloned(),
+            ..Variant::default()
+        };
+
+        match location.sibling(line) {
+            Location::Local(path) => match fs::read_to_string(&path) {
+                Ok(media) => match check_header(&media) {
+                    Ok(()) => {
+                        variant.segments = parse_media(&media, &Location::Local(path));
+                    }
+                    Err(err) => variant.error = Some(err.to_string()),
+                },
+                Err(err) => {
+                    warn!(uri = %line, error = %err, "Could not read variant playlist");
+                    variant.error = Some(format!("Could not read variant playlist: {err}"));
+                }
+            },
+            Location::Remote(_) => {
+                variant.error = Some("Remote playlists are not supported".to_string());
+            }
+        }
+
+        debug!(id = %variant.id, segments = variant.segments.len(), "Parsed variant");
+        variants.push(variant);
+    }
+
+    Ok(variants)
+}
+
+/// Parses the segments of a media playlist.
+fn parse_media(contents: &str, location: &Location) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut start = 0.0;
+    let mut duration = None;
+    let mut range = None;
+    let mut init = None;
+    // Where a range without an explicit offset continues from, per resource
+    let mut next_offsets: HashMap<String, u64> = HashMap::new();
+
+    for line in contents.lines().map(str::trim) {
+        if let Some(value) = line.strip_prefix("#EXTINF:") {
+            duration = value
+                .split(',')
+                .next()
+                .and_then(|value| value.trim().parse::<f64>().ok());
+        } else if let Some(value) = line.strip_prefix("#EXT-X-BYTERANGE:") {
+            range = Some(value.to_string());
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-MAP:") {
+            let attributes = parse_attributes(attributes);
+            init = attributes.get("URI").map(|uri| Resource {
+                uri: uri.clone(),
+                location: location.sibling(uri),
+                range: attributes
+                    .get("BYTERANGE")
+                    .and_then(|value| parse_byte_range(value, 0)),
+            });
+        } else if !line.is_empty() && !line.starts_with('#') {
+            let next_offset = next_offsets.get(line).copied().unwrap_or(0);
+            let range = range
+                .take()
+                .and_then(|value| parse_byte_range(&value, next_offset));
+            if let Some(ByteRange {
+                offset,
+                length: Some(length),
+            }) = range
+            {
+                next_offsets.insert(line.to_string(), offset.saturating_add(length));
+            }
+
+            let duration = duration.take().unwrap_or(0.0);
+            segments.push(Segment {
+                resource: Resource {
+                    uri: line.to_string(),
+                    location: location.sibling(line),
+                    range,
+                },
+                init: init.clone(),
+                start,
+                duration,
+            });
+            start += duration;
+        }
+    }
+
+    segments
+}
@@ -1,168 +1,177 @@
-use crate::models::MediaType;
+use crate::models::{Confidence, Detection, DetectionMethod, MediaType};
+use crate::registry;
+use crate::ts::detect_layout;
 use std::{fs::File, io::Read, path::Path};
 use tracing::{debug, instrument, trace, warn};
 
 #[instrument(skip(path), fields(path = %path.display()))]
 pub fn detect_media_type(path: &Path) -> MediaType {
+    detect(path).media_type
+}
+
+/// Detects the media type of a file on disk, recording how it was determined.
+#[instrument(skip(path), fields(path = %path.display()))]
+pub fn detect(path: &Path) -> Detection {
     debug!("Starting media type detection");
 
+    let mut buffer = [0; 512];
+    let mut bytes_read = 0;
     // First try to detect using infer crate (magic number detection)
     if let Ok(mut file) = File::open(path) {
-        let mut buffer = [0; 512];
-        if let Ok(bytes_read) = file.read(&mut buffer) {
-            trace!(bytes_read = bytes_read, "Read file header for magic number detection");
-            
-            if let Some(kind) = infer::get(&buffer[..bytes_read]) {
-                let mime_type = kind.mime_type();
-                debug!(mime_type = %mime_type, "Detected MIME type from magic numbers");
-                
-                let media_type = match mime_type {
-                    // Audio types
-                    "audio/mpeg" | "audio/mp3" | "audio/m4a" | "audio/ogg" | "audio/x-flac"
-                    | "audio/x-wav" | "audio/amr" | "audio/aac" | "audio/x-aiff"
-                    | "audio/x-dsf" | "audio/x-ape" | "audio/midi" => MediaType::Audio,
-
-                    // Video types
-                    "video/mp4" | "video/x-m4v" | "video/x-matroska" | "video/webm"
-                    | "video/quicktime" | "video/x-msvideo" | "video/x-ms-wmv" | "video/mpeg"
-                    | "video/x-flv" => MediaType::Video,
-
-                    // Image types
-                    "image/jpeg"
-                    | "image/png"
-                    | "image/gif"
-                    | "image/webp"
-                    | "image/x-canon-cr2"
-                    | "image/tiff"
-                    | "image/bmp"
-                    | "image/heif"
-                    | "image/avif"
-                    | "image/vnd.ms-photo"
-                    | "image/vnd.adobe.photoshop"
-                    | "image/vnd.microsoft.icon"
-                    | "image/openraster"
-                    | "image/vnd.djvu" => MediaType::Image,
-
-                    // Document types
-                    "application/pdf"
-                    | "application/rtf"
-                    | "application/msword"
-                    | "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
-                    | "application/vnd.ms-excel"
-                    | "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
-                    | "application/vnd.ms-powerpoint"
-                    | "application/vnd.openxmlformats-officedocument.presentationml.presentation"
-                    | "application/vnd.oasis.opendocument.text"
-                    | "application/vnd.oasis.opendocument.spreadsheet"
-                    | "application/vnd.oasis.opendocument.presentation" => MediaType::Document,
-
-                    // Archive types
-                    "application/zip"
-                    | "application/x-tar"
-                    | "application/vnd.rar"
-                    | "application/gzip"
-                    | "application/x-bzip2"
-                    | "application/vnd.bzip3"
-                    | "application/x-7z-compressed"
-                    | "application/x-xz"
-                    | "application/x-shockwave-flash"
-                    | "application/octet-stream"
-                    | "application/postscript"
-                    | "application/vnd.sqlite3"
-                    | "application/x-nintendo-nes-rom"
-                    | "application/x-google-chrome-extension"
-                    | "application/vnd.ms-cab-compressed"
-                    | "application/vnd.debian.binary-package"
-                    | "application/x-unix-archive"
-                    | "application/x-compress"
-                    | "application/x-lzip"
-                    | "application/x-rpm"
-                    | "application/dicom"
-                    | "application/zstd"
-                    | "application/x-lz4"
-                    | "application/x-ole-storage"
-                    | "application/x-cpio"
-                    | "application/x-par2"
-                    | "application/epub+zip"
-                    | "application/x-mobipocket-ebook" => MediaType::Archive,
-
-                    // Executable types
-                    "application/vnd.microsoft.portable-executable"
-                    | "application/x-executable"
-                    | "application/llvm"
-                    | "application/x-mach-binary"
-                    | "application/java"
-                    | "application/vnd.android.dex"
-                    | "application/vnd.android.dey"
-                    | "application/x-x509-ca-cert" => MediaType::Executable,
-
-                    // Library types (covered by executable types above, but keeping for clarity)
-                    _ => MediaType::Unknown,
-                };
-                
-                debug!(media_type = ?media_type, "Detected media type from magic numbers");
-                return media_type;
-            } else {
-                debug!("Magic number detection failed, falling back to extension-based detection");
+        match file.read(&mut buffer) {
+            Ok(read) => {
+                bytes_read = read;
+                trace!(bytes_read = bytes_read, "Read file header for magic number detection");
             }
-        } else {
-            warn!("Failed to read file for magic number detection");
+            Err(_) => warn!("Failed to read file for magic number detection"),
         }
     } else {
         warn!("Failed to open file for magic number detection");
     }
 
-    // Fallback to extension-based detection
-    if let Some(extension) = path.extension() {
-        let ext_str = extension.to_str().unwrap_or("").to_lowercase();
-        debug!(extension = %ext_str, "Detecting media type from file extension");
-        
-        let media_type = match ext_str.as_str() {
-            // Audio extensions
-            "mp3" | "wav" | "flac" | "ogg" | "m4a" | "aac" | "wma" | "mid" | "amr" | "aiff"
-            | "dsf" | "ape" => MediaType::Audio,
-
-            // Video extensions
-            "mp4" | "mkv" | "webm" | "mov" | "avi" | "wmv" | "mpg" | "flv" | "m4v" => {
-                MediaType::Video
-            }
+    detect_from_bytes(&buffer[..bytes_read], &path.to_string_lossy())
+}
 
-            // Image extensions
-            "gif" | "png" | "jpg" | "jpeg" | "bmp" | "tiff" | "webp" | "cr2" | "heif" | "avif"
-            | "jxr" | "psd" | "ico" | "ora" | "djvu" => MediaType::Image,
+/// Whether `line` is a SubRip cue timing, e.g. `00:00:01,000 --> 00:00:04,000`.
+fn is_srt_timing(line: &str) -> bool {
+    let bytes = line.as_bytes();
+    line.contains("-->")
+        && bytes.len() >= 12
+        && bytes[2] == b':'
+        && bytes[5] == b':'
+        && matches!(bytes[8], b',' | b'.')
+        && bytes[..8].iter().all(|b| b.is_ascii_digit() || *b == b':')
+}
 
-            // Document extensions
-            "txt" | "md" | "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "odt"
-            | "ods" | "odp" | "rtf" => MediaType::Document,
+/// Recognizes the text-based subtitle and playlist formats, which have no magic number
+/// `infer` knows, as well as PGS subtitles.
+fn sniff_text_format(header: &[u8]) -> Option<&'static str> {
+    // Presentation graphics segments: "PG", two timestamps, then the segment type
+    if header.len() >= 11 && header.starts_with(b"PG") && matches!(header[10], 0x14..=0x18 | 0x80) {
+        return Some("application/x-pgs");
+    }
 
-            // Archive extensions
-            "zip" | "rar" | "7z" | "tar" | "gz" | "bz2" | "bz3" | "xz" | "swf" | "sqlite"
-            | "nes" | "crx" | "cab" | "deb" | "ar" | "Z" | "lz" | "rpm" | "dcm" | "zst" | "lz4"
-            | "cpio" | "par2" | "epub" | "mobi" => MediaType::Archive,
+    let text = String::from_utf8_lossy(header);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if text.starts_with("#EXTM3U") {
+        Some("application/vnd.apple.mpegurl")
+    } else if text.starts_with("WEBVTT") {
+        Some("text/vtt")
+    } else if text.starts_with("[Script Info]") {
+        Some("text/x-ssa")
+    } else if text.starts_with("<MPD") || (text.starts_with("<?xml") && text.contains("<MPD")) {
+        Some("application/dash+xml")
+    } else {
+        let mut lines = text.lines().map(str::trim);
+        let is_srt = lines
+            .next()
+            .is_some_and(|index| !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()))
+            && lines.next().is_some_and(is_srt_timing);
+        is_srt.then_some("application/x-subrip")
+    }
+}
 
-            // Executable extensions
-            "exe" | "dll" | "msi" | "dmg" | "pkg" | "app" | "elf" | "bc" | "mach" | "class"
-            | "dex" | "dey" | "der" | "obj" => MediaType::Executable,
+/// Identifies content from its first bytes, returning its MIME type.
+fn sniff_mime_type(header: &[u8]) -> Option<String> {
+    if let Some(mime_type) = sniff_text_format(header) {
+        debug!(mime_type = %mime_type, "Detected text format from content");
+        Some(mime_type.to_string())
+    } else if let Some(kind) = infer::get(header) {
+        debug!(mime_type = %kind.mime_type(), "Detected MIME type from magic numbers");
+        Some(kind.mime_type().to_string())
+    } else if let Some(layout) = detect_layout(header) {
+        // Transport streams have no magic number, only a sync byte every packet
+        debug!(packet_size = layout.packet_size, "Detected MPEG transport stream");
+        Some("video/mp2t".to_string())
+    } else {
+        None
+    }
+}
 
-            // Library extensions
-            "so" | "dylib" => MediaType::Library,
+fn extension(name: &str) -> Option<String> {
+    Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase())
+}
 
-            _ => MediaType::Unknown,
-        };
-        
-        debug!(media_type = ?media_type, "Detected media type from extension");
-        media_type
-    } else {
-        debug!("No file extension found, returning Unknown");
-        MediaType::Unknown
+/// Detects the media type from the first bytes of some content, falling back to the
+/// extension in `name`, and flags content that contradicts its extension.
+pub fn detect_from_bytes(header: &[u8], name: &str) -> Detection {
+    let extension = extension(name);
+    let by_extension = extension.as_deref().and_then(registry::lookup_extension);
+    let shown_extension = extension.as_deref().unwrap_or_default();
+    let sniffed = sniff_mime_type(header);
+    let by_content = sniffed.as_deref().and_then(registry::lookup_mime);
+
+    let detection = match (sniffed, by_content) {
+        (Some(mime_type), Some(media_type)) => {
+            let mismatch = by_extension
+                .filter(|expected| *expected != media_type)
+                .map(|expected| {
+                    format!(
+                        "Content is {mime_type}, but the .{shown_extension} extension suggests \
+                         {expected:?}"
+                    )
+                });
+            Detection {
+                media_type,
+                mime_type: Some(mime_type),
+                method: DetectionMethod::Magic,
+                confidence: if mismatch.is_some() {
+                    Confidence::Medium
+                } else {
+                    Confidence::High
+                },
+                mismatch,
+            }
+        }
+        // Recognized, but not as anything we classify (e.g. HTML): trust the name, warily
+        (Some(mime_type), None) => Detection {
+            mismatch: by_extension.as_ref().map(|expected| {
+                format!(
+                    "Content is {mime_type}, not the {expected:?} the .{shown_extension} \
+                     extension suggests"
+                )
+            }),
+            media_type: by_extension.unwrap_or(MediaType::Unknown),
+            mime_type: Some(mime_type),
+            method: DetectionMethod::Extension,
+            confidence: Confidence::Low,
+        },
+        (None, _) => Detection {
+            media_type: by_extension.unwrap_or(MediaType::Unknown),
+            mime_type: extension
+                .as_deref()
+                .and_then(registry::lookup_extension_mime)
+                .map(str::to_string),
+            method: DetectionMethod::Extension,
+            confidence: Confidence::Low,
+            mismatch: None,
+        },
+    };
+
+    if let Some(mismatch) = &detection.mismatch {
+        warn!(mismatch = %mismatch, "Content does not match extension");
     }
+    debug!(
+        media_type = ?detection.media_type,
+        method = ?detection.method,
+        confidence = ?detection.confidence,
+        "Detected media type"
+    );
+    detection
 }
 
 #[instrument(skip(media_type))]
 pub fn is_media_file(media_type: &MediaType) -> bool {
     let is_media = matches!(
         media_type,
-        MediaType::Audio | MediaType::Video | MediaType::Image
+        MediaType::Audio
+            | MediaType::Video
+            | MediaType::Image
+            | MediaType::Subtitle
+            | MediaType::Playlist
     );
     
     debug!(media_type = ?media_type, is_media = is_media, "Checking if file is media type");
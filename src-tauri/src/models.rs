@@ -1,11 +1,17 @@
+use crate::qc::QcSettings;
+use crate::tools::ToolSource;
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
-#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, TS)]
 pub enum MediaType {
     Audio,
     Video,
     Image,
+    /// Standalone subtitle files, e.g. SubRip or PGS.
+    Subtitle,
+    /// Playlists and manifests referencing other media, e.g. HLS or DASH.
+    Playlist,
     Document,
     Executable,
     Archive,
@@ -13,6 +19,38 @@ pub enum MediaType {
     Unknown,
 }
 
+/// How a media type was determined.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+pub enum DetectionMethod {
+    /// Magic numbers (or packet structure) in the content.
+    Magic,
+    /// The file extension alone.
+    Extension,
+    /// ffprobe recognized the container.
+    Ffprobe,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, TS)]
+pub enum Confidence {
+    /// Guessed from the name, or the content contradicts it.
+    Low,
+    /// The content was recognized, but the extension disagrees.
+    Medium,
+    /// The content was recognized and the extension agrees.
+    High,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, TS)]
+pub struct Detection {
+    pub media_type: MediaType,
+    pub mime_type: Option<String>,
+    pub method: DetectionMethod,
+    pub confidence: Confidence,
+    /// Set when the content and the extension point at different types, e.g. a `.mp4`
+    /// that is actually an HTML error page.
+    pub mismatch: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, TS)]
 pub struct StreamResult {
     pub path: String,
@@ -54,6 +92,8 @@ pub struct StreamResultError {
 // New types for simplified drop overlay
 #[derive(Serialize, Deserialize, Debug, Clone, TS)]
 pub struct File {
+    /// Full path or URL, so entries expanded from a dropped directory can be analyzed further.
+    pub path: String,
     pub filename: String,
     pub size: u32,
     pub candidacy: FileCandidacy,
@@ -64,6 +104,7 @@ pub enum FileCandidacy {
     Success {
         #[serde(rename = "type")]
         file_type: MediaType,
+        detection: Detection,
     },
     Error {
         reason: String,
@@ -76,6 +117,9 @@ pub enum FileCandidacy {
 pub struct BitrateFrame {
     pub frame_num: u32,
     pub packet_size: u64,
+    /// Presentation timestamp in seconds, when the source reports one.
+    pub timestamp: Option<f64>,
+    pub keyframe: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, TS)]
@@ -84,6 +128,378 @@ pub struct BitrateData {
     pub frames: Vec<BitrateFrame>,
 }
 
+// Compression efficiency types
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, TS)]
+pub struct EfficiencyFrame {
+    /// Index into [`BitrateData::frames`].
+    pub frame_num: u32,
+    pub timestamp: Option<f64>,
+    pub bits_per_pixel: f64,
+    /// The frame's size as a rate at the stream's frame rate, per million pixels.
+    pub bits_per_second_per_megapixel: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct EfficiencyAnalysis {
+    pub id: String,
+    pub width: u32,
+    pub height: u32,
+    /// From the stream header, or estimated from timestamps or duration.
+    pub frame_rate: Option<f64>,
+    pub frames: Vec<EfficiencyFrame>,
+    /// Average over all frames.
+    pub bits_per_pixel: f64,
+    pub max_bits_per_pixel: f64,
+    /// Average over keyframes only.
+    pub keyframe_bits_per_pixel: Option<f64>,
+    /// Average bitrate in bits per second.
+    pub bitrate: Option<f64>,
+    pub bits_per_second_per_megapixel: Option<f64>,
+}
+
+// Still and animated image analysis types
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    WebP,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+pub enum ColorType {
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+    /// Palette-based; the bit depth is that of the palette indices.
+    Indexed,
+    YCbCr,
+    Cmyk,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, TS)]
+pub struct ExifInfo {
+    /// EXIF orientation, 1 (upright) to 8.
+    pub orientation: Option<u16>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    /// When the picture was taken, as written by the camera (`YYYY:MM:DD HH:MM:SS`).
+    pub date_time: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, TS)]
+pub struct AnimationFrame {
+    pub width: u32,
+    pub height: u32,
+    /// How long the frame is shown, in milliseconds.
+    pub delay_ms: u32,
+    /// Compressed size of the frame's image data in bytes.
+    #[ts(type = "number")]
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, TS)]
+pub struct Animation {
+    pub frame_count: u32,
+    /// How many times the animation plays; `0` means forever.
+    pub plays: u32,
+    #[ts(type = "number")]
+    pub duration_ms: u64,
+    pub frames: Vec<AnimationFrame>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, TS)]
+pub struct ImageAnalysis {
+    pub id: String,
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    pub color_type: ColorType,
+    /// Bits per channel (or per palette index).
+    pub bit_depth: u8,
+    pub has_icc_profile: bool,
+    pub exif: Option<ExifInfo>,
+    /// Present for images with more than one frame.
+    pub animation: Option<Animation>,
+}
+
+// Loudness analysis types
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct LoudnessPoint {
+    /// Seconds from the start of the stream.
+    pub time: f64,
+    /// Momentary loudness (400 ms window) in LUFS.
+    pub momentary: Option<f64>,
+    /// Short-term loudness (3 s window) in LUFS.
+    pub short_term: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct LoudnessAnalysis {
+    pub id: String,
+    /// Index among the file's audio streams.
+    pub audio_stream: u32,
+    /// Integrated loudness in LUFS; absent when nothing rose above the -70 LUFS gate.
+    pub integrated: Option<f64>,
+    /// Loudness range in LU.
+    pub loudness_range: Option<f64>,
+    /// Highest true peak across all channels in dBTP.
+    pub true_peak: Option<f64>,
+    /// One point per 100 ms block.
+    pub points: Vec<LoudnessPoint>,
+}
+
+// Waveform types
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, TS)]
+pub struct WaveformPeak {
+    pub min: f32,
+    pub max: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct Waveform {
+    pub id: String,
+    /// Index among the file's audio streams.
+    pub audio_stream: u32,
+    /// Buckets per second; bucket `n` starts at `n / resolution` seconds.
+    pub resolution: u32,
+    /// Lowest and highest sample of each bucket, between -1.0 and 1.0.
+    pub peaks: Vec<WaveformPeak>,
+}
+
+// Thumbnail types
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, TS)]
+pub enum ThumbnailFormat {
+    #[default]
+    Jpeg,
+    Png,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct Thumbnail {
+    /// The [`BitrateFrame::frame_num`] shown, for keyframe thumbnails.
+    pub frame_num: Option<u32>,
+    /// Seconds into the file.
+    pub timestamp: Option<f64>,
+    /// Image file in the thumbnail cache.
+    pub path: String,
+    /// The image as a `data:` URL, when requested inline.
+    pub data_url: Option<String>,
+}
+
+// Scene detection types
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, TS)]
+pub struct SceneScore {
+    /// Index into [`BitrateData::frames`] of the same file.
+    pub frame_num: u32,
+    pub timestamp: Option<f64>,
+    /// Difference from the previous frame, from 0 (identical) to 1.
+    pub score: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct SceneAnalysis {
+    pub id: String,
+    /// Scores above this count as cuts.
+    pub threshold: f64,
+    pub scores: Vec<SceneScore>,
+    pub cuts: Vec<SceneScore>,
+}
+
+// Objective quality types
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, TS)]
+pub struct QualityFrame {
+    /// Index into [`BitrateData::frames`] of the distorted file.
+    pub frame_num: u32,
+    pub timestamp: Option<f64>,
+    /// Average PSNR over all planes, in dB. `None` for identical frames.
+    pub psnr: Option<f64>,
+    /// SSIM over all planes, from 0 to 1.
+    pub ssim: Option<f64>,
+    /// VMAF score from 0 to 100, when libvmaf is available.
+    pub vmaf: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, TS)]
+pub struct QualitySummary {
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct QualityAnalysis {
+    /// The distorted file, whose frames the scores are keyed to.
+    pub id: String,
+    pub reference: String,
+    pub frames: Vec<QualityFrame>,
+    pub psnr: Option<QualitySummary>,
+    pub ssim: Option<QualitySummary>,
+    pub vmaf: Option<QualitySummary>,
+    /// Why VMAF scores are missing, e.g. ffmpeg built without libvmaf.
+    pub vmaf_unavailable: Option<String>,
+}
+
+// Quality control types
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct QcInterval {
+    /// Seconds, on the same timeline as [`BitrateFrame::timestamp`].
+    pub start: f64,
+    pub end: f64,
+    pub duration: f64,
+    /// Index into [`BitrateData::frames`] of the first frame in the interval.
+    pub start_frame: Option<u32>,
+    /// Index of the first frame after the interval; absent when it runs to the end.
+    pub end_frame: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct QcReport {
+    pub id: String,
+    /// The thresholds the intervals were detected with.
+    pub thresholds: QcSettings,
+    /// Black segments of the first video stream; empty when there is no video.
+    pub black: Vec<QcInterval>,
+    /// Silent gaps in the first audio stream; empty when there is no audio.
+    pub silence: Vec<QcInterval>,
+}
+
+// Transport stream analysis types
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub struct TsAnalysis {
+    pub id: String,
+    /// Bytes per packet on disk: 188, or 192/204 for timecoded and FEC variants.
+    pub packet_size: u32,
+    #[ts(type = "number")]
+    pub packet_count: u64,
+    /// Seconds covered by PCRs, when the stream carries any.
+    pub duration: Option<f64>,
+    /// Overall transport rate in bits per second, derived from PCRs.
+    pub bitrate: Option<f64>,
+    /// Width in seconds of each bucket in [`TsPid::bitrate`].
+    pub interval: f64,
+    pub programs: Vec<TsProgram>,
+    pub pids: Vec<TsPid>,
+    /// Fraction of packets on the null PID (0x1FFF).
+    pub null_ratio: f64,
+    #[ts(type = "number")]
+    pub continuity_errors: u64,
+    /// Packets with the transport error indicator set.
+    #[ts(type = "number")]
+    pub transport_errors: u64,
+    /// Times the packet sync byte was lost and had to be searched for.
+    #[ts(type = "number")]
+    pub sync_losses: u64,
+    pub pcr: Option<PcrStats>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub struct TsProgram {
+    pub program_number: u16,
+    pub pmt_pid: u16,
+    pub pcr_pid: Option<u16>,
+    pub streams: Vec<TsStream>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub struct TsStream {
+    pub pid: u16,
+    pub stream_type: u8,
+    pub codec: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub struct TsPid {
+    pub pid: u16,
+    /// What the PID carries, e.g. `PAT`, `PMT`, `H.264` or `Null`.
+    pub label: String,
+    #[ts(type = "number")]
+    pub packet_count: u64,
+    /// Average bits per second over the stream duration.
+    pub average_bitrate: Option<f64>,
+    /// Bits per second in each interval, empty when the stream has no PCRs.
+    pub bitrate: Vec<f64>,
+    #[ts(type = "number")]
+    pub continuity_errors: u64,
+    pub scrambled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub struct PcrStats {
+    pub pid: u16,
+    pub count: u32,
+    pub discontinuities: u32,
+    /// Longest gap between consecutive PCRs; the spec allows at most 40 ms.
+    pub max_interval_ms: f64,
+    /// Deviation of PCRs from a constant-rate fit of PCR against stream position.
+    pub max_jitter_ms: f64,
+    pub mean_jitter_ms: f64,
+}
+
+// Streaming manifest analysis types
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+pub enum ManifestKind {
+    Hls,
+    Dash,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub struct ManifestAnalysis {
+    pub id: String,
+    pub kind: ManifestKind,
+    pub variants: Vec<VariantAnalysis>,
+    /// Whether every segment boundary lands on the same keyframe in all renditions;
+    /// `None` when fewer than two renditions have keyframe data.
+    pub keyframes_aligned: Option<bool>,
+    pub misaligned_segments: Vec<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub struct VariantAnalysis {
+    /// Variant playlist URI (HLS) or Representation id (DASH).
+    pub id: String,
+    /// Declared peak bitrate: `BANDWIDTH` (HLS) or `@bandwidth` (DASH).
+    #[ts(type = "number | null")]
+    pub declared_bandwidth: Option<u64>,
+    #[ts(type = "number | null")]
+    pub average_bandwidth: Option<u64>,
+    pub resolution: Option<String>,
+    pub codecs: Option<String>,
+    /// Total segment bits over total segment duration.
+    pub measured_average: Option<f64>,
+    /// Highest single-segment bitrate.
+    pub measured_peak: Option<f64>,
+    /// Measured peak over declared bandwidth; above 1.0 means the manifest under-declares.
+    pub bandwidth_ratio: Option<f64>,
+    pub segments: Vec<SegmentAnalysis>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub struct SegmentAnalysis {
+    pub index: u32,
+    pub uri: String,
+    pub start: f64,
+    pub duration: f64,
+    #[ts(type = "number")]
+    pub size: u64,
+    /// Bits per second over the declared segment duration.
+    pub bitrate: Option<f64>,
+    pub starts_with_keyframe: Option<bool>,
+    /// Timestamp of the first keyframe, in stream time.
+    pub first_keyframe: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// The ffprobe binary in use, as reported to the frontend.
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub struct FfprobeInfo {
+    pub path: String,
+    pub version: String,
+    pub source: ToolSource,
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -95,9 +511,41 @@ mod tests {
         StreamResult::export_all_to("../src/bindings").expect("Failed to export bindings");
         StreamResultError::export_all_to("../src/bindings").expect("Failed to export bindings");
         MediaType::export_all_to("../src/bindings").expect("Failed to export bindings");
+        Detection::export_all_to("../src/bindings").expect("Failed to export bindings");
         File::export_all_to("../src/bindings").expect("Failed to export bindings");
         FileCandidacy::export_all_to("../src/bindings").expect("Failed to export bindings");
         BitrateFrame::export_all_to("../src/bindings").expect("Failed to export bindings");
         BitrateData::export_all_to("../src/bindings").expect("Failed to export bindings");
+        TsAnalysis::export_all_to("../src/bindings").expect("Failed to export bindings");
+        EfficiencyAnalysis::export_all_to("../src/bindings").expect("Failed to export bindings");
+        ImageAnalysis::export_all_to("../src/bindings").expect("Failed to export bindings");
+        LoudnessAnalysis::export_all_to("../src/bindings").expect("Failed to export bindings");
+        Waveform::export_all_to("../src/bindings").expect("Failed to export bindings");
+        Thumbnail::export_all_to("../src/bindings").expect("Failed to export bindings");
+        SceneAnalysis::export_all_to("../src/bindings").expect("Failed to export bindings");
+        QualityAnalysis::export_all_to("../src/bindings").expect("Failed to export bindings");
+        QcReport::export_all_to("../src/bindings").expect("Failed to export bindings");
+        ThumbnailFormat::export_all_to("../src/bindings").expect("Failed to export bindings");
+        ManifestAnalysis::export_all_to("../src/bindings").expect("Failed to export bindings");
+        FfprobeInfo::export_all_to("../src/bindings").expect("Failed to export bindings");
+        crate::cache::CacheSettings::export_all_to("../src/bindings")
+            .expect("Failed to export bindings");
+        crate::cache::CacheStats::export_all_to("../src/bindings")
+            .expect("Failed to export bindings");
+        crate::process::ProcessLimits::export_all_to("../src/bindings")
+            .expect("Failed to export bindings");
+        crate::remote::NetworkSettings::export_all_to("../src/bindings")
+            .expect("Failed to export bindings");
+        crate::scan::ScanSettings::export_all_to("../src/bindings")
+            .expect("Failed to export bindings");
+        crate::watch::WatchSettings::export_all_to("../src/bindings")
+            .expect("Failed to export bindings");
+        crate::watch::WatchEvent::export_all_to("../src/bindings")
+            .expect("Failed to export bindings");
+        crate::qc::QcSettings::export_all_to("../src/bindings").expect("Failed to export bindings");
+        crate::registry::MediaTypeSettings::export_all_to("../src/bindings")
+            .expect("Failed to export bindings");
+        crate::settings::Settings::export_all_to("../src/bindings")
+            .expect("Failed to export bindings");
     }
 }
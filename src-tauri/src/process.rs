@@ -0,0 +1,257 @@
+//! Bounded execution of external tools.
+//!
+//! A corrupt input can make ffprobe spin forever or flood its pipes, so every
+//! invocation runs under a wall-clock timeout with capped stdout and stderr.
+
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::{debug, warn};
+use ts_rs::TS;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+const READ_CHUNK: usize = 64 * 1024;
+
+/// Limits applied to each child process.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+#[serde(default)]
+pub struct ProcessLimits {
+    /// Wall-clock limit per invocation in seconds; `0` disables the timeout.
+    pub timeout_secs: u32,
+    /// Maximum bytes collected from stdout before the process is killed.
+    #[ts(type = "number")]
+    pub max_output_bytes: u64,
+    /// Maximum bytes of stderr retained for error messages; the rest is discarded.
+    #[ts(type = "number")]
+    pub max_stderr_bytes: u64,
+}
+
+impl Default for ProcessLimits {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 600,
+            max_output_bytes: 256 * 1024 * 1024,
+            max_stderr_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl ProcessLimits {
+    /// Tight limits for quick metadata queries such as `-version`.
+    pub fn quick() -> Self {
+        Self {
+            timeout_secs: 10,
+            max_output_bytes: 1024 * 1024,
+            ..Self::default()
+        }
+    }
+
+    /// Limits for ffmpeg passes that decode a whole file, which can take hours on a long
+    /// high-resolution encode, so only the output is capped.
+    pub fn decode() -> Self {
+        Self {
+            timeout_secs: 0,
+            ..Self::default()
+        }
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        (self.timeout_secs > 0).then(|| Duration::from_secs(u64::from(self.timeout_secs)))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ProcessError {
+    #[error("Failed to execute {program}: {source}")]
+    Spawn {
+        program: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{program} timed out after {} seconds", .timeout.as_secs())]
+    Timeout { program: String, timeout: Duration },
+    #[error("{program} produced more than {limit} bytes of output")]
+    OutputTooLarge { program: String, limit: u64 },
+    #[error("Failed to read output of {program}: {source}")]
+    Io {
+        program: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// A finished process whose output was collected within limits.
+#[derive(Debug)]
+pub struct ProcessOutput {
+    pub status: ExitStatus,
+    pub stdout: Vec<u8>,
+    /// Possibly truncated to [`ProcessLimits::max_stderr_bytes`].
+    pub stderr: String,
+}
+
+/// Runs `command` to completion, collecting stdout up to the configured maximum.
+pub fn run(command: Command, limits: &ProcessLimits) -> Result<ProcessOutput, ProcessError> {
+    let program = program_name(&command);
+    let mut stdout = Vec::new();
+
+    let (status, stderr) = stream(command, limits, |chunk| {
+        if (stdout.len() + chunk.len()) as u64 > limits.max_output_bytes {
+            return Err(ProcessError::OutputTooLarge {
+                program: program.clone(),
+                limit: limits.max_output_bytes,
+            });
+        }
+        stdout.extend_from_slice(chunk);
+        Ok(())
+    })?;
+
+    Ok(ProcessOutput {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// Runs `command`, handing stdout to `on_stdout` chunk by chunk as it arrives.
+///
+/// Returning an error from `on_stdout` kills the process and propagates the error.
+/// `max_output_bytes` is not enforced here since nothing is buffered.
+pub fn stream<F>(
+    mut command: Command,
+    limits: &ProcessLimits,
+    on_stdout: F,
+) -> Result<(ExitStatus, String), ProcessError>
+where
+    F: FnMut(&[u8]) -> Result<(), ProcessError> + Send,
+{
+    let program = program_name(&command);
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|source| ProcessError::Spawn {
+            program: program.clone(),
+            source,
+        })?;
+
+    let mut child_stdout = child.stdout.take().expect("stdout is piped");
+    let mut child_stderr = child.stderr.take().expect("stderr is piped");
+    let aborted = AtomicBool::new(false);
+    let failure: Mutex<Option<ProcessError>> = Mutex::new(None);
+    let started = Instant::now();
+
+    let fail = |err: ProcessError| {
+        failure
+            .lock()
+            .expect("failure lock poisoned")
+            .get_or_insert(err);
+        aborted.store(true, Ordering::SeqCst);
+    };
+
+    let status = thread::scope(|scope| {
+        scope.spawn(|| {
+            let mut on_stdout = on_stdout;
+            let mut buffer = vec![0; READ_CHUNK];
+            loop {
+                match child_stdout.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(read) => {
+                        if let Err(err) = on_stdout(&buffer[..read]) {
+                            fail(err);
+                            break;
+                        }
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(source) => {
+                        fail(ProcessError::Io {
+                            program: program.clone(),
+                            source,
+                        });
+                        break;
+                    }
+                }
+            }
+        });
+
+        let stderr_reader = scope.spawn(|| {
+            let mut retained = Vec::new();
+            // Keep draining past the cap so the child never blocks on a full pipe
+            let kept = (&mut child_stderr)
+                .take(limits.max_stderr_bytes)
+                .read_to_end(&mut retained);
+            if kept.is_ok() {
+                let _ = io::copy(&mut child_stderr, &mut io::sink());
+            }
+            String::from_utf8_lossy(&retained).into_owned()
+        });
+
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) => {}
+                Err(source) => {
+                    break Err(ProcessError::Io {
+                        program: program.clone(),
+                        source,
+                    })
+                }
+            }
+
+            let timed_out = limits
+                .timeout()
+                .filter(|timeout| started.elapsed() >= *timeout);
+
+            if timed_out.is_some() || aborted.load(Ordering::SeqCst) {
+                let _ = child.kill();
+                let _ = child.wait();
+
+                if let Some(timeout) = timed_out {
+                    warn!(program = %program, timeout_secs = timeout.as_secs(), "Process timed out, killed");
+                    fail(ProcessError::Timeout {
+                        program: program.clone(),
+                        timeout,
+                    });
+                } else {
+                    warn!(program = %program, "Process aborted, killed");
+                }
+
+                break Err(failure
+                    .lock()
+                    .expect("failure lock poisoned")
+                    .take()
+                    .expect("abort always records a failure"));
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        };
+
+        status.map(|status| (status, stderr_reader.join().unwrap_or_default()))
+    })?;
+
+    // The stdout reader may have failed after the process already exited
+    if let Some(err) = failure.into_inner().expect("failure lock poisoned") {
+        return Err(err);
+    }
+
+    debug!(
+        program = %program,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        status = %status.0,
+        "Process finished"
+    );
+
+    Ok(status)
+}
+
+fn program_name(command: &Command) -> String {
+    std::path::Path::new(command.get_program())
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| command.get_program().to_string_lossy().into_owned())
+}
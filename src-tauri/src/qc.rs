@@ -0,0 +1,200 @@
+//! Broadcast QC: black segments and silent gaps.
+//!
+//! ffmpeg's `blackdetect` and `silencedetect` filters tag the frames where a run starts
+//! and ends, which `metadata` prints to stdout. Video frames are numbered like
+//! [`BitrateData`] frames, so black intervals carry frame indices as well as times.
+//!
+//! [`BitrateData`]: crate::models::BitrateData
+
+use crate::analysis::{file_name, AnalysisError};
+use crate::ff::{parse_frame_metadata, Ffmpeg, Ffprobe, PRINT_METADATA};
+use crate::models::{QcInterval, QcReport};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::info;
+use ts_rs::TS;
+
+/// Detection thresholds, defaulting to common broadcast delivery specs.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, TS)]
+#[serde(default)]
+pub struct QcSettings {
+    /// Shortest black segment reported, in seconds.
+    pub black_min_duration: f64,
+    /// Fraction of black pixels for a frame to count as black.
+    pub black_picture_ratio: f64,
+    /// Luminance, from 0 to 1, at or below which a pixel counts as black.
+    pub black_pixel_threshold: f64,
+    /// Shortest silent gap reported, in seconds.
+    pub silence_min_duration: f64,
+    /// Level in dBFS at or below which audio counts as silent.
+    pub silence_noise_db: f64,
+}
+
+impl Default for QcSettings {
+    fn default() -> Self {
+        Self {
+            black_min_duration: 2.0,
+            black_picture_ratio: 0.98,
+            black_pixel_threshold: 0.10,
+            silence_min_duration: 2.0,
+            silence_noise_db: -60.0,
+        }
+    }
+}
+
+fn interval(start: f64, end: f64, start_frame: Option<u32>, end_frame: Option<u32>) -> QcInterval {
+    QcInterval {
+        start,
+        end,
+        duration: end - start,
+        start_frame,
+        end_frame,
+    }
+}
+
+/// Collects black runs from `blackdetect` metadata. A run still open at the end of the
+/// stream is closed at `duration`.
+pub fn parse_black(output: &str, settings: &QcSettings, duration: Option<f64>) -> Vec<QcInterval> {
+    let mut intervals = Vec::new();
+    let mut open: Option<(f64, u32)> = None;
+
+    for frame in parse_frame_metadata(output) {
+        // A frame can end one run and start the next, so ends are handled first
+        if let (Some(end), Some((start, start_frame))) = (frame.number("lavfi.black_end"), open) {
+            intervals.push(interval(
+                start,
+                end,
+                Some(start_frame),
+                Some(frame.frame as u32),
+            ));
+            open = None;
+        }
+        if let Some(start) = frame.number("lavfi.black_start") {
+            open = Some((start, frame.frame as u32));
+        }
+    }
+    if let Some((start, start_frame)) = open {
+        intervals.push(interval(
+            start,
+            duration.unwrap_or(start).max(start),
+            Some(start_frame),
+            None,
+        ));
+    }
+
+    // blackdetect tags every run; its minimum duration only applies to logging
+    intervals.retain(|interval| interval.duration >= settings.black_min_duration);
+    intervals
+}
+
+/// Collects silent gaps from `silencedetect` metadata. A gap still open at the end of
+/// the stream is closed at `duration`.
+pub fn parse_silence(
+    output: &str,
+    settings: &QcSettings,
+    duration: Option<f64>,
+) -> Vec<QcInterval> {
+    let mut intervals = Vec::new();
+    let mut open: Option<f64> = None;
+
+    for frame in parse_frame_metadata(output) {
+        if let (Some(end), Some(start)) = (frame.number("lavfi.silence_end"), open) {
+            intervals.push(interval(start, end, None, None));
+            open = None;
+        }
+        if let Some(start) = frame.number("lavfi.silence_start") {
+            open = Some(start);
+        }
+    }
+    if let Some(start) = open {
+        intervals.push(interval(
+            start,
+            duration.unwrap_or(start).max(start),
+            None,
+            None,
+        ));
+    }
+
+    intervals.retain(|interval| interval.duration >= settings.silence_min_duration);
+    intervals
+}
+
+/// Finds black segments in the first video stream.
+pub fn detect_black(
+    ffmpeg: &Ffmpeg,
+    path: &Path,
+    settings: &QcSettings,
+    duration: Option<f64>,
+) -> Result<Vec<QcInterval>, AnalysisError> {
+    let filter = format!(
+        "blackdetect=d={}:pic_th={}:pix_th={},metadata={PRINT_METADATA}",
+        settings.black_min_duration, settings.black_picture_ratio, settings.black_pixel_threshold
+    );
+    let output = ffmpeg.output(
+        &[],
+        path,
+        &["-map", "0:v:0", "-vf", &filter, "-f", "null", "-"],
+    )?;
+    Ok(parse_black(
+        &String::from_utf8_lossy(&output),
+        settings,
+        duration,
+    ))
+}
+
+/// Finds silent gaps in the first audio stream.
+pub fn detect_silence(
+    ffmpeg: &Ffmpeg,
+    path: &Path,
+    settings: &QcSettings,
+    duration: Option<f64>,
+) -> Result<Vec<QcInterval>, AnalysisError> {
+    let filter = format!(
+        "silencedetect=n={}dB:d={},ametadata={PRINT_METADATA}",
+        settings.silence_noise_db, settings.silence_min_duration
+    );
+    let output = ffmpeg.output(
+        &[],
+        path,
+        &["-map", "0:a:0", "-af", &filter, "-f", "null", "-"],
+    )?;
+    Ok(parse_silence(
+        &String::from_utf8_lossy(&output),
+        settings,
+        duration,
+    ))
+}
+
+/// Runs black detection if the file has video and silence detection if it has audio.
+pub fn run(
+    ffprobe: &Ffprobe,
+    ffmpeg: &Ffmpeg,
+    path: &Path,
+    settings: &QcSettings,
+) -> Result<QcReport, AnalysisError> {
+    let probe = ffprobe.probe_format(path)?;
+    let duration = probe.duration();
+
+    let black = if probe.has_stream("video") {
+        detect_black(ffmpeg, path, settings, duration)?
+    } else {
+        Vec::new()
+    };
+    let silence = if probe.has_stream("audio") {
+        detect_silence(ffmpeg, path, settings, duration)?
+    } else {
+        Vec::new()
+    };
+
+    info!(
+        black_count = black.len(),
+        silence_count = silence.len(),
+        "QC detection completed"
+    );
+    Ok(QcReport {
+        id: file_name(path),
+        thresholds: *settings,
+        black,
+        silence,
+    })
+}
@@ -0,0 +1,199 @@
+//! Full-reference quality metrics between an encode and its source.
+//!
+//! The `psnr` and `ssim` filters tag every frame of the distorted input with its score,
+//! which `metadata` prints to stdout. `libvmaf` only writes a log file, so it runs as a
+//! second pass, and only when the ffmpeg build includes it. The distorted file is
+//! scaled to the reference's size first, and scores are numbered like its
+//! [`BitrateData`] frames so they can be plotted against bitrate.
+//!
+//! [`BitrateData`]: crate::models::BitrateData
+
+use crate::analysis::{file_name, AnalysisError};
+use crate::ff::{escape_filter_value, parse_frame_metadata, Ffmpeg, FfmpegError, PRINT_METADATA};
+use crate::models::{QualityAnalysis, QualityFrame, QualitySummary};
+use serde::Deserialize;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+const PSNR_KEY: &str = "lavfi.psnr.psnr_avg";
+const SSIM_KEY: &str = "lavfi.ssim.All";
+
+/// Scales input 0 (distorted) to the size of input 1 (reference), as the metric
+/// filters need identically sized frames.
+const SCALE_TO_REFERENCE: &str = "[0:v:0][1:v:0]scale2ref=flags=bicubic[dist][ref]";
+
+#[derive(Deserialize)]
+struct VmafLog {
+    #[serde(default)]
+    frames: Vec<VmafLogFrame>,
+}
+
+#[derive(Deserialize)]
+struct VmafLogFrame {
+    #[serde(rename = "frameNum")]
+    frame_num: u32,
+    metrics: VmafLogMetrics,
+}
+
+#[derive(Deserialize)]
+struct VmafLogMetrics {
+    vmaf: Option<f64>,
+}
+
+/// Parses `metadata` print output from the `psnr` and `ssim` pass into per-frame scores.
+pub fn parse_scores(output: &str) -> Vec<QualityFrame> {
+    parse_frame_metadata(output)
+        .into_iter()
+        .map(|frame| QualityFrame {
+            frame_num: frame.frame as u32,
+            timestamp: frame.pts_time,
+            psnr: frame.number(PSNR_KEY),
+            ssim: frame.number(SSIM_KEY),
+            vmaf: None,
+        })
+        .collect()
+}
+
+/// Parses a libvmaf JSON log into `(frame_num, score)` pairs.
+pub fn parse_vmaf_log(log: &str) -> Result<Vec<(u32, f64)>, serde_json::Error> {
+    let log: VmafLog = serde_json::from_str(log)?;
+    Ok(log
+        .frames
+        .into_iter()
+        .filter_map(|frame| Some((frame.frame_num, frame.metrics.vmaf?)))
+        .collect())
+}
+
+/// Mean, minimum and maximum of the scores present, or `None` if there are none.
+pub fn summarize(scores: impl Iterator<Item = Option<f64>>) -> Option<QualitySummary> {
+    let mut count = 0;
+    let mut summary = QualitySummary {
+        mean: 0.0,
+        min: f64::INFINITY,
+        max: f64::NEG_INFINITY,
+    };
+    for score in scores.flatten() {
+        count += 1;
+        summary.mean += score;
+        summary.min = summary.min.min(score);
+        summary.max = summary.max.max(score);
+    }
+    (count > 0).then(|| QualitySummary {
+        mean: summary.mean / f64::from(count),
+        ..summary
+    })
+}
+
+fn run_filter(
+    ffmpeg: &Ffmpeg,
+    distorted: &Path,
+    reference: &Path,
+    graph: &str,
+) -> Result<Vec<u8>, AnalysisError> {
+    let args: Vec<&OsStr> = vec![
+        "-i".as_ref(),
+        reference.as_os_str(),
+        "-lavfi".as_ref(),
+        graph.as_ref(),
+        "-f".as_ref(),
+        "null".as_ref(),
+        "-".as_ref(),
+    ];
+    Ok(ffmpeg.output(&[], distorted, &args)?)
+}
+
+/// Runs the `psnr` and `ssim` pass.
+pub fn score(
+    ffmpeg: &Ffmpeg,
+    distorted: &Path,
+    reference: &Path,
+) -> Result<Vec<QualityFrame>, AnalysisError> {
+    let graph = format!(
+        "{SCALE_TO_REFERENCE};[ref]split[ref1][ref2];[dist][ref1]psnr[scored];\
+         [scored][ref2]ssim,metadata={PRINT_METADATA}"
+    );
+    let output = run_filter(ffmpeg, distorted, reference, &graph)?;
+
+    let stdout = String::from_utf8_lossy(&output);
+    debug!(
+        line_count = stdout.lines().count(),
+        "Parsing quality scores"
+    );
+    let frames = parse_scores(&stdout);
+    if frames.is_empty() {
+        return Err(AnalysisError::NoFrames);
+    }
+    Ok(frames)
+}
+
+/// Runs the `libvmaf` pass.
+pub fn score_vmaf(
+    ffmpeg: &Ffmpeg,
+    distorted: &Path,
+    reference: &Path,
+) -> Result<Vec<(u32, f64)>, AnalysisError> {
+    let log = tempfile::Builder::new()
+        .prefix("vmaf-")
+        .suffix(".json")
+        .tempfile()?;
+    let graph = format!(
+        "{SCALE_TO_REFERENCE};[dist][ref]libvmaf=log_fmt=json:log_path={}",
+        escape_filter_value(&log.path().to_string_lossy())
+    );
+    run_filter(ffmpeg, distorted, reference, &graph)?;
+
+    parse_vmaf_log(&fs::read_to_string(log.path())?)
+        .map_err(|err| FfmpegError::Failed(format!("invalid VMAF log: {err}")).into())
+}
+
+/// Scores `distorted` against `reference`, adding VMAF when ffmpeg supports it.
+pub fn compare(
+    ffmpeg: &Ffmpeg,
+    distorted: &Path,
+    reference: &Path,
+) -> Result<QualityAnalysis, AnalysisError> {
+    let mut frames = score(ffmpeg, distorted, reference)?;
+
+    let vmaf_unavailable = match ffmpeg.has_filter("libvmaf") {
+        Ok(true) => match score_vmaf(ffmpeg, distorted, reference) {
+            Ok(scores) => {
+                for (frame_num, vmaf) in scores {
+                    if let Some(frame) = frames.get_mut(frame_num as usize) {
+                        frame.vmaf = Some(vmaf);
+                    }
+                }
+                None
+            }
+            Err(err) => {
+                warn!(error = %err, "VMAF scoring failed, continuing without it");
+                Some(format!("VMAF scoring failed: {err}"))
+            }
+        },
+        Ok(false) => Some("ffmpeg was built without libvmaf".to_string()),
+        Err(err) => {
+            warn!(error = %err, "Could not list ffmpeg filters");
+            Some(format!("Could not check for libvmaf: {err}"))
+        }
+    };
+
+    let analysis = QualityAnalysis {
+        id: file_name(distorted),
+        reference: file_name(reference),
+        psnr: summarize(frames.iter().map(|frame| frame.psnr)),
+        ssim: summarize(frames.iter().map(|frame| frame.ssim)),
+        vmaf: summarize(frames.iter().map(|frame| frame.vmaf)),
+        frames,
+        vmaf_unavailable,
+    };
+
+    info!(
+        frame_count = analysis.frames.len(),
+        psnr = ?analysis.psnr.map(|summary| summary.mean),
+        ssim = ?analysis.ssim.map(|summary| summary.mean),
+        vmaf = ?analysis.vmaf.map(|summary| summary.mean),
+        "Quality comparison completed"
+    );
+    Ok(analysis)
+}
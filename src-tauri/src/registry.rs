@@ -0,0 +1,285 @@
+//! The table mapping MIME types and file extensions to media types.
+//!
+//! Magic number detection yields a MIME type and the fallback yields an extension; both
+//! are looked up here, so a format classifies the same way whichever path finds it.
+//! Users can add or override mappings in their settings.
+
+use crate::models::MediaType;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{LazyLock, RwLock};
+use tracing::{debug, info, warn};
+use ts_rs::TS;
+
+/// One format: the MIME types it is reported as and the extensions it is saved with.
+#[derive(Debug)]
+pub struct Entry {
+    pub media_type: MediaType,
+    pub mime_types: &'static [&'static str],
+    pub extensions: &'static [&'static str],
+}
+
+const fn entry(
+    media_type: MediaType,
+    mime_types: &'static [&'static str],
+    extensions: &'static [&'static str],
+) -> Entry {
+    Entry {
+        media_type,
+        mime_types,
+        extensions,
+    }
+}
+
+/// Built-in formats. `application/octet-stream` is deliberately absent: it says nothing
+/// about the content, so such files fall through to their extension.
+#[rustfmt::skip]
+pub const BUILTIN: &[Entry] = &[
+    // Audio
+    entry(MediaType::Audio, &["audio/mpeg", "audio/mp3"], &["mp3"]),
+    entry(MediaType::Audio, &["audio/x-wav", "audio/wav", "audio/vnd.wave"], &["wav"]),
+    entry(MediaType::Audio, &["audio/x-flac", "audio/flac"], &["flac"]),
+    entry(MediaType::Audio, &["audio/ogg"], &["ogg", "oga"]),
+    entry(MediaType::Audio, &["audio/opus"], &["opus"]),
+    entry(MediaType::Audio, &["audio/m4a", "audio/mp4"], &["m4a"]),
+    entry(MediaType::Audio, &["audio/aac"], &["aac"]),
+    entry(MediaType::Audio, &["audio/x-ms-wma"], &["wma"]),
+    entry(MediaType::Audio, &["audio/midi"], &["mid", "midi"]),
+    entry(MediaType::Audio, &["audio/amr"], &["amr"]),
+    entry(MediaType::Audio, &["audio/x-aiff"], &["aiff", "aif"]),
+    entry(MediaType::Audio, &["audio/x-dsf"], &["dsf"]),
+    entry(MediaType::Audio, &["audio/x-ape"], &["ape"]),
+    // Video
+    entry(MediaType::Video, &["video/mp4"], &["mp4"]),
+    entry(MediaType::Video, &["video/x-m4v"], &["m4v"]),
+    entry(MediaType::Video, &["video/x-matroska"], &["mkv"]),
+    entry(MediaType::Video, &["video/webm"], &["webm"]),
+    entry(MediaType::Video, &["video/quicktime"], &["mov"]),
+    entry(MediaType::Video, &["video/x-msvideo"], &["avi"]),
+    entry(MediaType::Video, &["video/x-ms-wmv"], &["wmv"]),
+    entry(MediaType::Video, &["video/mpeg"], &["mpg", "mpeg"]),
+    entry(MediaType::Video, &["video/x-flv"], &["flv"]),
+    entry(MediaType::Video, &["video/mp2t"], &["ts", "m2ts", "mts"]),
+    // Images
+    entry(MediaType::Image, &["image/jpeg"], &["jpg", "jpeg"]),
+    entry(MediaType::Image, &["image/png"], &["png"]),
+    entry(MediaType::Image, &["image/gif"], &["gif"]),
+    entry(MediaType::Image, &["image/webp"], &["webp"]),
+    entry(MediaType::Image, &["image/bmp"], &["bmp"]),
+    entry(MediaType::Image, &["image/tiff"], &["tiff", "tif"]),
+    entry(MediaType::Image, &["image/x-canon-cr2"], &["cr2"]),
+    entry(MediaType::Image, &["image/heif"], &["heif", "heic"]),
+    entry(MediaType::Image, &["image/avif"], &["avif"]),
+    entry(MediaType::Image, &["image/jp2"], &["jp2"]),
+    entry(MediaType::Image, &["image/jxl"], &["jxl"]),
+    entry(MediaType::Image, &["image/vnd.ms-photo"], &["jxr"]),
+    entry(MediaType::Image, &["image/vnd.adobe.photoshop"], &["psd"]),
+    entry(MediaType::Image, &["image/vnd.microsoft.icon"], &["ico"]),
+    entry(MediaType::Image, &["image/openraster"], &["ora"]),
+    entry(MediaType::Image, &["image/vnd.djvu"], &["djvu"]),
+    // Subtitles
+    entry(MediaType::Subtitle, &["application/x-subrip"], &["srt"]),
+    entry(MediaType::Subtitle, &["text/x-ssa", "text/x-ass"], &["ass", "ssa"]),
+    entry(MediaType::Subtitle, &["text/vtt"], &["vtt"]),
+    entry(MediaType::Subtitle, &["application/x-pgs"], &["sup"]),
+    // Playlists, only those ffprobe can open: a `.m3u` needs an `#EXTM3U` header to
+    // count, and CUE sheets never do
+    entry(MediaType::Playlist, &["application/vnd.apple.mpegurl", "application/x-mpegurl", "audio/mpegurl"], &["m3u8"]),
+    entry(MediaType::Playlist, &["application/dash+xml"], &["mpd"]),
+    // Documents
+    entry(MediaType::Document, &["text/plain"], &["txt"]),
+    entry(MediaType::Document, &["text/markdown"], &["md"]),
+    entry(MediaType::Document, &["application/pdf"], &["pdf"]),
+    entry(MediaType::Document, &["application/rtf", "text/rtf"], &["rtf"]),
+    entry(MediaType::Document, &["application/msword"], &["doc"]),
+    entry(MediaType::Document, &["application/vnd.openxmlformats-officedocument.wordprocessingml.document"], &["docx"]),
+    entry(MediaType::Document, &["application/vnd.ms-excel"], &["xls"]),
+    entry(MediaType::Document, &["application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"], &["xlsx"]),
+    entry(MediaType::Document, &["application/vnd.ms-powerpoint"], &["ppt"]),
+    entry(MediaType::Document, &["application/vnd.openxmlformats-officedocument.presentationml.presentation"], &["pptx"]),
+    entry(MediaType::Document, &["application/vnd.oasis.opendocument.text"], &["odt"]),
+    entry(MediaType::Document, &["application/vnd.oasis.opendocument.spreadsheet"], &["ods"]),
+    entry(MediaType::Document, &["application/vnd.oasis.opendocument.presentation"], &["odp"]),
+    // Archives
+    entry(MediaType::Archive, &["application/zip"], &["zip"]),
+    entry(MediaType::Archive, &["application/x-tar"], &["tar"]),
+    entry(MediaType::Archive, &["application/vnd.rar"], &["rar"]),
+    entry(MediaType::Archive, &["application/gzip"], &["gz"]),
+    entry(MediaType::Archive, &["application/x-bzip2"], &["bz2"]),
+    entry(MediaType::Archive, &["application/vnd.bzip3"], &["bz3"]),
+    entry(MediaType::Archive, &["application/x-7z-compressed"], &["7z"]),
+    entry(MediaType::Archive, &["application/x-xz"], &["xz"]),
+    entry(MediaType::Archive, &["application/x-shockwave-flash"], &["swf"]),
+    entry(MediaType::Archive, &["application/postscript"], &["ps"]),
+    entry(MediaType::Archive, &["application/vnd.sqlite3"], &["sqlite"]),
+    entry(MediaType::Archive, &["application/x-nintendo-nes-rom"], &["nes"]),
+    entry(MediaType::Archive, &["application/x-google-chrome-extension"], &["crx"]),
+    entry(MediaType::Archive, &["application/vnd.ms-cab-compressed"], &["cab"]),
+    entry(MediaType::Archive, &["application/vnd.debian.binary-package"], &["deb"]),
+    entry(MediaType::Archive, &["application/x-unix-archive"], &["ar"]),
+    entry(MediaType::Archive, &["application/x-compress"], &["z"]),
+    entry(MediaType::Archive, &["application/x-lzip"], &["lz"]),
+    entry(MediaType::Archive, &["application/x-rpm"], &["rpm"]),
+    entry(MediaType::Archive, &["application/dicom"], &["dcm"]),
+    entry(MediaType::Archive, &["application/zstd"], &["zst"]),
+    entry(MediaType::Archive, &["application/x-lz4"], &["lz4"]),
+    entry(MediaType::Archive, &["application/x-cpio"], &["cpio"]),
+    entry(MediaType::Archive, &["application/x-par2"], &["par2"]),
+    entry(MediaType::Archive, &["application/epub+zip"], &["epub"]),
+    entry(MediaType::Archive, &["application/x-mobipocket-ebook"], &["mobi"]),
+    // Executables
+    entry(MediaType::Executable, &["application/vnd.microsoft.portable-executable"], &["exe", "dll"]),
+    entry(MediaType::Executable, &["application/x-executable"], &["elf", "obj"]),
+    // Installers are the OLE compound files that aren't Office documents
+    entry(MediaType::Executable, &["application/x-ole-storage"], &["msi"]),
+    entry(MediaType::Executable, &["application/x-llvm", "application/llvm"], &["bc"]),
+    entry(MediaType::Executable, &["application/x-mach-binary"], &["mach"]),
+    entry(MediaType::Executable, &["application/java"], &["class"]),
+    entry(MediaType::Executable, &["application/vnd.android.dex"], &["dex"]),
+    entry(MediaType::Executable, &["application/vnd.android.dey"], &["dey"]),
+    entry(MediaType::Executable, &["application/x-x509-ca-cert"], &["der"]),
+    entry(MediaType::Executable, &["application/x-apple-diskimage"], &["dmg"]),
+    entry(MediaType::Executable, &[], &["pkg", "app"]),
+    // Libraries
+    entry(MediaType::Library, &["application/x-sharedlib"], &["so"]),
+    entry(MediaType::Library, &[], &["dylib"]),
+];
+
+/// User additions to the built-in table, taking precedence over it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, TS)]
+#[serde(default)]
+pub struct MediaTypeSettings {
+    /// MIME type (e.g. `video/x-prores`) to media type.
+    pub mime_types: BTreeMap<String, MediaType>,
+    /// Extension without the leading dot (e.g. `braw`) to media type.
+    pub extensions: BTreeMap<String, MediaType>,
+}
+
+/// Strips parameters such as `; charset=utf-8` and normalizes case.
+fn normalize_mime(mime_type: &str) -> String {
+    let essence = mime_type.split(';').next().unwrap_or_default();
+    essence.trim().to_ascii_lowercase()
+}
+
+fn normalize_extension(extension: &str) -> String {
+    extension.trim().trim_start_matches('.').to_lowercase()
+}
+
+#[derive(Debug, Clone)]
+pub struct MediaTypeRegistry {
+    mime_types: HashMap<String, MediaType>,
+    extensions: HashMap<String, MediaType>,
+    /// The first MIME type listed for each built-in extension.
+    extension_mime_types: HashMap<String, &'static str>,
+}
+
+impl Default for MediaTypeRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            mime_types: HashMap::new(),
+            extensions: HashMap::new(),
+            extension_mime_types: HashMap::new(),
+        };
+        for entry in BUILTIN {
+            for mime_type in entry.mime_types {
+                registry
+                    .mime_types
+                    .insert(normalize_mime(mime_type), entry.media_type.clone());
+            }
+            for extension in entry.extensions {
+                registry
+                    .extensions
+                    .insert(normalize_extension(extension), entry.media_type.clone());
+                if let Some(mime_type) = entry.mime_types.first() {
+                    registry
+                        .extension_mime_types
+                        .insert(normalize_extension(extension), mime_type);
+                }
+            }
+        }
+        registry
+    }
+}
+
+impl MediaTypeRegistry {
+    /// The built-in table with the user's mappings applied on top.
+    pub fn with_settings(settings: &MediaTypeSettings) -> Self {
+        let mut registry = Self::default();
+        for (mime_type, media_type) in &settings.mime_types {
+            registry
+                .mime_types
+                .insert(normalize_mime(mime_type), media_type.clone());
+        }
+        for (extension, media_type) in &settings.extensions {
+            registry
+                .extensions
+                .insert(normalize_extension(extension), media_type.clone());
+        }
+        registry
+    }
+
+    pub fn from_mime(&self, mime_type: &str) -> Option<MediaType> {
+        self.mime_types.get(&normalize_mime(mime_type)).cloned()
+    }
+
+    pub fn from_extension(&self, extension: &str) -> Option<MediaType> {
+        self.extensions
+            .get(&normalize_extension(extension))
+            .cloned()
+    }
+
+    /// The MIME type files with `extension` are usually served as, if known.
+    pub fn mime_for_extension(&self, extension: &str) -> Option<&'static str> {
+        self.extension_mime_types
+            .get(&normalize_extension(extension))
+            .copied()
+    }
+}
+
+static REGISTRY: LazyLock<RwLock<MediaTypeRegistry>> =
+    LazyLock::new(|| RwLock::new(MediaTypeRegistry::default()));
+
+/// Replaces the registry used for detection with one including the user's mappings.
+pub fn configure(settings: &MediaTypeSettings) {
+    let registry = MediaTypeRegistry::with_settings(settings);
+    match REGISTRY.write() {
+        Ok(mut current) => *current = registry,
+        Err(_) => {
+            warn!("Media type registry lock poisoned, keeping previous mappings");
+            return;
+        }
+    }
+    info!(
+        mime_types = settings.mime_types.len(),
+        extensions = settings.extensions.len(),
+        "Configured media type registry"
+    );
+}
+
+/// Looks up a MIME type in the configured registry.
+pub fn lookup_mime(mime_type: &str) -> Option<MediaType> {
+    let media_type = REGISTRY
+        .read()
+        .ok()
+        .and_then(|registry| registry.from_mime(mime_type));
+    debug!(mime_type = %mime_type, media_type = ?media_type, "Looked up MIME type");
+    media_type
+}
+
+/// Looks up an extension in the configured registry.
+pub fn lookup_extension(extension: &str) -> Option<MediaType> {
+    let media_type = REGISTRY
+        .read()
+        .ok()
+        .and_then(|registry| registry.from_extension(extension));
+    debug!(extension = %extension, media_type = ?media_type, "Looked up extension");
+    media_type
+}
+
+/// Looks up the usual MIME type for an extension in the configured registry.
+pub fn lookup_extension_mime(extension: &str) -> Option<&'static str> {
+    REGISTRY
+        .read()
+        .ok()
+        .and_then(|registry| registry.mime_for_extension(extension))
+}
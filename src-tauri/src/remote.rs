@@ -0,0 +1,197 @@
+//! Media inputs given as `http(s)://` URLs.
+//!
+//! Only the first bytes are downloaded, with a ranged GET, to detect the media type the
+//! same way as for local files. ffprobe then streams the rest itself, with reconnects
+//! and I/O timeouts taken from [`NetworkSettings`].
+
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use std::error::Error as _;
+use std::io::{self, Read};
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{debug, instrument, warn};
+use ts_rs::TS;
+use url::Url;
+
+/// Number of leading bytes fetched for magic number detection.
+pub const HEADER_LEN: u64 = 512;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// Connect and read timeout for remote inputs in seconds, for both our own
+    /// requests and ffprobe's.
+    pub timeout_secs: u32,
+    /// Whether ffprobe reconnects when a connection drops mid-stream.
+    pub reconnect: bool,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 15,
+            reconnect: true,
+        }
+    }
+}
+
+impl NetworkSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(u64::from(self.timeout_secs.max(1)))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum NetworkError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("Could not resolve host: {0}")]
+    Dns(String),
+    #[error("Could not connect to server: {0}")]
+    Connect(String),
+    #[error("Request timed out")]
+    Timeout,
+    #[error("Server responded with HTTP {0}")]
+    Status(u16),
+    #[error("Network error: {0}")]
+    Transport(String),
+}
+
+impl NetworkError {
+    /// Stable machine-readable identifier, used as `error_type` by the frontend.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NetworkError::InvalidUrl(_) => "invalid_url",
+            NetworkError::Timeout => "network_timeout",
+            NetworkError::Status(_) => "http_error",
+            NetworkError::Dns(_) | NetworkError::Connect(_) | NetworkError::Transport(_) => {
+                "network_error"
+            }
+        }
+    }
+}
+
+impl From<ureq::Error> for NetworkError {
+    fn from(err: ureq::Error) -> Self {
+        let transport = match err {
+            ureq::Error::Status(status, _) => return NetworkError::Status(status),
+            ureq::Error::Transport(transport) => transport,
+        };
+
+        let timed_out = transport
+            .source()
+            .and_then(|source| source.downcast_ref::<io::Error>())
+            .is_some_and(|err| err.kind() == io::ErrorKind::TimedOut);
+        if timed_out {
+            return NetworkError::Timeout;
+        }
+
+        let message = transport.to_string();
+        match transport.kind() {
+            ureq::ErrorKind::InvalidUrl | ureq::ErrorKind::UnknownScheme => {
+                NetworkError::InvalidUrl(message)
+            }
+            ureq::ErrorKind::Dns => NetworkError::Dns(message),
+            ureq::ErrorKind::ConnectionFailed
+            | ureq::ErrorKind::ProxyConnect
+            | ureq::ErrorKind::ProxyUnauthorized => NetworkError::Connect(message),
+            _ => NetworkError::Transport(message),
+        }
+    }
+}
+
+/// The start of a remote resource, along with what the server said about the rest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteHeader {
+    pub bytes: Vec<u8>,
+    /// Total size of the resource, when the server reports it.
+    pub size: Option<u64>,
+    pub content_type: Option<String>,
+}
+
+/// Whether `input` should be treated as a URL rather than a local path.
+pub fn is_url(input: &str) -> bool {
+    let scheme = input.split_once("://").map(|(scheme, _)| scheme);
+    scheme.is_some_and(|scheme| {
+        scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+    })
+}
+
+/// Parses and validates an `http(s)://` URL.
+pub fn parse_url(input: &str) -> Result<Url, NetworkError> {
+    let url = Url::parse(input.trim()).map_err(|err| NetworkError::InvalidUrl(err.to_string()))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(NetworkError::InvalidUrl(format!(
+            "{url} is not an HTTP(S) URL"
+        )));
+    }
+    Ok(url)
+}
+
+/// The last path segment of a URL, percent-decoded, falling back to the host name.
+pub fn url_file_name(url: &Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.rfind(|segment| !segment.is_empty()))
+        .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+        .or_else(|| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// The file name shown for a URL input, or the input itself if it doesn't parse.
+pub fn display_name(input: &str) -> String {
+    parse_url(input)
+        .map(|url| url_file_name(&url))
+        .unwrap_or_else(|_| input.to_string())
+}
+
+/// Total resource size from a `Content-Range: bytes 0-511/12345` header.
+fn content_range_total(value: &str) -> Option<u64> {
+    value.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+/// Fetches the first [`HEADER_LEN`] bytes of `url`.
+///
+/// Servers that ignore the `Range` header send the whole resource, so only as much of
+/// the body as needed is read before the connection is dropped.
+#[instrument(skip(url, settings), fields(url = %url))]
+pub fn fetch_header(url: &Url, settings: &NetworkSettings) -> Result<RemoteHeader, NetworkError> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(settings.timeout())
+        .timeout_read(settings.timeout())
+        .build();
+
+    let response = agent
+        .request_url("GET", url)
+        .set("Range", &format!("bytes=0-{}", HEADER_LEN - 1))
+        .call()
+        .inspect_err(|err| warn!(error = %err, "Ranged request failed"))?;
+
+    let status = response.status();
+    let size = match status {
+        206 => response
+            .header("Content-Range")
+            .and_then(content_range_total),
+        _ => response
+            .header("Content-Length")
+            .and_then(|value| value.trim().parse().ok()),
+    };
+    let content_type = response.header("Content-Type").map(str::to_string);
+    debug!(status, size = ?size, content_type = ?content_type, "Received response");
+
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(HEADER_LEN)
+        .read_to_end(&mut bytes)
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => NetworkError::Timeout,
+            _ => NetworkError::Transport(err.to_string()),
+        })?;
+
+    Ok(RemoteHeader {
+        bytes,
+        size,
+        content_type,
+    })
+}
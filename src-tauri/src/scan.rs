@@ -0,0 +1,199 @@
+//! Expanding dropped directories into the media files inside them.
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tracing::warn;
+use ts_rs::TS;
+use walkdir::{DirEntry, WalkDir};
+
+/// What to do with symbolic links found inside a dropped directory.
+///
+/// A dropped directory that is itself a link is always followed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, TS)]
+pub enum SymlinkPolicy {
+    /// Ignore every link.
+    Skip,
+    /// Include links to files, but don't descend into linked directories.
+    #[default]
+    Files,
+    /// Follow all links; loops are detected and skipped.
+    Follow,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, TS)]
+#[serde(default)]
+pub struct ScanSettings {
+    /// Directory levels searched below a dropped directory; `0` only looks at its own files.
+    pub max_depth: u32,
+    pub symlinks: SymlinkPolicy,
+    /// Whether dot-files and dot-directories are searched.
+    pub include_hidden: bool,
+    /// Glob patterns, matched against paths relative to the dropped directory, that files
+    /// must match at least one of. Empty includes everything.
+    pub include: Vec<String>,
+    /// Glob patterns for files and directories to leave out, e.g. `**/proxies/**`.
+    pub exclude: Vec<String>,
+    /// Most files taken from a single dropped directory.
+    pub max_files: u32,
+}
+
+impl Default for ScanSettings {
+    fn default() -> Self {
+        Self {
+            max_depth: 16,
+            symlinks: SymlinkPolicy::default(),
+            include_hidden: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_files: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ScanError {
+    #[error("Invalid pattern: {0}")]
+    Pattern(#[from] globset::Error),
+}
+
+fn build_set(patterns: &[String]) -> Result<Option<GlobSet>, ScanError> {
+    let patterns: Vec<&str> = patterns
+        .iter()
+        .map(|pattern| pattern.trim())
+        .filter(|pattern| !pattern.is_empty())
+        .collect();
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Compiled filters for a directory scan.
+#[derive(Debug, Clone)]
+pub struct Filters {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    include_hidden: bool,
+}
+
+impl Filters {
+    pub fn new(settings: &ScanSettings) -> Result<Self, ScanError> {
+        Ok(Self {
+            include: build_set(&settings.include)?,
+            exclude: build_set(&settings.exclude)?,
+            include_hidden: settings.include_hidden,
+        })
+    }
+
+    fn is_hidden(relative: &Path) -> bool {
+        relative
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.'))
+    }
+
+    /// Whether a directory at `relative` should be descended into.
+    pub fn allows_dir(&self, relative: &Path) -> bool {
+        if !self.include_hidden && Self::is_hidden(relative) {
+            return false;
+        }
+        !self
+            .exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.is_match(relative))
+    }
+
+    /// Whether a file at `relative` passes the hidden, include and exclude filters.
+    pub fn allows_file(&self, relative: &Path) -> bool {
+        if !self.include_hidden && Self::is_hidden(relative) {
+            return false;
+        }
+        if self
+            .exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.is_match(relative))
+        {
+            return false;
+        }
+        self.include
+            .as_ref()
+            .is_none_or(|include| include.is_match(relative))
+    }
+}
+
+/// Whether a walked entry is a file we should look at, resolving links per `policy`.
+fn is_candidate_file(entry: &DirEntry, policy: SymlinkPolicy) -> bool {
+    if !entry.path_is_symlink() {
+        return entry.file_type().is_file();
+    }
+
+    match policy {
+        SymlinkPolicy::Skip => false,
+        // With `Follow`, the walker has already resolved the link
+        SymlinkPolicy::Follow => entry.file_type().is_file(),
+        SymlinkPolicy::Files => fs::metadata(entry.path()).is_ok_and(|meta| meta.is_file()),
+    }
+}
+
+/// Recursively lists the files under `dir` that pass the scan filters and `keep`,
+/// sorted by path and capped at [`ScanSettings::max_files`].
+pub fn walk_files(
+    dir: &Path,
+    settings: &ScanSettings,
+    mut keep: impl FnMut(&Path) -> bool,
+) -> Result<Vec<PathBuf>, ScanError> {
+    let filters = Filters::new(settings)?;
+    let policy = settings.symlinks;
+
+    let walker = WalkDir::new(dir)
+        .max_depth(settings.max_depth as usize + 1)
+        .follow_links(policy == SymlinkPolicy::Follow)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            // The dropped directory itself is exempt from filtering
+            if entry.depth() == 0 {
+                return true;
+            }
+            let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+            if entry.file_type().is_dir() {
+                filters.allows_dir(relative)
+            } else {
+                filters.allows_file(relative)
+            }
+        });
+
+    let mut files = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                warn!(error = %err, "Skipping unreadable directory entry");
+                continue;
+            }
+        };
+
+        if entry.depth() == 0 || !is_candidate_file(&entry, policy) || !keep(entry.path()) {
+            continue;
+        }
+
+        files.push(entry.into_path());
+        if files.len() >= settings.max_files as usize {
+            warn!(
+                max_files = settings.max_files,
+                "File limit reached, ignoring the rest"
+            );
+            break;
+        }
+    }
+
+    Ok(files)
+}
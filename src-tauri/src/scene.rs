@@ -0,0 +1,69 @@
+//! Scene change detection.
+//!
+//! ffmpeg's `select` filter scores how much each frame differs from the one before it.
+//! Letting every frame through and printing the `lavfi.scene_score` metadata gives a
+//! score per frame in presentation order, the same order [`BitrateData`] frames are
+//! numbered in, so cuts can be lined up against bitrate spikes by index.
+//!
+//! [`BitrateData`]: crate::models::BitrateData
+
+use crate::analysis::AnalysisError;
+use crate::ff::{parse_frame_metadata, Ffmpeg, PRINT_METADATA};
+use crate::models::{SceneAnalysis, SceneScore};
+use std::path::Path;
+use tracing::{debug, info, instrument};
+
+/// Score above which a frame counts as a cut when the caller doesn't choose one.
+pub const DEFAULT_THRESHOLD: f64 = 0.4;
+
+const SCORE_KEY: &str = "lavfi.scene_score";
+
+/// Parses `metadata` print output into per-frame scores.
+pub fn parse_scores(output: &str) -> Vec<SceneScore> {
+    parse_frame_metadata(output)
+        .into_iter()
+        .map(|frame| SceneScore {
+            frame_num: frame.frame as u32,
+            timestamp: frame.pts_time,
+            score: frame.number(SCORE_KEY).unwrap_or(0.0),
+        })
+        .collect()
+}
+
+/// Scores every frame of the first video stream.
+#[instrument(skip(ffmpeg, path), fields(path = %path.display()))]
+pub fn score(ffmpeg: &Ffmpeg, path: &Path) -> Result<Vec<SceneScore>, AnalysisError> {
+    // The comma inside the expression is escaped from the filter graph parser
+    let filter = format!("select=gte(scene\\,0),metadata={PRINT_METADATA}");
+    let output = ffmpeg.output(
+        &[],
+        path,
+        &["-map", "0:v:0", "-vf", &filter, "-f", "null", "-"],
+    )?;
+
+    let stdout = String::from_utf8_lossy(&output);
+    debug!(line_count = stdout.lines().count(), "Parsing scene scores");
+    let scores = parse_scores(&stdout);
+    if scores.is_empty() {
+        return Err(AnalysisError::NoFrames);
+    }
+
+    info!(frame_count = scores.len(), "Scene scores computed");
+    Ok(scores)
+}
+
+/// Picks out the frames scoring above `threshold` as cuts.
+pub fn detect(id: String, scores: Vec<SceneScore>, threshold: f64) -> SceneAnalysis {
+    let cuts = scores
+        .iter()
+        .filter(|score| score.score > threshold)
+        .copied()
+        .collect();
+
+    SceneAnalysis {
+        id,
+        threshold,
+        scores,
+        cuts,
+    }
+}
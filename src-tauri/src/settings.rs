@@ -0,0 +1,113 @@
+use crate::cache::CacheSettings;
+use crate::process::ProcessLimits;
+use crate::qc::QcSettings;
+use crate::registry::MediaTypeSettings;
+use crate::remote::NetworkSettings;
+use crate::scan::ScanSettings;
+use crate::watch::WatchSettings;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, instrument, warn};
+use ts_rs::TS;
+
+/// Name of the settings file inside the app config directory.
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// User-configurable application settings, persisted as JSON.
+///
+/// Every field has a default so that older settings files keep loading as new options are added.
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[serde(default)]
+pub struct Settings {
+    /// Explicit path to an ffprobe binary, checked before any automatic discovery.
+    pub ffprobe_path: Option<String>,
+    /// Explicit path to an ffmpeg binary, used by analyses that decode media.
+    pub ffmpeg_path: Option<String>,
+    /// Timeout and output caps applied to every ffprobe invocation.
+    pub process_limits: ProcessLimits,
+    /// Timeout and output caps for ffmpeg, whose analyses decode entire files; unbounded
+    /// in time by default.
+    pub ffmpeg_limits: ProcessLimits,
+    /// On-disk caching of analysis results.
+    pub cache: CacheSettings,
+    /// Timeouts and reconnects for `http(s)://` inputs.
+    pub network: NetworkSettings,
+    /// How dropped directories are searched for media files.
+    pub scan: ScanSettings,
+    /// Polling and debouncing for watch folders.
+    pub watch: WatchSettings,
+    /// MIME type and extension mappings added to the built-in media type table.
+    pub media_types: MediaTypeSettings,
+    /// Thresholds for black frame and silence detection.
+    pub qc: QcSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ffprobe_path: None,
+            ffmpeg_path: None,
+            process_limits: ProcessLimits::default(),
+            ffmpeg_limits: ProcessLimits::decode(),
+            cache: CacheSettings::default(),
+            network: NetworkSettings::default(),
+            scan: ScanSettings::default(),
+            watch: WatchSettings::default(),
+            media_types: MediaTypeSettings::default(),
+            qc: QcSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads settings from `path`, falling back to defaults if the file is missing or invalid.
+    #[instrument(skip(path), fields(path = %path.display()))]
+    pub fn load(path: &Path) -> Settings {
+        match fs::read_to_string(path) {
+            Ok(contents) => match serde_json::from_str(&contents) {
+                Ok(settings) => {
+                    info!("Loaded settings");
+                    settings
+                }
+                Err(err) => {
+                    warn!(error = %err, "Settings file is invalid, using defaults");
+                    Settings::default()
+                }
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                debug!("No settings file found, using defaults");
+                Settings::default()
+            }
+            Err(err) => {
+                warn!(error = %err, "Failed to read settings file, using defaults");
+                Settings::default()
+            }
+        }
+    }
+
+    /// Writes settings to `path`, creating parent directories as needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let contents = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, contents)
+    }
+
+    pub fn ffprobe_path(&self) -> Option<PathBuf> {
+        self.ffprobe_path
+            .as_deref()
+            .filter(|path| !path.trim().is_empty())
+            .map(PathBuf::from)
+    }
+
+    pub fn ffmpeg_path(&self) -> Option<PathBuf> {
+        self.ffmpeg_path
+            .as_deref()
+            .filter(|path| !path.trim().is_empty())
+            .map(PathBuf::from)
+    }
+}
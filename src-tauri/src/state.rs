@@ -0,0 +1,216 @@
+use crate::cache::{AnalysisCache, CacheStats};
+use crate::ff::{Ffmpeg, Ffprobe};
+use crate::registry;
+use crate::settings::{Settings, SETTINGS_FILE};
+use crate::thumbnails;
+use crate::tools::ToolError;
+use crate::watch::{FolderWatcher, WatchError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+
+/// Shared state managed by Tauri and handed to every command.
+pub struct AppState {
+    settings_path: Option<PathBuf>,
+    cache_dir: Option<PathBuf>,
+    settings: RwLock<Settings>,
+    ffprobe: RwLock<Option<Ffprobe>>,
+    ffmpeg: RwLock<Option<Ffmpeg>>,
+    /// Active watch folders, keyed by canonical path.
+    watchers: Mutex<HashMap<PathBuf, FolderWatcher>>,
+}
+
+impl AppState {
+    /// Loads settings from `config_dir` (if available) and eagerly resolves ffprobe.
+    ///
+    /// Analysis results are cached under `cache_dir`; without one, caching is disabled.
+    pub fn load(config_dir: Option<PathBuf>, cache_dir: Option<PathBuf>) -> Self {
+        let settings_path = config_dir.map(|dir| dir.join(SETTINGS_FILE));
+        let settings = settings_path
+            .as_deref()
+            .map(Settings::load)
+            .unwrap_or_default();
+        registry::configure(&settings.media_types);
+
+        let state = Self {
+            settings_path,
+            cache_dir,
+            settings: RwLock::new(settings),
+            ffprobe: RwLock::new(None),
+            ffmpeg: RwLock::new(None),
+            watchers: Mutex::new(HashMap::new()),
+        };
+
+        if let Err(err) = state.ffprobe() {
+            warn!(error = %err, "ffprobe is unavailable, analysis will fail until it is configured");
+        }
+
+        state
+    }
+
+    pub fn settings(&self) -> Settings {
+        self.settings
+            .read()
+            .expect("settings lock poisoned")
+            .clone()
+    }
+
+    /// Replaces and persists the settings, dropping anything derived from the old ones.
+    pub fn update_settings(&self, settings: Settings) -> io::Result<()> {
+        if let Some(path) = &self.settings_path {
+            settings.save(path)?;
+        }
+
+        registry::configure(&settings.media_types);
+        *self.settings.write().expect("settings lock poisoned") = settings;
+        *self.ffprobe.write().expect("ffprobe lock poisoned") = None;
+        *self.ffmpeg.write().expect("ffmpeg lock poisoned") = None;
+        info!("Settings updated");
+        Ok(())
+    }
+
+    /// Returns the resolved ffprobe, resolving it on first use or after a failed attempt.
+    pub fn ffprobe(&self) -> Result<Ffprobe, ToolError> {
+        if let Some(ffprobe) = self.ffprobe.read().expect("ffprobe lock poisoned").as_ref() {
+            return Ok(ffprobe.clone());
+        }
+
+        let settings = self.settings();
+        let ffprobe = Ffprobe::resolve(settings.ffprobe_path().as_deref())?
+            .with_limits(settings.process_limits);
+        *self.ffprobe.write().expect("ffprobe lock poisoned") = Some(ffprobe.clone());
+        Ok(ffprobe)
+    }
+
+    /// Returns the resolved ffmpeg, resolving it on first use or after a failed attempt.
+    pub fn ffmpeg(&self) -> Result<Ffmpeg, ToolError> {
+        if let Some(ffmpeg) = self.ffmpeg.read().expect("ffmpeg lock poisoned").as_ref() {
+            return Ok(ffmpeg.clone());
+        }
+
+        let settings = self.settings();
+        let ffmpeg =
+            Ffmpeg::resolve(settings.ffmpeg_path().as_deref())?.with_limits(settings.ffmpeg_limits);
+        *self.ffmpeg.write().expect("ffmpeg lock poisoned") = Some(ffmpeg.clone());
+        Ok(ffmpeg)
+    }
+
+    /// The analysis cache, if enabled and a cache directory is available.
+    pub fn cache(&self) -> Option<AnalysisCache> {
+        let settings = self.settings().cache;
+        self.cache_dir
+            .as_ref()
+            .filter(|_| settings.enabled)
+            .map(|dir| AnalysisCache::new(dir.clone(), settings.max_bytes))
+    }
+
+    /// Runs `compute` through the analysis cache when it is enabled.
+    pub fn cached<T, E>(
+        &self,
+        path: &Path,
+        tool_version: &str,
+        kind: &str,
+        compute: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        match self.cache() {
+            Some(cache) => cache.get_or_compute(path, tool_version, kind, compute),
+            None => compute(),
+        }
+    }
+
+    /// Empties the cache directory regardless of whether caching is currently enabled.
+    pub fn clear_cache(&self) -> io::Result<CacheStats> {
+        let mut removed = match &self.cache_dir {
+            Some(dir) => AnalysisCache::new(dir.clone(), 0).clear()?,
+            None => CacheStats::default(),
+        };
+
+        let thumbnails = thumbnails::clear(&self.thumbnail_dir())?;
+        removed.entries += thumbnails.entries;
+        removed.bytes += thumbnails.bytes;
+        Ok(removed)
+    }
+
+    /// Where rendered thumbnails are kept: inside the cache directory when there is one,
+    /// since they have to be written somewhere even with caching disabled.
+    pub fn thumbnail_dir(&self) -> PathBuf {
+        self.cache_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("byte-me"))
+            .join("thumbnails")
+    }
+
+    /// Evicts the least recently used thumbnails, sparing those of `path`, so that they
+    /// and the analysis cache together stay within the cache size limit.
+    pub fn trim_thumbnails(&self, path: &Path, ffmpeg: &Ffmpeg) {
+        let max_bytes = self.settings().cache.max_bytes;
+        let cached = self
+            .cache()
+            .and_then(|cache| cache.stats().ok())
+            .map_or(0, |stats| stats.bytes);
+        let root = self.thumbnail_dir();
+        let evicted = thumbnails::file_dir(&root, path, ffmpeg)
+            .and_then(|keep| thumbnails::evict(&root, max_bytes.saturating_sub(cached), &keep));
+        if let Err(err) = evicted {
+            warn!(error = %err, "Failed to evict thumbnails");
+        }
+    }
+
+    /// Starts watching `dir` with the current settings, calling `on_settled` with the
+    /// canonical folder and file paths as files settle.
+    pub fn start_watching(
+        &self,
+        dir: &Path,
+        mut on_settled: impl FnMut(&Path, &Path) + Send + 'static,
+    ) -> Result<PathBuf, WatchError> {
+        let dir = dir
+            .canonicalize()
+            .map_err(|_| WatchError::NotDirectory(dir.display().to_string()))?;
+        let mut watchers = self.watchers.lock().expect("watchers lock poisoned");
+        if watchers.contains_key(&dir) {
+            return Err(WatchError::AlreadyWatching(dir.display().to_string()));
+        }
+
+        let settings = self.settings();
+        let folder = dir.clone();
+        let watcher = FolderWatcher::start(&dir, settings.watch, settings.scan, move |path| {
+            on_settled(&folder, path)
+        })?;
+        watchers.insert(dir.clone(), watcher);
+        Ok(dir)
+    }
+
+    /// Stops watching `dir`, waiting for its watcher thread to exit.
+    pub fn stop_watching(&self, dir: &Path) -> Result<(), WatchError> {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let watcher = self
+            .watchers
+            .lock()
+            .expect("watchers lock poisoned")
+            .remove(&dir)
+            .ok_or_else(|| WatchError::NotWatching(dir.display().to_string()))?;
+        // Joined outside the lock, as an analysis may still be running
+        watcher.stop();
+        Ok(())
+    }
+
+    /// Folders currently being watched, sorted.
+    pub fn watched_folders(&self) -> Vec<PathBuf> {
+        let mut folders: Vec<PathBuf> = self
+            .watchers
+            .lock()
+            .expect("watchers lock poisoned")
+            .keys()
+            .cloned()
+            .collect();
+        folders.sort();
+        folders
+    }
+}
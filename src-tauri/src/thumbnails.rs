@@ -0,0 +1,331 @@
+//! Frame thumbnails, rendered by ffmpeg and kept on disk.
+//!
+//! Thumbnails live under a directory per input file, named after the same key the
+//! analysis cache uses, so an edited file or a different ffmpeg never reuses stale
+//! images. Output is written under a temporary name and renamed into place, so a
+//! cancelled extraction is never mistaken for a finished one. Like analysis cache
+//! entries, the least recently used are evicted once over a size limit.
+
+use crate::analysis::{file_name, AnalysisError};
+use crate::cache::{CacheKey, CacheStats};
+use crate::ff::Ffmpeg;
+use crate::models::{BitrateData, BitrateFrame, Thumbnail, ThumbnailFormat};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{debug, info, instrument, warn};
+use walkdir::WalkDir;
+
+/// Width in pixels when the caller doesn't ask for one; height follows the aspect ratio.
+pub const DEFAULT_WIDTH: u32 = 160;
+pub const MAX_WIDTH: u32 = 1920;
+
+const PARTIAL_EXTENSION: &str = "partial";
+
+/// How far apart, in milliseconds, a decoded frame and a [`BitrateFrame`] can be and
+/// still be the same frame, as both timestamps are rounded.
+const PTS_TOLERANCE_MS: u64 = 1;
+
+impl ThumbnailFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "jpg",
+            ThumbnailFormat::Png => "png",
+        }
+    }
+
+    fn mime_type(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "image/jpeg",
+            ThumbnailFormat::Png => "image/png",
+        }
+    }
+
+    fn codec_args(self) -> &'static [&'static str] {
+        match self {
+            ThumbnailFormat::Jpeg => &["-c:v", "mjpeg", "-q:v", "4"],
+            ThumbnailFormat::Png => &["-c:v", "png"],
+        }
+    }
+}
+
+/// The directory holding thumbnails of `path` rendered by `ffmpeg`, under `root`.
+pub fn file_dir(root: &Path, path: &Path, ffmpeg: &Ffmpeg) -> io::Result<PathBuf> {
+    Ok(root.join(CacheKey::for_file(path, ffmpeg.version())?.as_str()))
+}
+
+/// Scales to `width`, keeping the height even as most encoders require.
+fn scale_filter(width: u32) -> String {
+    format!("scale={}:-2", width.clamp(16, MAX_WIDTH))
+}
+
+/// Marks a rendered image as recently used, so [`evict`] keeps it longer.
+fn touch(image: &Path) {
+    if let Ok(file) = fs::File::options().write(true).open(image) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+fn thumbnail(frame_num: Option<u32>, timestamp: Option<f64>, path: &Path) -> Thumbnail {
+    Thumbnail {
+        frame_num,
+        timestamp,
+        path: path.display().to_string(),
+        data_url: None,
+    }
+}
+
+/// Renders the frame shown at `timestamp` seconds.
+#[instrument(skip(ffmpeg, path, dir), fields(path = %path.display()))]
+pub fn at_timestamp(
+    ffmpeg: &Ffmpeg,
+    path: &Path,
+    dir: &Path,
+    timestamp: f64,
+    format: ThumbnailFormat,
+    width: u32,
+) -> Result<Thumbnail, AnalysisError> {
+    let timestamp = timestamp.max(0.0);
+    let output = dir.join(format!(
+        "at-{}-{width}.{}",
+        (timestamp * 1000.0).round() as u64,
+        format.extension()
+    ));
+    if output.is_file() {
+        debug!(output = %output.display(), "Thumbnail already rendered");
+        touch(&output);
+        return Ok(thumbnail(None, Some(timestamp), &output));
+    }
+
+    fs::create_dir_all(dir)?;
+    let partial = output.with_extension(PARTIAL_EXTENSION);
+    let seek = format!("{timestamp:.3}");
+    let filter = scale_filter(width);
+    let mut args: Vec<&OsStr> = ["-map", "0:v:0", "-frames:v", "1", "-vf", &filter]
+        .into_iter()
+        .chain(format.codec_args().iter().copied())
+        .chain(["-f", "image2", "-update", "1", "-y"])
+        .map(OsStr::new)
+        .collect();
+    args.push(partial.as_os_str());
+    // Seeking before the input is fast, and still frame-accurate when decoding
+    ffmpeg.output(&["-ss", &seek], path, &args)?;
+
+    // Seeking past the end succeeds without writing anything
+    if !partial.is_file() {
+        return Err(AnalysisError::NoFrames);
+    }
+    fs::rename(&partial, &output)?;
+
+    info!(timestamp, "Rendered thumbnail");
+    Ok(thumbnail(None, Some(timestamp), &output))
+}
+
+/// Finds the frame of `bitrate` presented at `pts_ms`, allowing for rounding.
+fn frame_at<'a>(frames: &[(i64, &'a BitrateFrame)], pts_ms: i64) -> Option<&'a BitrateFrame> {
+    let index = frames.partition_point(|(ms, _)| *ms < pts_ms);
+    [index.checked_sub(1), Some(index)]
+        .into_iter()
+        .flatten()
+        .filter_map(|index| frames.get(index))
+        .filter(|(ms, _)| ms.abs_diff(pts_ms) <= PTS_TOLERANCE_MS)
+        .min_by_key(|(ms, _)| ms.abs_diff(pts_ms))
+        .map(|(_, frame)| *frame)
+}
+
+/// Renders every keyframe of the first video stream, matched by timestamp to the frames
+/// of `bitrate`.
+#[instrument(skip(ffmpeg, path, dir, bitrate), fields(path = %path.display()))]
+pub fn for_keyframes(
+    ffmpeg: &Ffmpeg,
+    path: &Path,
+    dir: &Path,
+    bitrate: &BitrateData,
+    format: ThumbnailFormat,
+    width: u32,
+) -> Result<Vec<Thumbnail>, AnalysisError> {
+    let output = dir.join(format!("keyframes-pts-{width}-{}", format.extension()));
+    if !output.is_dir() {
+        let partial = output.with_extension(PARTIAL_EXTENSION);
+        // Left over from an interrupted run
+        if partial.exists() {
+            fs::remove_dir_all(&partial)?;
+        }
+        fs::create_dir_all(&partial)?;
+
+        let pattern = partial.join(format!("%d.{}", format.extension()));
+        let filter = scale_filter(width);
+        let [sync_option, sync_mode] = ffmpeg.capabilities().passthrough_args();
+        // Images are named by presentation time in milliseconds, on the input's timeline
+        let mut args: Vec<&OsStr> = [
+            "-map",
+            "0:v:0",
+            sync_option,
+            sync_mode,
+            "-copyts",
+            "-enc_time_base:v",
+            "1/1000",
+            "-frame_pts",
+            "1",
+            "-vf",
+            &filter,
+        ]
+        .into_iter()
+        .chain(format.codec_args().iter().copied())
+        .chain(["-f", "image2", "-y"])
+        .map(OsStr::new)
+        .collect();
+        args.push(pattern.as_os_str());
+        // Only keyframes are decoded at all, which keeps this fast on long files
+        ffmpeg.output(&["-skip_frame", "nokey"], path, &args)?;
+
+        // Nothing to keep for a file without video
+        if fs::read_dir(&partial)?.next().is_none() {
+            fs::remove_dir(&partial)?;
+            return Err(AnalysisError::NoFrames);
+        }
+        fs::rename(&partial, &output)?;
+    }
+
+    let mut images: Vec<(i64, PathBuf)> = fs::read_dir(&output)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|image| image.extension().and_then(|ext| ext.to_str()) == Some(format.extension()))
+        .filter_map(|image| {
+            let pts_ms = image.file_stem()?.to_str()?.parse().ok()?;
+            Some((pts_ms, image))
+        })
+        .collect();
+    images.sort();
+    let Some((_, first)) = images.first() else {
+        return Err(AnalysisError::NoFrames);
+    };
+    touch(first);
+
+    let mut frames: Vec<(i64, &BitrateFrame)> = bitrate
+        .frames
+        .iter()
+        .filter_map(|frame| Some(((frame.timestamp? * 1000.0).round() as i64, frame)))
+        .collect();
+    frames.sort_by_key(|(ms, _)| *ms);
+
+    // Container flags and the decoder can disagree on what is a keyframe, so images are
+    // matched by timestamp rather than by position
+    let mut unmatched = 0;
+    let thumbnails: Vec<Thumbnail> = images
+        .iter()
+        .map(|(pts_ms, image)| match frame_at(&frames, *pts_ms) {
+            Some(frame) => thumbnail(Some(frame.frame_num), frame.timestamp, image),
+            None => {
+                unmatched += 1;
+                thumbnail(None, Some(*pts_ms as f64 / 1000.0), image)
+            }
+        })
+        .collect();
+    if unmatched > 0 {
+        warn!(
+            filename = %file_name(path),
+            unmatched,
+            images = thumbnails.len(),
+            "Some thumbnails match no frame in the bitrate data"
+        );
+    }
+
+    info!(count = thumbnails.len(), "Rendered keyframe thumbnails");
+    Ok(thumbnails)
+}
+
+/// Fills in [`Thumbnail::data_url`] from the image on disk.
+pub fn inline(thumbnail: &mut Thumbnail, format: ThumbnailFormat) -> io::Result<()> {
+    let data = fs::read(&thumbnail.path)?;
+    thumbnail.data_url = Some(format!(
+        "data:{};base64,{}",
+        format.mime_type(),
+        STANDARD.encode(data)
+    ));
+    Ok(())
+}
+
+/// Size and most recent use of the thumbnails of one input file.
+fn usage(dir: &Path) -> (CacheStats, SystemTime) {
+    let mut stats = CacheStats::default();
+    let mut used = SystemTime::UNIX_EPOCH;
+    for entry in WalkDir::new(dir).into_iter().filter_map(Result::ok) {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_file() {
+            stats.entries += 1;
+            stats.bytes += metadata.len();
+            used = used.max(metadata.modified().unwrap_or(used));
+        }
+    }
+    (stats, used)
+}
+
+/// Deletes the thumbnails of the least recently used input files under `root` until
+/// the rest fit within `max_bytes`, always sparing the directory `keep`. Returns what
+/// was removed.
+pub fn evict(root: &Path, max_bytes: u64, keep: &Path) -> io::Result<CacheStats> {
+    let mut removed = CacheStats::default();
+    let read_dir = match fs::read_dir(root) {
+        Ok(read_dir) => read_dir,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(removed),
+        Err(err) => return Err(err),
+    };
+
+    let mut dirs = Vec::new();
+    for entry in read_dir {
+        let path = entry?.path();
+        if path.is_dir() {
+            let (stats, used) = usage(&path);
+            dirs.push((path, stats, used));
+        }
+    }
+    let mut total: u64 = dirs.iter().map(|(_, stats, _)| stats.bytes).sum();
+    if total <= max_bytes {
+        return Ok(removed);
+    }
+
+    dirs.sort_by_key(|(_, _, used)| *used);
+    for (path, stats, _) in dirs {
+        if total <= max_bytes {
+            break;
+        }
+        if path == keep {
+            continue;
+        }
+        fs::remove_dir_all(&path)?;
+        total -= stats.bytes;
+        removed.entries += stats.entries;
+        removed.bytes += stats.bytes;
+        debug!(path = %path.display(), bytes = stats.bytes, "Evicted thumbnails");
+    }
+    Ok(removed)
+}
+
+/// Deletes every rendered thumbnail under `root`, returning what was removed.
+pub fn clear(root: &Path) -> io::Result<CacheStats> {
+    let mut removed = CacheStats::default();
+    if !root.exists() {
+        return Ok(removed);
+    }
+
+    for entry in WalkDir::new(root).into_iter().filter_map(Result::ok) {
+        if entry.file_type().is_file() {
+            removed.entries += 1;
+            removed.bytes += entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        }
+    }
+    fs::remove_dir_all(root)?;
+
+    info!(
+        entries = removed.entries,
+        bytes = removed.bytes,
+        "Cleared thumbnails"
+    );
+    Ok(removed)
+}
@@ -0,0 +1,173 @@
+//! Discovery and validation of the external ffmpeg tools we shell out to.
+//!
+//! A tool is looked up in order from the user setting, an environment variable,
+//! a sidecar binary next to our own executable (Tauri `externalBin`), and finally
+//! `PATH`. Every candidate must successfully answer `-version` before it is used.
+
+use crate::process::{self, ProcessLimits};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use thiserror::Error;
+use tracing::{debug, info, instrument, warn};
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Ffprobe,
+    Ffmpeg,
+}
+
+impl Tool {
+    /// Executable name without any platform suffix.
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Ffprobe => "ffprobe",
+            Tool::Ffmpeg => "ffmpeg",
+        }
+    }
+
+    /// Environment variable that overrides discovery for this tool.
+    pub fn env_var(self) -> &'static str {
+        match self {
+            Tool::Ffprobe => "BYTE_ME_FFPROBE",
+            Tool::Ffmpeg => "BYTE_ME_FFMPEG",
+        }
+    }
+
+    fn file_name(self) -> String {
+        format!("{}{}", self.name(), env::consts::EXE_SUFFIX)
+    }
+}
+
+/// Where a resolved tool was found.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+pub enum ToolSource {
+    Setting,
+    Environment,
+    Sidecar,
+    Path,
+}
+
+/// A tool binary that has been located and validated.
+#[derive(Debug, Clone)]
+pub struct ResolvedTool {
+    pub tool: Tool,
+    pub path: PathBuf,
+    pub version: String,
+    pub source: ToolSource,
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum ToolError {
+    #[error("{} could not be found (tried: {})", .tool.name(), .tried.join("; "))]
+    NotFound { tool: Tool, tried: Vec<String> },
+    #[error("{} {version} is too old, version {minimum} or newer is required", .tool.name())]
+    Unsupported {
+        tool: Tool,
+        version: String,
+        minimum: String,
+    },
+}
+
+/// Locates `tool`, preferring an explicitly configured path.
+#[instrument(skip(configured), fields(tool = tool.name()))]
+pub fn resolve(tool: Tool, configured: Option<&Path>) -> Result<ResolvedTool, ToolError> {
+    let mut tried = Vec::new();
+
+    for (source, candidate) in candidates(tool, configured) {
+        debug!(source = ?source, candidate = %candidate.display(), "Checking tool candidate");
+        match validate(tool, &candidate) {
+            Ok(version) => {
+                info!(
+                    source = ?source,
+                    path = %candidate.display(),
+                    version = %version,
+                    "Resolved tool"
+                );
+                return Ok(ResolvedTool {
+                    tool,
+                    path: candidate,
+                    version,
+                    source,
+                });
+            }
+            Err(reason) => {
+                warn!(source = ?source, candidate = %candidate.display(), reason = %reason, "Rejected tool candidate");
+                tried.push(format!("{} ({reason})", candidate.display()));
+            }
+        }
+    }
+
+    if tried.is_empty() {
+        tried.push("no candidates found on PATH".to_string());
+    }
+
+    Err(ToolError::NotFound { tool, tried })
+}
+
+/// Lists candidate binaries in priority order.
+fn candidates(tool: Tool, configured: Option<&Path>) -> Vec<(ToolSource, PathBuf)> {
+    let mut candidates = Vec::new();
+
+    if let Some(path) = configured {
+        candidates.push((ToolSource::Setting, path.to_path_buf()));
+    }
+
+    if let Some(path) = env::var_os(tool.env_var()).filter(|value| !value.is_empty()) {
+        candidates.push((ToolSource::Environment, PathBuf::from(path)));
+    }
+
+    // Tauri places `externalBin` sidecars alongside the main executable, without the target triple
+    if let Some(dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        let sidecar = dir.join(tool.file_name());
+        if sidecar.is_file() {
+            candidates.push((ToolSource::Sidecar, sidecar));
+        }
+    }
+
+    if let Some(path) = env::var_os("PATH") {
+        candidates.extend(
+            env::split_paths(&path)
+                .map(|dir| dir.join(tool.file_name()))
+                .filter(|candidate| candidate.is_file())
+                .map(|candidate| (ToolSource::Path, candidate)),
+        );
+    }
+
+    candidates
+}
+
+/// Runs `<binary> -version`, returning the reported version string.
+fn validate(tool: Tool, binary: &Path) -> Result<String, String> {
+    if !binary.is_file() {
+        return Err("not a file".to_string());
+    }
+
+    let mut command = Command::new(binary);
+    command.arg("-version");
+    let output = process::run(command, &ProcessLimits::quick()).map_err(|err| err.to_string())?;
+
+    if !output.status.success() {
+        return Err(format!("exited with {}", output.status));
+    }
+
+    parse_version(tool, &String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| format!("unrecognized -version output, expected {}", tool.name()))
+}
+
+/// Extracts the version from the first line of `-version` output,
+/// e.g. `ffprobe version 6.1.1-3ubuntu5 Copyright (c) ...` yields `6.1.1-3ubuntu5`.
+pub fn parse_version(tool: Tool, output: &str) -> Option<String> {
+    let mut words = output.lines().next()?.split_whitespace();
+
+    if words.next()? != tool.name() || words.next()? != "version" {
+        return None;
+    }
+
+    words.next().map(str::to_string)
+}
@@ -0,0 +1,689 @@
+//! MPEG transport stream analysis.
+//!
+//! Walks every packet once, tracking PSI tables, continuity counters and PCRs. Per-PID
+//! byte counts are spread over time by interpolating between consecutive PCRs, so the
+//! bitrate timeline follows the mux clock rather than file position.
+
+use crate::analysis::file_name;
+use crate::backend::DemuxError;
+use crate::models::{PcrStats, TsAnalysis, TsPid, TsProgram, TsStream};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use tracing::{debug, info, instrument};
+
+/// Size of a transport packet, excluding any timecode or FEC bytes.
+pub const PACKET_SIZE: usize = 188;
+const SYNC_BYTE: u8 = 0x47;
+const NULL_PID: u16 = 0x1FFF;
+const PAT_PID: u16 = 0x0000;
+/// PCR ticks per second.
+const PCR_CLOCK: f64 = 27_000_000.0;
+/// Gaps between PCRs larger than this are treated as discontinuities, and no
+/// discontinuity is bridged by more.
+const MAX_PCR_GAP: f64 = 1.0;
+/// How long a continuous PCR segment must run before its rate is trusted.
+const MIN_RATE_SPAN: f64 = 0.1;
+/// Width of each bitrate bucket in seconds.
+pub const DEFAULT_INTERVAL: f64 = 1.0;
+/// Narrowest bucket [`analyze`] accepts, as every bucket is kept in memory per PID.
+pub const MIN_INTERVAL: f64 = 0.001;
+
+const READ_CHUNK: usize = 1024 * 1024;
+
+/// Packet framing variants: `(packet size, offset of the sync byte)`.
+const LAYOUTS: [(usize, usize); 3] = [
+    (PACKET_SIZE, 0),
+    // M2TS/Blu-ray: 4-byte timecode before each packet
+    (192, 4),
+    // DVB with 16 bytes of Reed-Solomon parity after each packet
+    (204, 0),
+];
+
+/// On-disk framing of a transport stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TsLayout {
+    pub packet_size: usize,
+    pub sync_offset: usize,
+}
+
+/// Detects transport stream framing from the first bytes of a file.
+///
+/// Requires three consecutive sync bytes, which fits in a 512-byte header for every layout.
+pub fn detect_layout(header: &[u8]) -> Option<TsLayout> {
+    LAYOUTS
+        .iter()
+        .find(|(packet_size, sync_offset)| {
+            (0..3).all(|index| header.get(sync_offset + index * packet_size) == Some(&SYNC_BYTE))
+        })
+        .map(|&(packet_size, sync_offset)| TsLayout {
+            packet_size,
+            sync_offset,
+        })
+}
+
+/// Human-readable codec for a PMT `stream_type`, refined by ES descriptors for private data.
+pub fn stream_type_name(stream_type: u8, descriptors: &[u8]) -> String {
+    let name = match stream_type {
+        0x01 => "MPEG-1 Video",
+        0x02 => "MPEG-2 Video",
+        0x03 => "MPEG-1 Audio",
+        0x04 => "MPEG-2 Audio",
+        0x05 => "Private Sections",
+        0x06 => return private_stream_name(descriptors),
+        0x0F => "AAC",
+        0x10 => "MPEG-4 Video",
+        0x11 => "AAC LATM",
+        0x15 => "Metadata",
+        0x1B => "H.264",
+        0x20 => "H.264 MVC",
+        0x24 => "HEVC",
+        0x33 => "VVC",
+        0x81 => "AC-3",
+        0x82 => "DTS",
+        0x83 => "TrueHD",
+        0x86 => "SCTE-35",
+        0x87 => "E-AC-3",
+        0x90 => "PGS Subtitles",
+        other => return format!("Unknown (0x{other:02X})"),
+    };
+    name.to_string()
+}
+
+fn private_stream_name(descriptors: &[u8]) -> String {
+    let mut rest = descriptors;
+    while let [tag, len, tail @ ..] = rest {
+        let len = usize::from(*len).min(tail.len());
+        let body = &tail[..len];
+        match tag {
+            0x05 if body.len() >= 4 => {
+                return String::from_utf8_lossy(&body[..4]).trim().to_string();
+            }
+            0x56 => return "Teletext".to_string(),
+            0x59 => return "DVB Subtitles".to_string(),
+            0x6A => return "AC-3".to_string(),
+            0x7A => return "E-AC-3".to_string(),
+            0x7B => return "DTS".to_string(),
+            _ => {}
+        }
+        rest = &tail[len..];
+    }
+    "Private Data".to_string()
+}
+
+#[derive(Debug, Default)]
+struct PidState {
+    packets: u64,
+    last_cc: Option<u8>,
+    duplicate: bool,
+    continuity_errors: u64,
+    scrambled: bool,
+    /// Bytes in each interval bucket.
+    buckets: Vec<u64>,
+}
+
+impl PidState {
+    /// Checks the continuity counter, allowing a single duplicate packet.
+    fn continuity(&mut self, cc: u8, has_payload: bool, discontinuity: bool) {
+        if let (Some(last), false) = (self.last_cc, discontinuity) {
+            let valid = if !has_payload {
+                cc == last
+            } else if cc == last && !self.duplicate {
+                self.duplicate = true;
+                true
+            } else {
+                self.duplicate = false;
+                cc == (last + 1) & 0x0F
+            };
+
+            if !valid {
+                self.continuity_errors += 1;
+            }
+        } else {
+            self.duplicate = false;
+        }
+        self.last_cc = Some(cc);
+    }
+
+    fn add_bytes(&mut self, bucket: usize, bytes: u64) {
+        if self.buckets.len() <= bucket {
+            self.buckets.resize(bucket + 1, 0);
+        }
+        self.buckets[bucket] += bytes;
+    }
+}
+
+/// A PCR sample: stream position in bytes and PCR in seconds.
+#[derive(Debug, Clone, Copy)]
+struct PcrSample {
+    offset: u64,
+    pcr: f64,
+    /// Position on the continuous analysis timeline, in seconds.
+    time: f64,
+    /// Starts a new continuous segment.
+    discontinuity: bool,
+}
+
+/// Incremental transport stream analyzer, fed one 188-byte packet at a time.
+#[derive(Debug)]
+pub struct TsAnalyzer {
+    interval: f64,
+    packets: u64,
+    null_packets: u64,
+    transport_errors: u64,
+    pids: BTreeMap<u16, PidState>,
+    sections: HashMap<u16, Vec<u8>>,
+    pmt_pids: BTreeMap<u16, u16>,
+    programs: BTreeMap<u16, TsProgram>,
+    labels: HashMap<u16, String>,
+    pcr_pid: Option<u16>,
+    pcrs: Vec<PcrSample>,
+    /// PIDs of packets seen since the last PCR, awaiting placement on the timeline.
+    pending: Vec<u16>,
+}
+
+impl TsAnalyzer {
+    pub fn new(interval: f64) -> Self {
+        Self {
+            interval,
+            packets: 0,
+            null_packets: 0,
+            transport_errors: 0,
+            pids: BTreeMap::new(),
+            sections: HashMap::new(),
+            pmt_pids: BTreeMap::new(),
+            programs: BTreeMap::new(),
+            labels: HashMap::new(),
+            pcr_pid: None,
+            pcrs: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Processes one packet starting with the sync byte.
+    pub fn packet(&mut self, packet: &[u8]) {
+        debug_assert_eq!(packet.len(), PACKET_SIZE);
+        let offset = self.packets * PACKET_SIZE as u64;
+        self.packets += 1;
+
+        let transport_error = packet[1] & 0x80 != 0;
+        let unit_start = packet[1] & 0x40 != 0;
+        let pid = u16::from_be_bytes([packet[1] & 0x1F, packet[2]]);
+        let scrambled = packet[3] & 0xC0 != 0;
+        let adaptation = packet[3] & 0x20 != 0;
+        let has_payload = packet[3] & 0x10 != 0;
+        let cc = packet[3] & 0x0F;
+
+        if transport_error {
+            self.transport_errors += 1;
+        }
+
+        let mut payload_start = 4;
+        let mut discontinuity = false;
+        let mut pcr = None;
+        if adaptation {
+            let length = usize::from(packet[4]);
+            payload_start = 5 + length;
+            if length > 0 {
+                let flags = packet[5];
+                discontinuity = flags & 0x80 != 0;
+                if flags & 0x10 != 0 && length >= 7 {
+                    pcr = Some(parse_pcr(&packet[6..12]));
+                }
+            }
+        }
+
+        let state = self.pids.entry(pid).or_default();
+        state.packets += 1;
+        state.scrambled |= scrambled;
+
+        if pid == NULL_PID {
+            self.null_packets += 1;
+        } else {
+            state.continuity(cc, has_payload, discontinuity);
+        }
+
+        self.pending.push(pid);
+        if let Some(pcr) = pcr.filter(|_| Some(pid) == self.pcr_pid) {
+            self.record_pcr(offset, pcr, discontinuity);
+        }
+
+        if transport_error || scrambled || !has_payload || payload_start >= PACKET_SIZE {
+            return;
+        }
+        if pid == PAT_PID || self.pmt_pids.contains_key(&pid) {
+            self.psi_payload(pid, unit_start, &packet[payload_start..]);
+        }
+    }
+
+    fn record_pcr(&mut self, offset: u64, pcr: f64, flagged: bool) {
+        let previous = self.pcrs.last().copied();
+        let (time, discontinuity) = match previous {
+            None => (0.0, false),
+            Some(previous) => {
+                let delta = pcr - previous.pcr;
+                if !flagged && (0.0..=MAX_PCR_GAP).contains(&delta) {
+                    (previous.time + delta, false)
+                } else {
+                    // Bridge the gap at the rate of the previous segment, when known
+                    let rate = self.segment_rate();
+                    let bridged = rate
+                        .map(|rate| (offset - previous.offset) as f64 * 8.0 / rate)
+                        .unwrap_or(0.0)
+                        .min(MAX_PCR_GAP);
+                    (previous.time + bridged, true)
+                }
+            }
+        };
+
+        let start = previous.map_or(0.0, |previous| previous.time);
+        self.place_pending(start, time);
+        self.pcrs.push(PcrSample {
+            offset,
+            pcr,
+            time,
+            discontinuity,
+        });
+    }
+
+    /// Transport rate in bits per second over the current continuous PCR segment, once
+    /// it spans at least [`MIN_RATE_SPAN`].
+    fn segment_rate(&self) -> Option<f64> {
+        let last = self.pcrs.last()?;
+        let first = self
+            .pcrs
+            .iter()
+            .rev()
+            .find(|sample| sample.discontinuity)
+            .unwrap_or(&self.pcrs[0]);
+        let elapsed = last.time - first.time;
+        (elapsed >= MIN_RATE_SPAN).then(|| (last.offset - first.offset) as f64 * 8.0 / elapsed)
+    }
+
+    /// Spreads pending packets evenly between two timeline positions.
+    fn place_pending(&mut self, start: f64, end: f64) {
+        let count = self.pending.len();
+        for (index, pid) in self.pending.drain(..).enumerate() {
+            let time = start + (end - start) * index as f64 / count as f64;
+            let bucket = (time / self.interval).max(0.0) as usize;
+            self.pids
+                .get_mut(&pid)
+                .expect("pending PIDs are tracked")
+                .add_bytes(bucket, PACKET_SIZE as u64);
+        }
+    }
+
+    /// Accumulates PSI section bytes for `pid`, handling any sections completed.
+    fn psi_payload(&mut self, pid: u16, unit_start: bool, payload: &[u8]) {
+        let mut completed = Vec::new();
+
+        if unit_start {
+            let pointer = usize::from(payload[0]);
+            let Some(tail) = payload.get(1..1 + pointer) else {
+                return;
+            };
+            if let Some(mut buffer) = self.sections.remove(&pid) {
+                buffer.extend_from_slice(tail);
+                completed.extend(take_sections(&mut buffer));
+            }
+            let mut buffer = payload[1 + pointer..].to_vec();
+            completed.extend(take_sections(&mut buffer));
+            if !buffer.is_empty() {
+                self.sections.insert(pid, buffer);
+            }
+        } else if let Some(buffer) = self.sections.get_mut(&pid) {
+            buffer.extend_from_slice(payload);
+            completed.extend(take_sections(buffer));
+            if buffer.is_empty() {
+                self.sections.remove(&pid);
+            }
+        }
+
+        for section in completed {
+            match section.first() {
+                Some(0x00) if pid == PAT_PID => self.parse_pat(&section),
+                Some(0x02) => {
+                    if let Some(&program_number) = self.pmt_pids.get(&pid) {
+                        self.parse_pmt(pid, program_number, &section);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn parse_pat(&mut self, section: &[u8]) {
+        let Some(entries) = section_body(section) else {
+            return;
+        };
+
+        for entry in entries.chunks_exact(4) {
+            let program_number = u16::from_be_bytes([entry[0], entry[1]]);
+            let pid = u16::from_be_bytes([entry[2] & 0x1F, entry[3]]);
+            if program_number == 0 {
+                // Network information table
+                self.labels.insert(pid, "NIT".to_string());
+                continue;
+            }
+            if self.pmt_pids.insert(pid, program_number).is_none() {
+                debug!(program_number, pmt_pid = pid, "Found program");
+            }
+            self.labels
+                .insert(pid, format!("PMT (program {program_number})"));
+        }
+    }
+
+    fn parse_pmt(&mut self, pmt_pid: u16, program_number: u16, section: &[u8]) {
+        let Some(body) = section_body(section) else {
+            return;
+        };
+        if body.len() < 4 {
+            return;
+        }
+
+        let pcr_pid = u16::from_be_bytes([body[0] & 0x1F, body[1]]);
+        let info_length = usize::from(u16::from_be_bytes([body[2] & 0x0F, body[3]]));
+        let mut rest = body.get(4 + info_length..).unwrap_or_default();
+
+        let mut streams = Vec::new();
+        while rest.len() >= 5 {
+            let stream_type = rest[0];
+            let pid = u16::from_be_bytes([rest[1] & 0x1F, rest[2]]);
+            let es_info_length = usize::from(u16::from_be_bytes([rest[3] & 0x0F, rest[4]]));
+            let descriptors = rest.get(5..5 + es_info_length).unwrap_or_default();
+            let codec = stream_type_name(stream_type, descriptors);
+
+            self.labels.insert(pid, codec.clone());
+            streams.push(TsStream {
+                pid,
+                stream_type,
+                codec,
+            });
+            rest = rest.get(5 + es_info_length..).unwrap_or_default();
+        }
+
+        let pcr_pid = (pcr_pid != NULL_PID).then_some(pcr_pid);
+        // Follow the first program's clock for the timeline
+        if self.pcr_pid.is_none() {
+            self.pcr_pid = pcr_pid;
+        }
+
+        self.programs.insert(
+            program_number,
+            TsProgram {
+                program_number,
+                pmt_pid,
+                pcr_pid,
+                streams,
+            },
+        );
+    }
+
+    fn label(&self, pid: u16) -> String {
+        if let Some(label) = self.labels.get(&pid) {
+            return label.clone();
+        }
+        match pid {
+            0x0000 => "PAT",
+            0x0001 => "CAT",
+            0x0002 => "TSDT",
+            0x0010 => "NIT",
+            0x0011 => "SDT",
+            0x0012 => "EIT",
+            0x0014 => "TDT/TOT",
+            NULL_PID => "Null",
+            _ => "Unknown",
+        }
+        .to_string()
+    }
+
+    fn pcr_stats(&self) -> Option<PcrStats> {
+        let pid = self.pcr_pid?;
+        if self.pcrs.is_empty() {
+            return None;
+        }
+
+        let mut max_interval: f64 = 0.0;
+        let mut residuals = Vec::with_capacity(self.pcrs.len());
+        let mut segment_start = 0;
+        for index in 1..=self.pcrs.len() {
+            let ends_segment = index == self.pcrs.len() || self.pcrs[index].discontinuity;
+            if !ends_segment {
+                max_interval = max_interval.max(self.pcrs[index].pcr - self.pcrs[index - 1].pcr);
+                continue;
+            }
+            residuals.extend(fit_residuals(&self.pcrs[segment_start..index]));
+            segment_start = index;
+        }
+
+        let max_jitter = residuals
+            .iter()
+            .fold(0.0f64, |max, value| max.max(value.abs()));
+        let mean_jitter = if residuals.is_empty() {
+            0.0
+        } else {
+            residuals.iter().map(|value| value.abs()).sum::<f64>() / residuals.len() as f64
+        };
+
+        Some(PcrStats {
+            pid,
+            count: self.pcrs.len() as u32,
+            discontinuities: self
+                .pcrs
+                .iter()
+                .filter(|sample| sample.discontinuity)
+                .count() as u32,
+            max_interval_ms: max_interval * 1000.0,
+            max_jitter_ms: max_jitter * 1000.0,
+            mean_jitter_ms: mean_jitter * 1000.0,
+        })
+    }
+
+    /// Completes the analysis, placing packets after the last PCR at the final rate.
+    pub fn finish(mut self, id: String, packet_size: usize, sync_losses: u64) -> TsAnalysis {
+        let last = self.pcrs.last().copied();
+        let rate = self.segment_rate();
+        if let (Some(last), Some(rate)) = (last, rate) {
+            let tail = self.pending.len() as f64 * PACKET_SIZE as f64 * 8.0 / rate;
+            self.place_pending(last.time, last.time + tail);
+        }
+
+        let duration = last
+            .filter(|_| self.pcrs.len() > 1)
+            .map(|last| last.time - self.pcrs[0].time);
+        let bitrate = duration.filter(|duration| *duration > 0.0).map(|duration| {
+            let span = last.expect("duration implies PCRs").offset - self.pcrs[0].offset;
+            span as f64 * 8.0 / duration
+        });
+        let pcr = self.pcr_stats();
+
+        let pids = self
+            .pids
+            .iter()
+            .map(|(&pid, state)| TsPid {
+                pid,
+                label: self.label(pid),
+                packet_count: state.packets,
+                average_bitrate: duration
+                    .filter(|duration| *duration > 0.0)
+                    .map(|duration| (state.packets * PACKET_SIZE as u64) as f64 * 8.0 / duration),
+                bitrate: state
+                    .buckets
+                    .iter()
+                    .map(|bytes| *bytes as f64 * 8.0 / self.interval)
+                    .collect(),
+                continuity_errors: state.continuity_errors,
+                scrambled: state.scrambled,
+            })
+            .collect();
+
+        TsAnalysis {
+            id,
+            packet_size: packet_size as u32,
+            packet_count: self.packets,
+            duration,
+            bitrate,
+            interval: self.interval,
+            programs: self.programs.into_values().collect(),
+            pids,
+            null_ratio: if self.packets == 0 {
+                0.0
+            } else {
+                self.null_packets as f64 / self.packets as f64
+            },
+            continuity_errors: self
+                .pids
+                .values()
+                .map(|state| state.continuity_errors)
+                .sum(),
+            transport_errors: self.transport_errors,
+            sync_losses,
+            pcr,
+        }
+    }
+}
+
+/// Decodes a 6-byte PCR field into seconds.
+fn parse_pcr(bytes: &[u8]) -> f64 {
+    let base = (u64::from(bytes[0]) << 25)
+        | (u64::from(bytes[1]) << 17)
+        | (u64::from(bytes[2]) << 9)
+        | (u64::from(bytes[3]) << 1)
+        | (u64::from(bytes[4]) >> 7);
+    let extension = (u64::from(bytes[4] & 0x01) << 8) | u64::from(bytes[5]);
+    (base * 300 + extension) as f64 / PCR_CLOCK
+}
+
+/// Splits complete sections off the front of `buffer`, dropping trailing stuffing.
+fn take_sections(buffer: &mut Vec<u8>) -> Vec<Vec<u8>> {
+    let mut sections = Vec::new();
+    loop {
+        match buffer.first() {
+            None => break,
+            Some(0xFF) => {
+                buffer.clear();
+                break;
+            }
+            Some(_) => {}
+        }
+        if buffer.len() < 3 {
+            break;
+        }
+
+        let length = 3 + usize::from(u16::from_be_bytes([buffer[1] & 0x0F, buffer[2]]));
+        if buffer.len() < length {
+            break;
+        }
+        sections.push(buffer.drain(..length).collect());
+    }
+    sections
+}
+
+/// The body of a long-form PSI section, between the 8-byte header and the CRC.
+fn section_body(section: &[u8]) -> Option<&[u8]> {
+    // Only the current table applies; next-version tables are announced ahead of time
+    if section.len() < 12 || section[5] & 0x01 == 0 {
+        return None;
+    }
+    section.get(8..section.len() - 4)
+}
+
+/// Residuals of a least-squares fit of PCR against byte offset, in seconds.
+fn fit_residuals(samples: &[PcrSample]) -> Vec<f64> {
+    if samples.len() < 3 {
+        return Vec::new();
+    }
+
+    let count = samples.len() as f64;
+    let origin = samples[0].offset as f64;
+    let mean_x = samples
+        .iter()
+        .map(|s| s.offset as f64 - origin)
+        .sum::<f64>()
+        / count;
+    let mean_y = samples.iter().map(|s| s.time).sum::<f64>() / count;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for sample in samples {
+        let dx = sample.offset as f64 - origin - mean_x;
+        covariance += dx * (sample.time - mean_y);
+        variance += dx * dx;
+    }
+    if variance == 0.0 {
+        return Vec::new();
+    }
+
+    let slope = covariance / variance;
+    samples
+        .iter()
+        .map(|sample| {
+            let expected = mean_y + slope * (sample.offset as f64 - origin - mean_x);
+            sample.time - expected
+        })
+        .collect()
+}
+
+/// Analyzes the transport stream at `path`, bucketing bitrates into `interval`-second bins.
+#[instrument(skip(path), fields(path = %path.display()))]
+pub fn analyze(path: &Path, interval: f64) -> Result<TsAnalysis, DemuxError> {
+    // NaN fails the comparison too
+    if !(MIN_INTERVAL..=f64::MAX).contains(&interval) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("bitrate interval must be at least {MIN_INTERVAL} seconds, not {interval}"),
+        )
+        .into());
+    }
+    let mut file = File::open(path)?;
+    let mut header = Vec::with_capacity(1024);
+    (&mut file).take(1024).read_to_end(&mut header)?;
+    let layout = detect_layout(&header)
+        .ok_or_else(|| DemuxError::Malformed("no transport stream sync found".to_string()))?;
+    file.seek(SeekFrom::Start(0))?;
+    info!(
+        packet_size = layout.packet_size,
+        "Analyzing transport stream"
+    );
+
+    let mut analyzer = TsAnalyzer::new(interval);
+    let mut data = Vec::with_capacity(READ_CHUNK + layout.packet_size);
+    let mut chunk = vec![0; READ_CHUNK];
+    let mut sync_losses = 0;
+    let mut in_sync = true;
+
+    loop {
+        let read = file.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        data.extend_from_slice(&chunk[..read]);
+
+        let mut pos = 0;
+        while pos + layout.packet_size <= data.len() {
+            let start = pos + layout.sync_offset;
+            if data[start] != SYNC_BYTE {
+                // Slide byte by byte until packets line up again
+                if in_sync {
+                    sync_losses += 1;
+                    in_sync = false;
+                }
+                pos += 1;
+                continue;
+            }
+            in_sync = true;
+            analyzer.packet(&data[start..start + PACKET_SIZE]);
+            pos += layout.packet_size;
+        }
+        data.drain(..pos);
+    }
+
+    let analysis = analyzer.finish(file_name(path), layout.packet_size, sync_losses);
+    info!(
+        packets = analysis.packet_count,
+        pids = analysis.pids.len(),
+        programs = analysis.programs.len(),
+        continuity_errors = analysis.continuity_errors,
+        "Transport stream analyzed"
+    );
+    Ok(analysis)
+}
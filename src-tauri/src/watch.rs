@@ -0,0 +1,329 @@
+//! Watch folders: automatic analysis of media files as they appear.
+//!
+//! Folders are polled rather than subscribed to through OS notifications, which don't
+//! work on the network shares render farms typically write to. A file is only handed
+//! on once its size and modification time have stopped changing for a while, so
+//! encodes that are still being written aren't analyzed half-finished.
+
+use crate::analysis::{self, AnalysisError};
+use crate::models::{BitrateData, MediaType, StreamResult, StreamResultError};
+use crate::scan::{self, ScanError, ScanSettings};
+use crate::state::AppState;
+use crate::strings::transform_filename;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+use thiserror::Error;
+use tracing::{debug, info, instrument, warn};
+use ts_rs::TS;
+
+/// Name of the event emitted for every analyzed file.
+pub const WATCH_EVENT: &str = "watch-result";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+#[serde(default)]
+pub struct WatchSettings {
+    /// How often watched folders are rescanned, in milliseconds.
+    pub poll_interval_ms: u32,
+    /// How long a file's size and modification time must stay unchanged before it is
+    /// analyzed, in milliseconds.
+    pub stable_for_ms: u32,
+    /// Whether files already present when watching starts are analyzed too.
+    pub include_existing: bool,
+    /// Whether bitrate data is extracted for video files, on top of stream probing.
+    pub extract_bitrate: bool,
+}
+
+impl Default for WatchSettings {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: 1000,
+            stable_for_ms: 3000,
+            include_existing: false,
+            extract_bitrate: true,
+        }
+    }
+}
+
+impl WatchSettings {
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_millis(u64::from(self.poll_interval_ms.max(10)))
+    }
+
+    pub fn stable_for(&self) -> Duration {
+        Duration::from_millis(u64::from(self.stable_for_ms))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WatchError {
+    #[error("Not a directory: {0}")]
+    NotDirectory(String),
+    #[error("Already watching {0}")]
+    AlreadyWatching(String),
+    #[error("Not watching {0}")]
+    NotWatching(String),
+    #[error(transparent)]
+    Scan(#[from] ScanError),
+    #[error("Could not start watcher: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Result of analyzing a file that settled in a watched folder.
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub enum WatchEvent {
+    Analyzed {
+        /// The watched folder the file was found in.
+        folder: String,
+        streams: StreamResult,
+        /// Frame sizes for video files, when enabled and extractable.
+        bitrate: Option<BitrateData>,
+    },
+    Failed {
+        folder: String,
+        path: String,
+        error: StreamResultError,
+    },
+}
+
+/// Last observed state of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl Snapshot {
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Tracked {
+    snapshot: Snapshot,
+    changed_at: Instant,
+    reported: bool,
+}
+
+/// Debounces scan results into "this file has settled" notifications.
+#[derive(Debug)]
+pub struct StabilityTracker {
+    stable_for: Duration,
+    files: HashMap<PathBuf, Tracked>,
+}
+
+impl StabilityTracker {
+    pub fn new(stable_for: Duration) -> Self {
+        Self {
+            stable_for,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Records files as already handled, so only later changes are reported.
+    pub fn baseline(&mut self, files: impl IntoIterator<Item = (PathBuf, Snapshot)>, now: Instant) {
+        for (path, snapshot) in files {
+            self.files.insert(
+                path,
+                Tracked {
+                    snapshot,
+                    changed_at: now,
+                    reported: true,
+                },
+            );
+        }
+    }
+
+    /// Records the outcome of a scan and returns the files that have just settled.
+    ///
+    /// Files missing from `files` are forgotten, so a file that is deleted and written
+    /// again is reported again.
+    pub fn update(
+        &mut self,
+        files: impl IntoIterator<Item = (PathBuf, Snapshot)>,
+        now: Instant,
+    ) -> Vec<PathBuf> {
+        let mut seen = HashMap::new();
+        for (path, snapshot) in files {
+            let tracked = match self.files.remove(&path) {
+                Some(tracked) if tracked.snapshot == snapshot => tracked,
+                _ => Tracked {
+                    snapshot,
+                    changed_at: now,
+                    reported: false,
+                },
+            };
+            seen.insert(path, tracked);
+        }
+        self.files = seen;
+
+        let mut settled: Vec<PathBuf> = self
+            .files
+            .iter_mut()
+            .filter(|(_, tracked)| {
+                !tracked.reported && now.duration_since(tracked.changed_at) >= self.stable_for
+            })
+            .map(|(path, tracked)| {
+                tracked.reported = true;
+                path.clone()
+            })
+            .collect();
+        settled.sort();
+        settled
+    }
+}
+
+fn snapshot_files(dir: &Path, scan: &ScanSettings) -> Result<Vec<(PathBuf, Snapshot)>, ScanError> {
+    let files = scan::walk_files(dir, scan, |_| true)?;
+    Ok(files
+        .into_iter()
+        .filter_map(|path| Snapshot::of(&path).map(|snapshot| (path, snapshot)))
+        .collect())
+}
+
+/// A background thread polling one folder.
+#[derive(Debug)]
+pub struct FolderWatcher {
+    dir: PathBuf,
+    stop: Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+impl FolderWatcher {
+    /// Starts polling `dir`, calling `on_settled` from the watcher thread for every file
+    /// that settles.
+    pub fn start(
+        dir: &Path,
+        settings: WatchSettings,
+        scan: ScanSettings,
+        mut on_settled: impl FnMut(&Path) + Send + 'static,
+    ) -> Result<Self, WatchError> {
+        if !dir.is_dir() {
+            return Err(WatchError::NotDirectory(dir.display().to_string()));
+        }
+        // Surface bad patterns now rather than on every poll
+        scan::Filters::new(&scan)?;
+
+        let mut tracker = StabilityTracker::new(settings.stable_for());
+        if !settings.include_existing {
+            tracker.baseline(snapshot_files(dir, &scan)?, Instant::now());
+        }
+
+        let (stop, stopped) = mpsc::channel();
+        let thread_dir = dir.to_path_buf();
+        let handle = thread::Builder::new()
+            .name("byte-me-watch".to_string())
+            .spawn(move || loop {
+                match snapshot_files(&thread_dir, &scan) {
+                    Ok(files) => {
+                        for path in tracker.update(files, Instant::now()) {
+                            debug!(path = %path.display(), "File settled");
+                            on_settled(&path);
+                        }
+                    }
+                    Err(err) => warn!(error = %err, "Failed to scan watched folder"),
+                }
+
+                match stopped.recv_timeout(settings.poll_interval()) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    // Stopped explicitly, or the handle was dropped
+                    Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+                }
+            })?;
+
+        info!(dir = %dir.display(), "Started watching folder");
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            stop,
+            handle,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Stops polling, waiting for an in-progress analysis to finish.
+    pub fn stop(self) {
+        let _ = self.stop.send(());
+        if self.handle.join().is_err() {
+            warn!(dir = %self.dir.display(), "Watcher thread panicked");
+        }
+        info!(dir = %self.dir.display(), "Stopped watching folder");
+    }
+}
+
+/// Probes a settled file and, for video, extracts its bitrate data, through the cache.
+///
+/// Returns `None` for files that aren't media, which are silently ignored.
+#[instrument(skip(state, folder, path), fields(path = %path.display()))]
+pub fn analyze_settled(
+    state: &AppState,
+    folder: &Path,
+    path: &Path,
+    extract_bitrate: bool,
+) -> Option<WatchEvent> {
+    let truncated_name = transform_filename(&analysis::file_name(path), 15);
+    let folder = folder.display().to_string();
+    let failed = |err: AnalysisError| WatchEvent::Failed {
+        folder: folder.clone(),
+        path: path.display().to_string(),
+        error: StreamResultError {
+            filename: Some(analysis::file_name(path)),
+            reason: err.to_string(),
+            error_type: err.kind().to_string(),
+        },
+    };
+
+    let ffprobe = state.ffprobe();
+    match analysis::detect_file(ffprobe.as_ref().ok(), path) {
+        Ok(_) => {}
+        Err(AnalysisError::NotMedia(_)) => {
+            debug!(filename = %truncated_name, "Ignoring non-media file");
+            return None;
+        }
+        Err(err) => return Some(failed(err)),
+    }
+
+    let ffprobe = match ffprobe {
+        Ok(ffprobe) => ffprobe,
+        Err(err) => return Some(failed(AnalysisError::from(err))),
+    };
+
+    let streams = match state.cached(path, ffprobe.version(), "streams", || {
+        analysis::probe_file(&ffprobe, path)
+    }) {
+        Ok(streams) => streams,
+        Err(err) => return Some(failed(err)),
+    };
+
+    let bitrate = (extract_bitrate && matches!(streams.media_type, MediaType::Video))
+        .then(|| {
+            state.cached(path, ffprobe.version(), "bitrate", || {
+                analysis::extract_bitrate_data(&ffprobe, path)
+            })
+        })
+        .and_then(|result| {
+            result
+                .inspect_err(
+                    |err| warn!(filename = %truncated_name, error = %err, "No bitrate data"),
+                )
+                .ok()
+        });
+
+    info!(filename = %truncated_name, "Analyzed watched file");
+    Some(WatchEvent::Analyzed {
+        folder,
+        streams,
+        bitrate,
+    })
+}
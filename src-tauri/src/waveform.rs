@@ -0,0 +1,144 @@
+//! Min/max peak extraction for drawing waveforms.
+//!
+//! ffmpeg decodes the chosen audio stream to mono 32-bit float PCM at a fixed rate, so
+//! bucket boundaries are known without probing the source. The PCM is streamed and
+//! folded into buckets as it arrives; an hour of audio never sits in memory.
+
+use crate::analysis::{file_name, AnalysisError};
+use crate::ff::Ffmpeg;
+use crate::models::{Waveform, WaveformPeak};
+use std::path::Path;
+use tracing::{info, instrument};
+
+/// Rate the audio is decoded at.
+pub const SAMPLE_RATE: u32 = 48_000;
+/// Buckets per second when the caller doesn't ask for a resolution.
+pub const DEFAULT_RESOLUTION: u32 = 50;
+/// Finest supported resolution: one bucket per millisecond.
+pub const MAX_RESOLUTION: u32 = 1000;
+
+const SAMPLE_SIZE: usize = std::mem::size_of::<f32>();
+
+/// Folds little-endian `f32` samples into min/max buckets.
+#[derive(Debug)]
+pub struct PeakAccumulator {
+    samples_per_bucket: usize,
+    count: usize,
+    current: WaveformPeak,
+    peaks: Vec<WaveformPeak>,
+    /// Bytes of a sample split across two chunks.
+    partial: Vec<u8>,
+}
+
+impl PeakAccumulator {
+    pub fn new(samples_per_bucket: usize) -> Self {
+        Self {
+            samples_per_bucket: samples_per_bucket.max(1),
+            count: 0,
+            current: WaveformPeak { min: 0.0, max: 0.0 },
+            peaks: Vec::new(),
+            partial: Vec::with_capacity(SAMPLE_SIZE),
+        }
+    }
+
+    pub fn push_sample(&mut self, sample: f32) {
+        // NaN from a broken decoder would poison the bucket, so treat it as silence
+        let sample = if sample.is_nan() {
+            0.0
+        } else {
+            sample.clamp(-1.0, 1.0)
+        };
+        if self.count == 0 {
+            self.current = WaveformPeak {
+                min: sample,
+                max: sample,
+            };
+        } else {
+            self.current.min = self.current.min.min(sample);
+            self.current.max = self.current.max.max(sample);
+        }
+
+        self.count += 1;
+        if self.count == self.samples_per_bucket {
+            self.peaks.push(self.current);
+            self.count = 0;
+        }
+    }
+
+    pub fn push_bytes(&mut self, mut bytes: &[u8]) {
+        if !self.partial.is_empty() {
+            let needed = (SAMPLE_SIZE - self.partial.len()).min(bytes.len());
+            self.partial.extend_from_slice(&bytes[..needed]);
+            bytes = &bytes[needed..];
+            if self.partial.len() < SAMPLE_SIZE {
+                return;
+            }
+            let sample = f32::from_le_bytes(self.partial[..].try_into().expect("four bytes"));
+            self.partial.clear();
+            self.push_sample(sample);
+        }
+
+        let mut samples = bytes.chunks_exact(SAMPLE_SIZE);
+        for sample in &mut samples {
+            self.push_sample(f32::from_le_bytes(sample.try_into().expect("four bytes")));
+        }
+        self.partial.extend_from_slice(samples.remainder());
+    }
+
+    /// Closes the last, possibly partial, bucket and returns all of them.
+    pub fn finish(mut self) -> Vec<WaveformPeak> {
+        if self.count > 0 {
+            self.peaks.push(self.current);
+        }
+        self.peaks
+    }
+}
+
+/// Extracts `resolution` min/max pairs per second from the `audio_stream`-th audio stream.
+#[instrument(skip(ffmpeg, path), fields(path = %path.display()))]
+pub fn extract(
+    ffmpeg: &Ffmpeg,
+    path: &Path,
+    audio_stream: u32,
+    resolution: u32,
+) -> Result<Waveform, AnalysisError> {
+    let resolution = resolution.clamp(1, MAX_RESOLUTION);
+    let map = format!("0:a:{audio_stream}");
+    let rate = SAMPLE_RATE.to_string();
+    let mut accumulator = PeakAccumulator::new((SAMPLE_RATE / resolution) as usize);
+
+    ffmpeg.stream(
+        &[],
+        path,
+        &[
+            "-map",
+            &map,
+            "-ac",
+            "1",
+            "-ar",
+            &rate,
+            "-f",
+            "f32le",
+            "-c:a",
+            "pcm_f32le",
+            "-",
+        ],
+        |chunk| {
+            accumulator.push_bytes(chunk);
+            Ok(())
+        },
+    )?;
+
+    let peaks = accumulator.finish();
+    if peaks.is_empty() {
+        return Err(AnalysisError::NoFrames);
+    }
+
+    info!(bucket_count = peaks.len(), resolution, "Waveform extracted");
+    Ok(Waveform {
+        id: file_name(path),
+        audio_stream,
+        resolution,
+        peaks,
+    })
+}
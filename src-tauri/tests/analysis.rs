@@ -0,0 +1,94 @@
+use byte_me_lib::analysis::{analyze_file, detect_file, probe_file, AnalysisError};
+use byte_me_lib::capabilities::Capabilities;
+use byte_me_lib::ff::Ffprobe;
+use byte_me_lib::models::{FileCandidacy, MediaType};
+use byte_me_lib::tools::{ResolvedTool, Tool, ToolSource};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// An ffprobe handle that is never actually executed by these tests.
+fn unused_ffprobe() -> Ffprobe {
+    Ffprobe::new(
+        ResolvedTool {
+            tool: Tool::Ffprobe,
+            path: PathBuf::from("/nonexistent/ffprobe"),
+            version: "6.0".to_string(),
+            source: ToolSource::Path,
+        },
+        Capabilities::new(None, Vec::new()),
+    )
+}
+
+#[test]
+fn test_missing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing.mp4");
+
+    let err = detect_file(None, &path).unwrap_err();
+    assert!(matches!(err, AnalysisError::NotFound));
+    assert_eq!(err.kind(), "not_found");
+
+    let file = analyze_file(None, &path);
+    assert_eq!(file.filename, "missing.mp4");
+    assert_eq!(file.size, 0);
+    assert!(
+        matches!(file.candidacy, FileCandidacy::Error { ref reason } if reason == "File does not exist")
+    );
+}
+
+#[test]
+fn test_directory_is_not_a_file() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let err = detect_file(None, dir.path()).unwrap_err();
+    assert!(matches!(err, AnalysisError::NotFile));
+    assert_eq!(err.kind(), "not_file");
+
+    // Probing short-circuits before ever spawning ffprobe
+    assert!(matches!(
+        probe_file(&unused_ffprobe(), dir.path()),
+        Err(AnalysisError::NotFile)
+    ));
+}
+
+#[test]
+fn test_non_media_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.txt");
+    std::fs::File::create(&path)
+        .unwrap()
+        .write_all(b"just some notes")
+        .unwrap();
+
+    let err = detect_file(None, &path).unwrap_err();
+    assert!(matches!(err, AnalysisError::NotMedia(MediaType::Document)));
+    assert_eq!(err.to_string(), "Not a media file (detected as Document)");
+
+    let file = analyze_file(None, &path);
+    assert_eq!(file.size, 15);
+    assert!(matches!(file.candidacy, FileCandidacy::Error { .. }));
+}
+
+#[test]
+fn test_media_file_candidacy() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("clip.png");
+    // PNG signature followed by a truncated IHDR chunk is enough for magic detection
+    std::fs::write(
+        &path,
+        b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR\x00\x00\x00\x01\x00\x00\x00\x01",
+    )
+    .unwrap();
+
+    assert!(matches!(
+        detect_file(None, &path),
+        Ok(detection) if detection.media_type == MediaType::Image
+    ));
+    assert!(matches!(
+        analyze_file(None, &path).candidacy,
+        FileCandidacy::Success {
+            file_type: MediaType::Image,
+            ..
+        }
+    ));
+}
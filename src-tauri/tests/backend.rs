@@ -0,0 +1,426 @@
+use byte_me_lib::analysis::AnalysisError;
+use byte_me_lib::backend::{
+    extract_bitrate_data, parse_compact_line, AnalysisBackend, AnimationBackend, DemuxError,
+    MatroskaBackend, Mp4Backend, Sample,
+};
+use byte_me_lib::capabilities::{Capabilities, FrameSizeSource};
+use byte_me_lib::ff::Ffprobe;
+use byte_me_lib::models::BitrateData;
+use byte_me_lib::tools::{ResolvedTool, Tool, ToolSource};
+use std::path::{Path, PathBuf};
+
+mod common;
+use common::*;
+
+/// An ffprobe handle pointing nowhere, so any fallback fails to spawn.
+fn unused_ffprobe() -> Ffprobe {
+    Ffprobe::new(
+        ResolvedTool {
+            tool: Tool::Ffprobe,
+            path: PathBuf::from("/nonexistent/ffprobe"),
+            version: "6.0".to_string(),
+            source: ToolSource::Path,
+        },
+        Capabilities::new(None, Vec::new()),
+    )
+}
+
+fn write_file(dir: &Path, name: &str, contents: &[u8]) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn summarize(data: &BitrateData) -> Vec<(u64, f64, bool)> {
+    data.frames
+        .iter()
+        .map(|frame| {
+            let timestamp = (frame.timestamp.unwrap() * 1000.0).round() / 1000.0;
+            (frame.packet_size, timestamp, frame.keyframe)
+        })
+        .collect()
+}
+
+// --- MP4 ---
+
+fn progressive_mp4() -> Vec<u8> {
+    // Decode order I P B B; composition offsets put the B frames before the P
+    let stbl = [
+        full_box(b"stsz", 0, 0, &words(&[0, 4, 1000, 200, 300, 150])),
+        full_box(b"stts", 0, 0, &words(&[1, 4, 512])),
+        full_box(
+            b"ctts",
+            1,
+            0,
+            &words(&[4, 1, 0, 1, 1024, 1, 0, 1, (-1024i32) as u32]),
+        ),
+        full_box(b"stss", 0, 0, &words(&[1, 1])),
+    ]
+    .concat();
+    let audio = trak(2, b"soun", 48000, &full_box(b"stsz", 0, 0, &words(&[0, 0])));
+    let video = trak(1, b"vide", 12800, &stbl);
+
+    [
+        ftyp(),
+        mp4_box(b"moov", &[audio, video].concat()),
+        mp4_box(b"mdat", &[0; 1650]),
+    ]
+    .concat()
+}
+
+fn fragmented_mp4() -> Vec<u8> {
+    [
+        init_segment(1000, 40),
+        fragment(1, 0, &[800, 100], true),
+        fragment(2, 80, &[700, 90], true),
+    ]
+    .concat()
+}
+
+#[test]
+fn test_mp4_sample_tables() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_file(dir.path(), "progressive.mp4", &progressive_mp4());
+
+    let data = Mp4Backend.extract_bitrate_data(&path).unwrap();
+    assert_eq!(data.id, "progressive.mp4");
+    assert_eq!(
+        summarize(&data),
+        vec![
+            (1000, 0.0, true),
+            (150, 0.04, false),
+            (300, 0.08, false),
+            (200, 0.12, false)
+        ]
+    );
+    let numbers: Vec<u32> = data.frames.iter().map(|frame| frame.frame_num).collect();
+    assert_eq!(numbers, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn test_mp4_fragments() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_file(dir.path(), "fragmented.mp4", &fragmented_mp4());
+
+    let data = Mp4Backend.extract_bitrate_data(&path).unwrap();
+    assert_eq!(
+        summarize(&data),
+        vec![
+            (800, 0.0, true),
+            (100, 0.04, false),
+            (700, 0.08, true),
+            (90, 0.12, false)
+        ]
+    );
+}
+
+#[test]
+fn test_mp4_supports() {
+    assert!(Mp4Backend.supports(&progressive_mp4()[..64]));
+    assert!(!Mp4Backend.supports(b"\x1a\x45\xdf\xa3 not an mp4"));
+    assert!(!Mp4Backend.supports(b"tiny"));
+}
+
+// --- Matroska ---
+
+/// Encodes an element with an 8-byte size, or the unknown size when `payload` is `None`.
+fn element(id: &[u8], payload: Option<&[u8]>) -> Vec<u8> {
+    let mut out = id.to_vec();
+    match payload {
+        Some(payload) => {
+            out.push(0x01);
+            out.extend_from_slice(&(payload.len() as u64).to_be_bytes()[1..]);
+            out.extend_from_slice(payload);
+        }
+        None => out.extend_from_slice(&[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+    }
+    out
+}
+
+fn uint(id: &[u8], value: u64) -> Vec<u8> {
+    element(id, Some(&value.to_be_bytes()))
+}
+
+fn block(id: &[u8], track: u8, relative: i16, flags: u8, size: usize) -> Vec<u8> {
+    let mut payload = vec![0x80 | track];
+    payload.extend_from_slice(&relative.to_be_bytes());
+    payload.push(flags);
+    payload.extend(vec![0; size]);
+    element(id, Some(&payload))
+}
+
+const SIMPLE_BLOCK: &[u8] = &[0xA3];
+const CLUSTER: &[u8] = &[0x1F, 0x43, 0xB6, 0x75];
+const TIMESTAMP: &[u8] = &[0xE7];
+
+fn matroska() -> Vec<u8> {
+    let header = element(
+        &[0x1A, 0x45, 0xDF, 0xA3],
+        Some(&element(&[0x42, 0x82], Some(b"webm"))),
+    );
+    let info = element(
+        &[0x15, 0x49, 0xA9, 0x66],
+        Some(&uint(&[0x2A, 0xD7, 0xB1], 1_000_000)),
+    );
+    let audio = element(
+        &[0xAE],
+        Some(&[uint(&[0xD7], 1), uint(&[0x83], 2)].concat()),
+    );
+    let video = element(
+        &[0xAE],
+        Some(&[uint(&[0xD7], 2), uint(&[0x83], 1)].concat()),
+    );
+    let tracks = element(&[0x16, 0x54, 0xAE, 0x6B], Some(&[audio, video].concat()));
+
+    let group = element(
+        &[0xA0],
+        Some(&[block(&[0xA1], 2, 40, 0, 120), uint(&[0xFB], 0)].concat()),
+    );
+    let first = element(
+        CLUSTER,
+        Some(
+            &[
+                uint(TIMESTAMP, 0),
+                block(SIMPLE_BLOCK, 2, 0, 0x80, 500),
+                block(SIMPLE_BLOCK, 1, 0, 0x80, 40),
+                group,
+            ]
+            .concat(),
+        ),
+    );
+    // A live-style cluster of unknown size, ended by the next cluster
+    let mut second = element(CLUSTER, None);
+    second.extend(uint(TIMESTAMP, 80));
+    second.extend(block(SIMPLE_BLOCK, 2, 0, 0, 90));
+    let third = element(
+        CLUSTER,
+        Some(&[uint(TIMESTAMP, 120), block(SIMPLE_BLOCK, 2, 0, 0x80, 400)].concat()),
+    );
+
+    let segment = element(
+        &[0x18, 0x53, 0x80, 0x67],
+        Some(&[info, tracks, first, second, third].concat()),
+    );
+    [header, segment].concat()
+}
+
+#[test]
+fn test_matroska_blocks() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_file(dir.path(), "clip.webm", &matroska());
+
+    assert!(MatroskaBackend.supports(&matroska()[..64]));
+    let data = MatroskaBackend.extract_bitrate_data(&path).unwrap();
+    assert_eq!(
+        summarize(&data),
+        vec![
+            (500, 0.0, true),
+            (120, 0.04, false),
+            (90, 0.08, false),
+            (400, 0.12, true)
+        ]
+    );
+}
+
+#[test]
+fn test_native_backend_used_without_ffprobe() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_file(dir.path(), "clip.mkv", &matroska());
+
+    let data = extract_bitrate_data(&unused_ffprobe(), &path).unwrap();
+    assert_eq!(data.frames.len(), 4);
+}
+
+#[test]
+fn test_malformed_container_falls_back_to_ffprobe() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut contents = ftyp();
+    contents.extend(mp4_box(b"moov", b"garbage that is not a box"));
+    let path = write_file(dir.path(), "broken.mp4", &contents);
+
+    assert!(Mp4Backend.extract_bitrate_data(&path).is_err());
+
+    // The fallback tries to spawn the (missing) ffprobe
+    let err = extract_bitrate_data(&unused_ffprobe(), &path).unwrap_err();
+    assert!(matches!(err, AnalysisError::Probe(_)));
+}
+
+fn assert_malformed(dir: &Path, name: &str, contents: &[u8]) {
+    let path = write_file(dir, name, contents);
+    let err = Mp4Backend.extract_bitrate_data(&path).unwrap_err();
+    assert!(
+        matches!(err, AnalysisError::Demux(DemuxError::Malformed(_))),
+        "{name}: {err:?}"
+    );
+}
+
+#[test]
+fn test_mp4_oversized_largesize() {
+    let dir = tempfile::tempdir().unwrap();
+    // A 64-bit size that would wrap the offset back to the start of the file
+    let mut contents = ftyp();
+    contents.extend(1u32.to_be_bytes());
+    contents.extend(b"mdat");
+    contents.extend((u64::MAX - contents.len() as u64 + 9).to_be_bytes());
+    contents.extend([0; 32]);
+    assert_malformed(dir.path(), "wrapping.mp4", &contents);
+
+    let mut contents = progressive_mp4();
+    contents.extend(1u32.to_be_bytes());
+    contents.extend(b"free");
+    contents.extend(1_000_000u64.to_be_bytes());
+    assert_malformed(dir.path(), "past_end.mp4", &contents);
+}
+
+#[test]
+fn test_mp4_corrupt_sample_tables() {
+    let dir = tempfile::tempdir().unwrap();
+    let mp4 = |stbl: &[u8]| {
+        [
+            ftyp(),
+            mp4_box(b"moov", &trak(1, b"vide", 1000, stbl)),
+            mp4_box(b"mdat", &[0; 64]),
+        ]
+        .concat()
+    };
+
+    // A constant sample size repeated far beyond the file
+    let stsz = full_box(b"stsz", 0, 0, &words(&[1000, u32::MAX]));
+    assert_malformed(dir.path(), "stsz.mp4", &mp4(&stsz));
+
+    // One-byte samples, as many as the file has bytes, still aren't all kept in memory
+    let count = (1 << 20) + 1;
+    let stsz = full_box(b"stsz", 0, 0, &words(&[1, count]));
+    let contents = [
+        ftyp(),
+        mp4_box(b"moov", &trak(1, b"vide", 1000, &stsz)),
+        mp4_box(b"mdat", &vec![0; count as usize]),
+    ]
+    .concat();
+    assert_malformed(dir.path(), "stsz-tiny.mp4", &contents);
+
+    // A composition offset run longer than the sample table is clamped to it
+    let stbl = [
+        full_box(b"stsz", 0, 0, &words(&[0, 2, 10, 20])),
+        full_box(b"stts", 0, 0, &words(&[1, 2, 40])),
+        full_box(b"ctts", 0, 0, &words(&[1, u32::MAX, 80])),
+    ]
+    .concat();
+    let path = write_file(dir.path(), "ctts.mp4", &mp4(&stbl));
+    let data = Mp4Backend.extract_bitrate_data(&path).unwrap();
+    assert_eq!(data.frames.len(), 2);
+    assert_eq!(data.frames[0].timestamp, Some(0.08));
+}
+
+#[test]
+fn test_mp4_corrupt_track_run() {
+    let dir = tempfile::tempdir().unwrap();
+    // Every sample takes the trex defaults, so the box doesn't bound the count
+    let tfhd = full_box(b"tfhd", 0, 0x020000, &words(&[1]));
+    let trun = full_box(b"trun", 0, 0, &words(&[u32::MAX]));
+    let traf = mp4_box(b"traf", &[tfhd, trun].concat());
+    let moof = mp4_box(
+        b"moof",
+        &[full_box(b"mfhd", 0, 0, &words(&[1])), traf].concat(),
+    );
+    let contents = [init_segment(1000, 40), moof, mp4_box(b"mdat", &[0; 64])].concat();
+    assert_malformed(dir.path(), "trun.mp4", &contents);
+}
+
+// --- Animations ---
+
+/// A 4x3 GIF with one frame per `(width, height, delay in centiseconds)`.
+fn gif(frames: &[(u16, u16, u16)]) -> Vec<u8> {
+    let mut gif = b"GIF89a\x04\x00\x03\x00\x81\x00\x00".to_vec();
+    gif.extend_from_slice(&[0; 12]);
+    for (width, height, delay) in frames {
+        gif.extend_from_slice(b"\x21\xF9\x04\x00");
+        gif.extend_from_slice(&delay.to_le_bytes());
+        gif.extend_from_slice(&[0, 0]);
+        gif.extend_from_slice(b"\x2C\0\0\0\0");
+        gif.extend_from_slice(&width.to_le_bytes());
+        gif.extend_from_slice(&height.to_le_bytes());
+        gif.extend_from_slice(&[0, 2, 2, 0x4C, 0x01, 0]);
+    }
+    gif.push(0x3B);
+    gif
+}
+
+#[test]
+fn test_animation_timeline() {
+    let dir = tempfile::tempdir().unwrap();
+    let contents = gif(&[(4, 3, 10), (2, 2, 5), (4, 3, 7)]);
+    let path = write_file(dir.path(), "spinner.gif", &contents);
+
+    assert!(AnimationBackend.supports(&contents));
+    assert!(!AnimationBackend.supports(&ftyp()));
+    let data = AnimationBackend.extract_bitrate_data(&path).unwrap();
+    assert_eq!(data.id, "spinner.gif");
+    assert_eq!(
+        summarize(&data),
+        vec![(15, 0.0, true), (15, 0.1, false), (15, 0.15, true)]
+    );
+
+    // Used ahead of ffprobe
+    let data = extract_bitrate_data(&unused_ffprobe(), &path).unwrap();
+    assert_eq!(data.frames.len(), 3);
+}
+
+#[test]
+fn test_still_image_falls_back_to_ffprobe() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_file(dir.path(), "still.gif", &gif(&[(4, 3, 0)]));
+
+    assert!(matches!(
+        AnimationBackend.extract_bitrate_data(&path),
+        Err(AnalysisError::NoFrames)
+    ));
+    let err = extract_bitrate_data(&unused_ffprobe(), &path).unwrap_err();
+    assert!(matches!(err, AnalysisError::Probe(_)));
+}
+
+// --- ffprobe output ---
+
+#[test]
+fn test_parse_compact_frames() {
+    assert_eq!(
+        parse_compact_line(
+            "key_frame=1|best_effort_timestamp_time=0.040000|pkt_size=1234",
+            FrameSizeSource::Frames
+        ),
+        Some(Sample {
+            size: 1234,
+            pts: Some(0.04),
+            keyframe: true
+        })
+    );
+    assert_eq!(
+        parse_compact_line(
+            "key_frame=0|best_effort_timestamp_time=N/A|pkt_size=99",
+            FrameSizeSource::Frames
+        ),
+        Some(Sample {
+            size: 99,
+            pts: None,
+            keyframe: false
+        })
+    );
+    assert_eq!(parse_compact_line("", FrameSizeSource::Frames), None);
+}
+
+#[test]
+fn test_parse_compact_packets() {
+    assert_eq!(
+        parse_compact_line("pts_time=1.5|size=42|flags=K__", FrameSizeSource::Packets),
+        Some(Sample {
+            size: 42,
+            pts: Some(1.5),
+            keyframe: true
+        })
+    );
+    assert_eq!(
+        parse_compact_line("pts_time=1.54|size=7|flags=___", FrameSizeSource::Packets)
+            .map(|sample| sample.keyframe),
+        Some(false)
+    );
+}
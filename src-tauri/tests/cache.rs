@@ -0,0 +1,110 @@
+use byte_me_lib::cache::{AnalysisCache, CacheKey};
+use byte_me_lib::models::{BitrateData, BitrateFrame};
+use std::cell::Cell;
+
+fn sample_data(frames: u32) -> BitrateData {
+    BitrateData {
+        id: "clip.mp4".to_string(),
+        frames: (0..frames)
+            .map(|frame_num| BitrateFrame {
+                frame_num,
+                packet_size: 1000 + u64::from(frame_num),
+                timestamp: Some(f64::from(frame_num) / 25.0),
+                keyframe: frame_num == 0,
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_key_tracks_file_identity() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("clip.mp4");
+    std::fs::write(&path, b"first").unwrap();
+
+    let key = CacheKey::for_file(&path, "6.1").unwrap();
+    assert_eq!(key, CacheKey::for_file(&path, "6.1").unwrap());
+    assert_ne!(key, CacheKey::for_file(&path, "7.0").unwrap());
+
+    std::fs::write(&path, b"second, longer").unwrap();
+    assert_ne!(key, CacheKey::for_file(&path, "6.1").unwrap());
+
+    assert!(CacheKey::for_file(&dir.path().join("missing.mp4"), "6.1").is_err());
+}
+
+#[test]
+fn test_get_or_compute() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("clip.mp4");
+    std::fs::write(&input, b"media").unwrap();
+    let cache = AnalysisCache::new(dir.path().join("cache"), u64::MAX);
+
+    let computed = Cell::new(0);
+    let compute = || {
+        computed.set(computed.get() + 1);
+        Ok::<_, String>(sample_data(3))
+    };
+
+    let first = cache
+        .get_or_compute(&input, "6.1", "bitrate", compute)
+        .unwrap();
+    let second = cache
+        .get_or_compute(&input, "6.1", "bitrate", compute)
+        .unwrap();
+    assert_eq!(computed.get(), 1);
+    assert_eq!(first.frames.len(), second.frames.len());
+    assert_eq!(second.frames[2].packet_size, 1002);
+
+    // A different kind or tool version is a separate entry
+    cache
+        .get_or_compute(&input, "7.0", "bitrate", compute)
+        .unwrap();
+    assert_eq!(computed.get(), 2);
+    assert_eq!(cache.stats().unwrap().entries, 2);
+
+    // Errors are never cached
+    let failed: Result<BitrateData, String> =
+        cache.get_or_compute(&input, "6.1", "streams", || Err("boom".to_string()));
+    assert!(failed.is_err());
+    assert_eq!(cache.stats().unwrap().entries, 2);
+}
+
+#[test]
+fn test_eviction_and_clear() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("clip.mp4");
+    std::fs::write(&input, b"media").unwrap();
+
+    let entry_size = serde_json::to_vec(&sample_data(50)).unwrap().len() as u64;
+    let cache = AnalysisCache::new(dir.path().join("cache"), entry_size * 2);
+    let key = CacheKey::for_file(&input, "6.1").unwrap();
+
+    for kind in ["a", "b", "c"] {
+        cache.put(&key, kind, &sample_data(50)).unwrap();
+        // Ensure distinct modification times for LRU ordering
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+
+    let stats = cache.stats().unwrap();
+    assert_eq!(stats.entries, 2);
+    assert!(stats.bytes <= entry_size * 2);
+    assert!(cache.get::<BitrateData>(&key, "a").is_none());
+    assert!(cache.get::<BitrateData>(&key, "c").is_some());
+
+    let removed = cache.clear().unwrap();
+    assert_eq!(removed, stats);
+    assert_eq!(cache.stats().unwrap().entries, 0);
+}
+
+#[test]
+fn test_corrupt_entry_is_discarded() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("clip.mp4");
+    std::fs::write(&input, b"media").unwrap();
+    let cache = AnalysisCache::new(dir.path().join("cache"), u64::MAX);
+    let key = CacheKey::for_file(&input, "6.1").unwrap();
+
+    cache.put(&key, "bitrate", &"not bitrate data").unwrap();
+    assert!(cache.get::<BitrateData>(&key, "bitrate").is_none());
+    assert_eq!(cache.stats().unwrap().entries, 0);
+}
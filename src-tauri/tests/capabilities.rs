@@ -0,0 +1,89 @@
+use byte_me_lib::capabilities::{
+    parse_buildconf, Capabilities, FfmpegVersion, FrameSizeSource, MINIMUM_VERSION,
+};
+
+#[test]
+fn test_parse_version() {
+    let release = |major, minor, patch| FfmpegVersion::Release {
+        major,
+        minor,
+        patch,
+    };
+
+    // Distribution and upstream release strings
+    assert_eq!(
+        FfmpegVersion::parse("6.1.1-3ubuntu5"),
+        Some(release(6, 1, 1))
+    );
+    assert_eq!(
+        FfmpegVersion::parse("4.4.2-0ubuntu0.22.04.1"),
+        Some(release(4, 4, 2))
+    );
+    assert_eq!(FfmpegVersion::parse("n7.0"), Some(release(7, 0, 0)));
+    assert_eq!(FfmpegVersion::parse("5.1.2-static"), Some(release(5, 1, 2)));
+    assert_eq!(
+        FfmpegVersion::parse("7.1-full_build-www.gyan.dev"),
+        Some(release(7, 1, 0))
+    );
+    assert_eq!(FfmpegVersion::parse("3"), Some(release(3, 0, 0)));
+
+    // Development snapshots
+    assert_eq!(
+        FfmpegVersion::parse("N-113000-g1234abcd"),
+        Some(FfmpegVersion::Development)
+    );
+    assert_eq!(
+        FfmpegVersion::parse("git-2023-01-01-abcdef"),
+        Some(FfmpegVersion::Development)
+    );
+    assert_eq!(
+        FfmpegVersion::parse("2024-03-04-git-e30369bc1c-full_build-www.gyan.dev"),
+        Some(FfmpegVersion::Development)
+    );
+
+    // Garbage
+    assert_eq!(FfmpegVersion::parse(""), None);
+    assert_eq!(FfmpegVersion::parse("unknown"), None);
+}
+
+#[test]
+fn test_version_ordering() {
+    assert!(FfmpegVersion::parse("4.0").unwrap() >= MINIMUM_VERSION);
+    assert!(FfmpegVersion::parse("3.4.8").unwrap() < MINIMUM_VERSION);
+    assert!(FfmpegVersion::parse("6.1.1").unwrap() > FfmpegVersion::parse("6.1").unwrap());
+    assert!(FfmpegVersion::Development > FfmpegVersion::parse("99.0").unwrap());
+
+    assert_eq!(FfmpegVersion::parse("6.1").unwrap().to_string(), "6.1");
+    assert_eq!(FfmpegVersion::parse("6.1.1").unwrap().to_string(), "6.1.1");
+}
+
+#[test]
+fn test_parse_buildconf() {
+    let output = "  configuration:\n    --prefix=/usr\n    --enable-gpl\n    --enable-libvmaf\n";
+    let flags = parse_buildconf(output);
+    assert_eq!(flags, ["--prefix=/usr", "--enable-gpl", "--enable-libvmaf"]);
+
+    let capabilities = Capabilities::new(FfmpegVersion::parse("6.0"), flags);
+    assert!(capabilities.has_configuration_flag("--enable-libvmaf"));
+    assert!(!capabilities.has_configuration_flag("--enable-libx265"));
+}
+
+#[test]
+fn test_argument_gating() {
+    let old = Capabilities::new(FfmpegVersion::parse("3.4.8"), Vec::new());
+    assert!(!old.is_supported());
+
+    let six = Capabilities::new(FfmpegVersion::parse("6.1.1"), Vec::new());
+    assert!(six.is_supported());
+    assert_eq!(six.frame_size_source(), FrameSizeSource::Frames);
+    assert_eq!(six.stream_probe_args(), ["-show_format", "-show_streams"]);
+
+    let seven = Capabilities::new(FfmpegVersion::parse("7.0.2"), Vec::new());
+    assert_eq!(seven.frame_size_source(), FrameSizeSource::Packets);
+    assert_eq!(seven.stream_probe_args()[0], "-show_entries");
+
+    // Unknown versions are assumed to be recent
+    let unknown = Capabilities::new(None, Vec::new());
+    assert!(unknown.is_supported());
+    assert_eq!(unknown.frame_size_source(), FrameSizeSource::Packets);
+}
@@ -0,0 +1,73 @@
+use byte_me_lib::analysis::analyze_file;
+use byte_me_lib::clipboard::{file_uri_to_path, parse_clipboard_text};
+use byte_me_lib::models::{FileCandidacy, MediaType};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn test_plain_paths() {
+    let text = "/videos/a.mp4\r\n\n   /videos/b.mkv  \n\"/videos/with space.mov\"\n/videos/a.mp4\n";
+    assert_eq!(
+        parse_clipboard_text(text),
+        ["/videos/a.mp4", "/videos/b.mkv", "/videos/with space.mov"]
+    );
+
+    assert!(parse_clipboard_text("").is_empty());
+    assert!(parse_clipboard_text("\n  \n\"\"\n").is_empty());
+}
+
+#[test]
+fn test_uri_list() {
+    // `text/uri-list`, as put on the clipboard by most Linux file managers
+    let text = "# copied from Files\r\nfile:///home/user/My%20Clip.mp4\r\nfile://localhost/home/user/b%C3%A9b%C3%A9.mkv\r\n";
+    assert_eq!(
+        parse_clipboard_text(text),
+        ["/home/user/My Clip.mp4", "/home/user/bébé.mkv"]
+    );
+}
+
+#[test]
+fn test_urls_are_kept() {
+    let text = "https://cdn.example.com/clip.mp4?token=a%20b\nFILE:///tmp/x.mp4";
+    assert_eq!(
+        parse_clipboard_text(text),
+        ["https://cdn.example.com/clip.mp4?token=a%20b", "/tmp/x.mp4"]
+    );
+}
+
+#[test]
+fn test_file_uri_to_path() {
+    assert_eq!(
+        file_uri_to_path("file:///tmp/a%23b.mp4"),
+        Some(PathBuf::from("/tmp/a#b.mp4"))
+    );
+    // Non-local hosts still yield their path rather than being dropped
+    assert_eq!(
+        file_uri_to_path("file://server/share/clip.mp4"),
+        Some(PathBuf::from("/share/clip.mp4"))
+    );
+    assert_eq!(file_uri_to_path("https://example.com/clip.mp4"), None);
+}
+
+#[test]
+fn test_pasted_uri_matches_dropped_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pasted image.png");
+    std::fs::write(&path, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+    let uri = url::Url::from_file_path(&path).unwrap();
+    let inputs = parse_clipboard_text(&format!("{uri}\n"));
+    assert_eq!(inputs.len(), 1);
+
+    let pasted = analyze_file(None, Path::new(&inputs[0]));
+    let dropped = analyze_file(None, &path);
+    assert_eq!(pasted.filename, "pasted image.png");
+    assert_eq!(pasted.filename, dropped.filename);
+    assert_eq!(pasted.size, dropped.size);
+    assert!(matches!(
+        pasted.candidacy,
+        FileCandidacy::Success {
+            file_type: MediaType::Image,
+            ..
+        }
+    ));
+}
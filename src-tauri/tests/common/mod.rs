@@ -0,0 +1,121 @@
+//! Builders for synthetic media shared by the integration tests.
+#![allow(dead_code)]
+
+use byte_me_lib::capabilities::Capabilities;
+use byte_me_lib::ff::{Ffmpeg, Ffprobe};
+use byte_me_lib::tools::{ResolvedTool, Tool, ToolSource};
+use std::path::Path;
+
+/// An ffmpeg handle for a shell script running `body`.
+#[cfg(unix)]
+pub fn fake_ffmpeg(dir: &Path, body: &str) -> Ffmpeg {
+    fake_ffmpeg_version(dir, "6.0", body)
+}
+
+/// Like [`fake_ffmpeg`], but reporting `version`.
+#[cfg(unix)]
+pub fn fake_ffmpeg_version(dir: &Path, version: &str, body: &str) -> Ffmpeg {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = dir.join("ffmpeg");
+    std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    Ffmpeg::new(ResolvedTool {
+        tool: Tool::Ffmpeg,
+        path,
+        version: version.to_string(),
+        source: ToolSource::Setting,
+    })
+}
+
+/// An ffprobe handle for a shell script running `body`.
+#[cfg(unix)]
+pub fn fake_ffprobe(dir: &Path, body: &str) -> Ffprobe {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = dir.join("ffprobe");
+    std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    Ffprobe::new(
+        ResolvedTool {
+            tool: Tool::Ffprobe,
+            path,
+            version: "6.0".to_string(),
+            source: ToolSource::Setting,
+        },
+        Capabilities::new(None, Vec::new()),
+    )
+}
+
+pub fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(payload);
+    out
+}
+
+pub fn full_box(kind: &[u8; 4], version: u8, flags: u32, body: &[u8]) -> Vec<u8> {
+    let mut payload = vec![version];
+    payload.extend_from_slice(&flags.to_be_bytes()[1..]);
+    payload.extend_from_slice(body);
+    mp4_box(kind, &payload)
+}
+
+pub fn words(values: &[u32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect()
+}
+
+pub fn trak(track_id: u32, handler: &[u8; 4], timescale: u32, stbl: &[u8]) -> Vec<u8> {
+    let tkhd = full_box(b"tkhd", 0, 3, &words(&[0, 0, track_id, 0, 0]));
+    let mdhd = full_box(b"mdhd", 0, 0, &words(&[0, 0, timescale, 0, 0]));
+    let mut hdlr_body = words(&[0]);
+    hdlr_body.extend_from_slice(handler);
+    hdlr_body.extend_from_slice(&[0; 13]);
+    let hdlr = full_box(b"hdlr", 0, 0, &hdlr_body);
+    let minf = mp4_box(b"minf", &mp4_box(b"stbl", stbl));
+    let mdia = mp4_box(b"mdia", &[mdhd, hdlr, minf].concat());
+    mp4_box(b"trak", &[tkhd, mdia].concat())
+}
+
+pub fn ftyp() -> Vec<u8> {
+    mp4_box(b"ftyp", b"isom\0\0\0\0isomavc1")
+}
+
+/// A `moof`/`mdat` pair for track 1; only the first sample is a sync sample when `first_sync`.
+pub fn fragment(sequence: u32, decode_time: u64, sizes: &[u32], first_sync: bool) -> Vec<u8> {
+    let mfhd = full_box(b"mfhd", 0, 0, &words(&[sequence]));
+    let tfhd = full_box(b"tfhd", 0, 0x020000, &words(&[1]));
+    let tfdt = full_box(b"tfdt", 1, 0, &decode_time.to_be_bytes());
+    // data offset, first sample flags and per-sample sizes
+    let first_flags = if first_sync { 0 } else { 0x0001_0000 };
+    let mut trun_body = words(&[sizes.len() as u32, 0, first_flags]);
+    trun_body.extend(words(sizes));
+    let trun = full_box(b"trun", 0, 0x000001 | 0x000004 | 0x000200, &trun_body);
+    let traf = mp4_box(b"traf", &[tfhd, tfdt, trun].concat());
+    let moof = mp4_box(b"moof", &[mfhd, traf].concat());
+    let total: u32 = sizes.iter().sum();
+    [moof, mp4_box(b"mdat", &vec![0; total as usize])].concat()
+}
+
+/// An `ftyp`/`moov` init segment for fragmented video on track 1.
+///
+/// Samples default to `sample_duration` ticks and non-sync flags.
+pub fn init_segment(timescale: u32, sample_duration: u32) -> Vec<u8> {
+    let video = trak(
+        1,
+        b"vide",
+        timescale,
+        &full_box(b"stsz", 0, 0, &words(&[0, 0])),
+    );
+    let trex = full_box(
+        b"trex",
+        0,
+        0,
+        &words(&[1, 1, sample_duration, 0, 0x0001_0000]),
+    );
+    let moov = mp4_box(b"moov", &[video, mp4_box(b"mvex", &trex)].concat());
+    [ftyp(), moov].concat()
+}
@@ -0,0 +1,303 @@
+use byte_me_lib::analysis::{
+    analyze_directory, analyze_file, detect_file, probe_file, AnalysisError,
+};
+use byte_me_lib::ff::FormatProbe;
+use byte_me_lib::media::{detect_from_bytes, is_media_file};
+use byte_me_lib::models::{Confidence, DetectionMethod, FileCandidacy, MediaType};
+use byte_me_lib::scan::ScanSettings;
+use std::path::Path;
+
+mod common;
+use common::*;
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+const ERROR_PAGE: &[u8] = b"<!DOCTYPE html>\n<html><body>403 Forbidden</body></html>";
+
+#[test]
+fn test_magic_agreeing_with_extension() {
+    let detection = detect_from_bytes(PNG, "frame.png");
+    assert_eq!(detection.media_type, MediaType::Image);
+    assert_eq!(detection.mime_type.as_deref(), Some("image/png"));
+    assert_eq!(detection.method, DetectionMethod::Magic);
+    assert_eq!(detection.confidence, Confidence::High);
+    assert_eq!(detection.mismatch, None);
+
+    // Nothing to contradict
+    let detection = detect_from_bytes(PNG, "frame");
+    assert_eq!(detection.confidence, Confidence::High);
+}
+
+#[test]
+fn test_magic_contradicting_extension() {
+    let detection = detect_from_bytes(PNG, "clip.mp4");
+    assert_eq!(detection.media_type, MediaType::Image);
+    assert_eq!(detection.method, DetectionMethod::Magic);
+    assert_eq!(detection.confidence, Confidence::Medium);
+    assert_eq!(
+        detection.mismatch.as_deref(),
+        Some("Content is image/png, but the .mp4 extension suggests Video")
+    );
+}
+
+#[test]
+fn test_error_page_saved_as_video() {
+    let detection = detect_from_bytes(ERROR_PAGE, "clip.mp4");
+    assert_eq!(detection.media_type, MediaType::Video);
+    assert_eq!(detection.mime_type.as_deref(), Some("text/html"));
+    assert_eq!(detection.method, DetectionMethod::Extension);
+    assert_eq!(detection.confidence, Confidence::Low);
+    assert!(detection.mismatch.unwrap().contains("text/html"));
+}
+
+#[test]
+fn test_extension_only() {
+    let detection = detect_from_bytes(&[0x5A; 64], "clip.MKV");
+    assert_eq!(detection.media_type, MediaType::Video);
+    assert_eq!(detection.mime_type.as_deref(), Some("video/x-matroska"));
+    assert_eq!(detection.method, DetectionMethod::Extension);
+    assert_eq!(detection.confidence, Confidence::Low);
+    assert_eq!(detection.mismatch, None);
+
+    let detection = detect_from_bytes(&[], "clip");
+    assert_eq!(detection.media_type, MediaType::Unknown);
+    assert_eq!(detection.mime_type, None);
+}
+
+#[test]
+fn test_candidacy_carries_detection() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("download.mp4");
+    std::fs::write(&path, ERROR_PAGE).unwrap();
+
+    let file = analyze_file(None, &path);
+    match file.candidacy {
+        FileCandidacy::Success {
+            file_type,
+            detection,
+        } => {
+            assert_eq!(file_type, MediaType::Video);
+            assert_eq!(detection.confidence, Confidence::Low);
+            assert!(detection.mismatch.is_some());
+        }
+        other => panic!("unexpected candidacy: {other:?}"),
+    }
+}
+
+fn format_probe(json: &str) -> FormatProbe {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn test_format_probe_classification() {
+    let raw_h264 =
+        format_probe(r#"{"streams":[{"codec_type":"video"}],"format":{"format_name":"h264"}}"#);
+    assert_eq!(raw_h264.media_type(), Some(MediaType::Video));
+
+    let y4m = format_probe(
+        r#"{"streams":[{"codec_type":"video"}],"format":{"format_name":"yuv4mpegpipe"}}"#,
+    );
+    assert_eq!(y4m.media_type(), Some(MediaType::Video));
+
+    let still =
+        format_probe(r#"{"streams":[{"codec_type":"video"}],"format":{"format_name":"png_pipe"}}"#);
+    assert_eq!(still.media_type(), Some(MediaType::Image));
+
+    let opus = format_probe(
+        r#"{"streams":[{"codec_type":"audio","codec_name":"opus"}],"format":{"format_name":"ogg"}}"#,
+    );
+    assert_eq!(opus.media_type(), Some(MediaType::Audio));
+
+    let data = format_probe(r#"{"streams":[{"codec_type":"data"}]}"#);
+    assert_eq!(data.media_type(), None);
+    assert_eq!(format_probe("{}").media_type(), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ffprobe_fallback() {
+    let dir = tempfile::tempdir().unwrap();
+    let clip = dir.path().join("clip.h264");
+    std::fs::write(&clip, [0, 0, 0, 1, 0x67, 0x64, 0, 0x1f]).unwrap();
+
+    // Neither magic numbers nor the extension know raw H.264
+    assert!(matches!(
+        detect_file(None, &clip),
+        Err(AnalysisError::NotMedia(MediaType::Unknown))
+    ));
+
+    let ffprobe = fake_ffprobe(
+        dir.path(),
+        r#"echo '{"streams":[{"index":0,"codec_type":"video","codec_name":"h264"}],"format":{"format_name":"h264"}}'"#,
+    );
+    let detection = detect_file(Some(&ffprobe), &clip).unwrap();
+    assert_eq!(detection.media_type, MediaType::Video);
+    assert_eq!(detection.method, DetectionMethod::Ffprobe);
+    assert_eq!(detection.confidence, Confidence::Medium);
+
+    // Recognized files never reach ffprobe
+    let failing = fake_ffprobe(dir.path(), "exit 1");
+    let png = dir.path().join("frame.png");
+    std::fs::write(&png, PNG).unwrap();
+    let detection = detect_file(Some(&failing), &png).unwrap();
+    assert_eq!(detection.method, DetectionMethod::Magic);
+
+    // Nor do files that can't be demuxed
+    assert!(matches!(
+        detect_file(Some(&failing), &clip),
+        Err(AnalysisError::NotMedia(MediaType::Unknown))
+    ));
+}
+
+#[test]
+fn test_subtitle_and_playlist_content() {
+    let cases: &[(&[u8], &str, MediaType, &str)] = &[
+        (
+            b"1\r\n00:00:01,000 --> 00:00:04,000\r\nHello\r\n",
+            "episode.srt",
+            MediaType::Subtitle,
+            "application/x-subrip",
+        ),
+        (
+            b"\xEF\xBB\xBFWEBVTT\n\n00:01.000 --> 00:04.000\nHello\n",
+            "episode.vtt",
+            MediaType::Subtitle,
+            "text/vtt",
+        ),
+        (
+            b"[Script Info]\nScriptType: v4.00+\n",
+            "episode.ass",
+            MediaType::Subtitle,
+            "text/x-ssa",
+        ),
+        (
+            b"PG\0\0\x01\x5f\0\0\0\0\x16\0\x13",
+            "episode.sup",
+            MediaType::Subtitle,
+            "application/x-pgs",
+        ),
+        (
+            b"#EXTM3U\n#EXT-X-VERSION:3\n",
+            "master.m3u8",
+            MediaType::Playlist,
+            "application/vnd.apple.mpegurl",
+        ),
+        (
+            b"<?xml version=\"1.0\"?>\n<MPD xmlns=\"urn:mpeg:dash:schema:mpd:2011\">",
+            "stream.mpd",
+            MediaType::Playlist,
+            "application/dash+xml",
+        ),
+    ];
+
+    for (content, name, media_type, mime_type) in cases {
+        let detection = detect_from_bytes(content, name);
+        assert_eq!(detection.media_type, *media_type, "{name}");
+        assert_eq!(detection.mime_type.as_deref(), Some(*mime_type), "{name}");
+        assert_eq!(detection.method, DetectionMethod::Magic, "{name}");
+        assert_eq!(detection.mismatch, None, "{name}");
+    }
+
+    // Recognized even when misnamed
+    let detection = detect_from_bytes(b"#EXTM3U\n", "index.txt");
+    assert_eq!(detection.media_type, MediaType::Playlist);
+    assert!(detection.mismatch.is_some());
+
+    // Nothing ffprobe can open, so neither CUE sheets nor plain lists count
+    let detection = detect_from_bytes(
+        b"REM GENRE Ambient\nFILE \"album.flac\" WAVE\n",
+        "album.cue",
+    );
+    assert_eq!(detection.media_type, MediaType::Unknown);
+    let detection = detect_from_bytes(b"/music/one.flac\n/music/two.flac\n", "mix.m3u");
+    assert_eq!(detection.media_type, MediaType::Unknown);
+    let detection = detect_from_bytes(b"#EXTM3U\n#EXTINF:-1,One\none.flac\n", "mix.m3u");
+    assert_eq!(detection.media_type, MediaType::Playlist);
+}
+
+#[test]
+fn test_subtitles_and_playlists_are_candidates() {
+    let dir = tempfile::tempdir().unwrap();
+    for (name, content, media_type) in [
+        (
+            "episode.srt",
+            &b"1\n00:00:01,000 --> 00:00:02,000\nHi\n"[..],
+            MediaType::Subtitle,
+        ),
+        (
+            "master.m3u8",
+            b"#EXTM3U\n#EXT-X-VERSION:3\n",
+            MediaType::Playlist,
+        ),
+    ] {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        assert!(is_media_file(&media_type));
+        assert!(matches!(
+            analyze_file(None, &path).candidacy,
+            FileCandidacy::Success { file_type, .. } if file_type == media_type
+        ));
+    }
+}
+
+/// Full `ffprobe -show_streams -show_format` output for a one-stream MP4.
+#[cfg(unix)]
+const CLIP_PROBE: &str = r#"{
+  "streams": [{
+    "index": 0, "codec_name": "h264", "codec_type": "video",
+    "codec_tag_string": "avc1", "codec_tag": "0x31637661",
+    "r_frame_rate": "25/1", "avg_frame_rate": "25/1", "time_base": "1/12800",
+    "width": 320, "height": 240,
+    "disposition": {
+      "default": 1, "dub": 0, "original": 0, "comment": 0, "lyrics": 0, "karaoke": 0,
+      "forced": 0, "hearing_impaired": 0, "visual_impaired": 0, "clean_effects": 0,
+      "attached_pic": 0, "timed_thumbnails": 0
+    }
+  }],
+  "format": {
+    "filename": "clip.mp4", "nb_streams": 1, "nb_programs": 0,
+    "format_name": "mov,mp4,m4a,3gp,3g2,mj2", "format_long_name": "QuickTime / MOV",
+    "duration": "2.000000", "probe_score": 100
+  }
+}"#;
+
+#[cfg(unix)]
+#[test]
+fn test_unprobeable_lists_stay_out_of_batches() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("clip.mp4"), b"\0\0\0\x18ftypisom").unwrap();
+    std::fs::write(dir.path().join("album.cue"), b"FILE \"album.flac\" WAVE\n").unwrap();
+    std::fs::write(dir.path().join("mix.m3u"), b"one.flac\ntwo.flac\n").unwrap();
+    let tools = tempfile::tempdir().unwrap();
+    let probe = tools.path().join("probe.json");
+    std::fs::write(&probe, CLIP_PROBE).unwrap();
+    // Fails on anything but the MP4, as the real ffprobe would
+    let ffprobe = fake_ffprobe(
+        tools.path(),
+        &format!(
+            "case \"$*\" in\n  *clip.mp4*) cat '{}' ;;\n  *) exit 1 ;;\nesac",
+            probe.display()
+        ),
+    );
+
+    // A dropped directory and the same files dropped one by one
+    let mut candidates = analyze_directory(dir.path(), &ScanSettings::default());
+    candidates.extend(
+        ["clip.mp4", "album.cue", "mix.m3u"]
+            .map(|name| analyze_file(Some(&ffprobe), &dir.path().join(name))),
+    );
+    let paths: Vec<_> = candidates
+        .iter()
+        .filter(|file| matches!(file.candidacy, FileCandidacy::Success { .. }))
+        .map(|file| file.path.clone())
+        .collect();
+    assert_eq!(paths.len(), 2);
+
+    // Probed as a batch, the way `has_streams` does
+    let streams = paths
+        .iter()
+        .map(|path| probe_file(&ffprobe, Path::new(path)))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(streams.iter().all(|result| result.filename == "clip.mp4"));
+    assert_eq!(streams[0].streams.len(), 1);
+}
@@ -0,0 +1,133 @@
+use byte_me_lib::analysis::AnalysisError;
+use byte_me_lib::efficiency::{compute, parse_frame_rate};
+use byte_me_lib::models::{BitrateData, BitrateFrame, MediaType, StreamDetail, StreamResult};
+
+fn streams(
+    width: u32,
+    height: u32,
+    frame_rate: Option<&str>,
+    duration: Option<f64>,
+) -> StreamResult {
+    StreamResult {
+        path: "/media/clip.mp4".to_string(),
+        filename: "clip.mp4".to_string(),
+        media_type: MediaType::Video,
+        duration,
+        size: 0,
+        streams: vec![
+            StreamDetail::Audio {
+                codec: "aac".to_string(),
+                sample_rate: Some("48000".to_string()),
+                channels: Some(2),
+                bit_rate: None,
+            },
+            StreamDetail::Video {
+                codec: "h264".to_string(),
+                width: Some(width),
+                height: Some(height),
+                bit_rate: None,
+                frame_rate: frame_rate.map(str::to_string),
+            },
+        ],
+    }
+}
+
+/// A keyframe followed by smaller frames, 40 ms apart.
+fn bitrate(sizes: &[u64]) -> BitrateData {
+    BitrateData {
+        id: "clip.mp4".to_string(),
+        frames: sizes
+            .iter()
+            .enumerate()
+            .map(|(index, &packet_size)| BitrateFrame {
+                frame_num: index as u32,
+                packet_size,
+                timestamp: Some(index as f64 * 0.04),
+                keyframe: index == 0,
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_parse_frame_rate() {
+    assert_eq!(parse_frame_rate("25/1"), Some(25.0));
+    assert_eq!(parse_frame_rate("24"), Some(24.0));
+    assert!((parse_frame_rate("30000/1001").unwrap() - 29.97).abs() < 0.001);
+    assert_eq!(parse_frame_rate("0/0"), None);
+    assert_eq!(parse_frame_rate("n/a"), None);
+}
+
+#[test]
+fn test_bits_per_pixel() {
+    // 1000x1000 at 25 fps: 250 000 bytes is 2 bits per pixel
+    let analysis = compute(
+        &streams(1000, 1000, Some("25/1"), None),
+        &bitrate(&[250_000, 50_000, 50_000, 50_000]),
+    )
+    .unwrap();
+    assert_eq!(analysis.width, 1000);
+    assert_eq!(analysis.frame_rate, Some(25.0));
+
+    let bpp: Vec<_> = analysis
+        .frames
+        .iter()
+        .map(|frame| frame.bits_per_pixel)
+        .collect();
+    assert_eq!(bpp, [2.0, 0.4, 0.4, 0.4]);
+    assert_eq!(
+        analysis.frames[1].bits_per_second_per_megapixel,
+        Some(10_000_000.0)
+    );
+    assert_eq!(analysis.bits_per_pixel, 0.8);
+    assert_eq!(analysis.max_bits_per_pixel, 2.0);
+    assert_eq!(analysis.keyframe_bits_per_pixel, Some(2.0));
+    assert_eq!(analysis.bitrate, Some(20_000_000.0));
+    assert_eq!(analysis.bits_per_second_per_megapixel, Some(20_000_000.0));
+}
+
+#[test]
+fn test_frame_rate_fallbacks() {
+    let sizes = [1000, 1000, 1000, 1000, 1000];
+
+    // Timestamps 40 ms apart
+    let analysis = compute(
+        &streams(640, 360, Some("0/0"), Some(10.0)),
+        &bitrate(&sizes),
+    )
+    .unwrap();
+    assert!((analysis.frame_rate.unwrap() - 25.0).abs() < 1e-9);
+
+    // Then the container duration
+    let mut untimed = bitrate(&sizes);
+    for frame in &mut untimed.frames {
+        frame.timestamp = None;
+    }
+    let analysis = compute(&streams(640, 360, None, Some(0.5)), &untimed).unwrap();
+    assert_eq!(analysis.frame_rate, Some(10.0));
+
+    // Without any, only the per-pixel metrics are available
+    let analysis = compute(&streams(640, 360, None, None), &untimed).unwrap();
+    assert_eq!(analysis.frame_rate, None);
+    assert_eq!(analysis.bitrate, None);
+    assert_eq!(analysis.frames[0].bits_per_second_per_megapixel, None);
+    assert!(analysis.bits_per_pixel > 0.0);
+}
+
+#[test]
+fn test_requires_video_dimensions() {
+    let mut audio_only = streams(0, 0, None, None);
+    audio_only.streams.truncate(1);
+    assert!(matches!(
+        compute(&audio_only, &bitrate(&[100])),
+        Err(AnalysisError::NoVideoStream)
+    ));
+    assert!(matches!(
+        compute(&streams(0, 0, Some("25/1"), None), &bitrate(&[100])),
+        Err(AnalysisError::NoVideoStream)
+    ));
+    assert!(matches!(
+        compute(&streams(1920, 1080, Some("25/1"), None), &bitrate(&[])),
+        Err(AnalysisError::NoFrames)
+    ));
+}
@@ -0,0 +1,319 @@
+use byte_me_lib::analysis::{analyze_image, AnalysisError};
+use byte_me_lib::image::{analyze_bytes, ImageError};
+use byte_me_lib::models::{ColorType, ImageFormat};
+
+/// Minimal little-endian TIFF structure with Make, Model, Orientation and DateTime.
+fn tiff(make: &str, model: &str, orientation: u16, date_time: &str) -> Vec<u8> {
+    let entries = 4;
+    let mut data_offset = 8 + 2 + entries * 12 + 4;
+    let mut ifd = Vec::new();
+    let mut data = Vec::new();
+
+    let mut push_ascii = |ifd: &mut Vec<u8>, tag: u16, text: &str| {
+        let bytes = [text.as_bytes(), b"\0"].concat();
+        ifd.extend_from_slice(&tag.to_le_bytes());
+        ifd.extend_from_slice(&2u16.to_le_bytes());
+        ifd.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        ifd.extend_from_slice(&(data_offset as u32).to_le_bytes());
+        data_offset += bytes.len();
+        data.extend_from_slice(&bytes);
+    };
+    push_ascii(&mut ifd, 0x010F, make);
+    push_ascii(&mut ifd, 0x0110, model);
+    // Orientation, SHORT, stored inline
+    ifd.extend_from_slice(&0x0112u16.to_le_bytes());
+    ifd.extend_from_slice(&3u16.to_le_bytes());
+    ifd.extend_from_slice(&1u32.to_le_bytes());
+    ifd.extend_from_slice(&u32::from(orientation).to_le_bytes());
+    push_ascii(&mut ifd, 0x0132, date_time);
+
+    let mut out = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+    out.extend_from_slice(&(entries as u16).to_le_bytes());
+    out.extend_from_slice(&ifd);
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&data);
+    out
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], body: &[u8]) {
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(body);
+    out.extend_from_slice(&[0; 4]); // CRC, not checked
+}
+
+fn ihdr(width: u32, height: u32, bit_depth: u8, color_type: u8) -> Vec<u8> {
+    [
+        &width.to_be_bytes()[..],
+        &height.to_be_bytes(),
+        &[bit_depth, color_type, 0, 0, 0],
+    ]
+    .concat()
+}
+
+fn fctl(sequence: u32, width: u32, height: u32, num: u16, den: u16) -> Vec<u8> {
+    [
+        &sequence.to_be_bytes()[..],
+        &width.to_be_bytes(),
+        &height.to_be_bytes(),
+        &[0; 8],
+        &num.to_be_bytes(),
+        &den.to_be_bytes(),
+        &[0, 0],
+    ]
+    .concat()
+}
+
+#[test]
+fn test_png_still() {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png_chunk(&mut png, b"IHDR", &ihdr(640, 480, 16, 6));
+    png_chunk(&mut png, b"iCCP", b"sRGB\0\0compressed");
+    png_chunk(
+        &mut png,
+        b"eXIf",
+        &tiff("Canon", "EOS R5", 6, "2024:05:01 12:00:00"),
+    );
+    png_chunk(&mut png, b"IDAT", &[0; 32]);
+    png_chunk(&mut png, b"IEND", &[]);
+
+    let analysis = analyze_bytes("still.png".to_string(), &png).unwrap();
+    assert_eq!(analysis.format, ImageFormat::Png);
+    assert_eq!((analysis.width, analysis.height), (640, 480));
+    assert_eq!(analysis.color_type, ColorType::Rgba);
+    assert_eq!(analysis.bit_depth, 16);
+    assert!(analysis.has_icc_profile);
+    assert!(analysis.animation.is_none());
+
+    let exif = analysis.exif.unwrap();
+    assert_eq!(exif.orientation, Some(6));
+    assert_eq!(exif.camera_make.as_deref(), Some("Canon"));
+    assert_eq!(exif.camera_model.as_deref(), Some("EOS R5"));
+    assert_eq!(exif.date_time.as_deref(), Some("2024:05:01 12:00:00"));
+}
+
+#[test]
+fn test_apng_frames() {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png_chunk(&mut png, b"IHDR", &ihdr(32, 16, 8, 3));
+    png_chunk(&mut png, b"acTL", &[0, 0, 0, 2, 0, 0, 0, 0]);
+    png_chunk(&mut png, b"fcTL", &fctl(0, 32, 16, 1, 10));
+    png_chunk(&mut png, b"IDAT", &[0; 100]);
+    png_chunk(&mut png, b"IDAT", &[0; 20]);
+    // Zero denominator means hundredths of a second
+    png_chunk(&mut png, b"fcTL", &fctl(1, 8, 8, 5, 0));
+    png_chunk(&mut png, b"fdAT", &[0; 60]);
+    png_chunk(&mut png, b"IEND", &[]);
+
+    let analysis = analyze_bytes("spinner.png".to_string(), &png).unwrap();
+    assert_eq!(analysis.color_type, ColorType::Indexed);
+    assert!(!analysis.has_icc_profile);
+    assert!(analysis.exif.is_none());
+
+    let animation = analysis.animation.unwrap();
+    assert_eq!(animation.frame_count, 2);
+    assert_eq!(animation.plays, 0);
+    assert_eq!(animation.duration_ms, 150);
+    let frames: Vec<_> = animation
+        .frames
+        .iter()
+        .map(|frame| (frame.width, frame.height, frame.delay_ms, frame.size))
+        .collect();
+    assert_eq!(frames, [(32, 16, 100, 120), (8, 8, 50, 60)]);
+}
+
+fn gif(netscape_loops: Option<u16>, delays: &[u16]) -> Vec<u8> {
+    // 4x3 canvas with a four-entry global color table
+    let mut gif = b"GIF89a\x04\x00\x03\x00\x81\x00\x00".to_vec();
+    gif.extend_from_slice(&[0; 12]);
+    if let Some(loops) = netscape_loops {
+        gif.extend_from_slice(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01");
+        gif.extend_from_slice(&loops.to_le_bytes());
+        gif.push(0);
+    }
+    for delay in delays {
+        gif.extend_from_slice(b"\x21\xF9\x04\x00");
+        gif.extend_from_slice(&delay.to_le_bytes());
+        gif.extend_from_slice(&[0, 0]);
+        // Image descriptor, LZW code size, one data sub-block and its terminator
+        gif.extend_from_slice(b"\x2C\0\0\0\0\x04\x00\x03\x00\x00");
+        gif.extend_from_slice(&[2, 2, 0x4C, 0x01, 0]);
+    }
+    gif.push(0x3B);
+    gif
+}
+
+#[test]
+fn test_gif_animation() {
+    let analysis = analyze_bytes("loop.gif".to_string(), &gif(Some(0), &[10, 5, 7])).unwrap();
+    assert_eq!(analysis.format, ImageFormat::Gif);
+    assert_eq!((analysis.width, analysis.height), (4, 3));
+    assert_eq!(analysis.color_type, ColorType::Indexed);
+    assert_eq!(analysis.bit_depth, 2);
+
+    let animation = analysis.animation.unwrap();
+    assert_eq!(animation.frame_count, 3);
+    assert_eq!(animation.plays, 0);
+    assert_eq!(animation.duration_ms, 220);
+    assert_eq!(animation.frames[1].delay_ms, 50);
+    assert_eq!(animation.frames[0].size, 15);
+
+    // The stored count is repeats after the first play
+    let twice = analyze_bytes("twice.gif".to_string(), &gif(Some(1), &[10, 10])).unwrap();
+    assert_eq!(twice.animation.unwrap().plays, 2);
+    let once = analyze_bytes("once.gif".to_string(), &gif(None, &[10, 10])).unwrap();
+    assert_eq!(once.animation.unwrap().plays, 1);
+
+    let still = analyze_bytes("still.gif".to_string(), &gif(None, &[0])).unwrap();
+    assert!(still.animation.is_none());
+
+    // Missing trailer
+    let mut cut = gif(None, &[10, 10]);
+    cut.pop();
+    assert!(analyze_bytes("cut.gif".to_string(), &cut).is_ok());
+}
+
+fn riff(chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let mut body = b"WEBP".to_vec();
+    for (kind, data) in chunks {
+        body.extend_from_slice(*kind);
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            body.push(0);
+        }
+    }
+    [&b"RIFF"[..], &(body.len() as u32).to_le_bytes(), &body].concat()
+}
+
+fn u24(value: u32) -> [u8; 3] {
+    let [a, b, c, _] = value.to_le_bytes();
+    [a, b, c]
+}
+
+fn anmf(width: u32, height: u32, duration: u32, payload: usize) -> Vec<u8> {
+    [
+        &[0; 6][..],
+        &u24(width - 1),
+        &u24(height - 1),
+        &u24(duration),
+        &[0],
+        &vec![0; payload],
+    ]
+    .concat()
+}
+
+#[test]
+fn test_webp_animation() {
+    let vp8x = [&[0x32, 0, 0, 0][..], &u24(399), &u24(299)].concat();
+    let webp = riff(&[
+        (b"VP8X", vp8x),
+        (b"ICCP", vec![0; 12]),
+        (b"ANIM", vec![0, 0, 0, 0, 3, 0]),
+        (b"ANMF", anmf(400, 300, 80, 9)),
+        (b"ANMF", anmf(100, 50, 120, 4)),
+        (
+            b"EXIF",
+            [
+                &b"Exif\0\0"[..],
+                &tiff("Google", "Pixel 8", 1, "2024:01:02 03:04:05"),
+            ]
+            .concat(),
+        ),
+    ]);
+
+    let analysis = analyze_bytes("sticker.webp".to_string(), &webp).unwrap();
+    assert_eq!(analysis.format, ImageFormat::WebP);
+    assert_eq!((analysis.width, analysis.height), (400, 300));
+    assert_eq!(analysis.color_type, ColorType::Rgba);
+    assert!(analysis.has_icc_profile);
+    assert_eq!(
+        analysis.exif.unwrap().camera_model.as_deref(),
+        Some("Pixel 8")
+    );
+
+    let animation = analysis.animation.unwrap();
+    assert_eq!(animation.plays, 3);
+    assert_eq!(animation.duration_ms, 200);
+    let frames: Vec<_> = animation
+        .frames
+        .iter()
+        .map(|frame| (frame.width, frame.height, frame.delay_ms, frame.size))
+        .collect();
+    // Padding after the odd-sized first frame is skipped
+    assert_eq!(frames, [(400, 300, 80, 25), (100, 50, 120, 20)]);
+
+    let bits: u32 = 63 | (31 << 14) | (1 << 28);
+    let lossless = riff(&[(b"VP8L", [&[0x2F][..], &bits.to_le_bytes()].concat())]);
+    let analysis = analyze_bytes("icon.webp".to_string(), &lossless).unwrap();
+    assert_eq!((analysis.width, analysis.height), (64, 32));
+    assert_eq!(analysis.color_type, ColorType::Rgba);
+    assert!(analysis.animation.is_none());
+}
+
+fn jpeg_segment(out: &mut Vec<u8>, marker: u8, body: &[u8]) {
+    out.extend_from_slice(&[0xFF, marker]);
+    out.extend_from_slice(&(body.len() as u16 + 2).to_be_bytes());
+    out.extend_from_slice(body);
+}
+
+#[test]
+fn test_jpeg_metadata() {
+    let mut jpeg = vec![0xFF, 0xD8];
+    jpeg_segment(&mut jpeg, 0xE0, b"JFIF\0\x01\x02\0\0\x01\0\x01\0\0");
+    let exif = tiff("NIKON CORPORATION", "NIKON Z 6", 8, "2023:12:24 18:30:00");
+    jpeg_segment(&mut jpeg, 0xE1, &[&b"Exif\0\0"[..], &exif].concat());
+    jpeg_segment(&mut jpeg, 0xE2, b"ICC_PROFILE\0\x01\x01profile");
+    jpeg_segment(
+        &mut jpeg,
+        0xC2,
+        &[
+            8, 0x0F, 0xA0, 0x17, 0x70, 3, 1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1,
+        ],
+    );
+    jpeg_segment(&mut jpeg, 0xDA, &[1, 1, 0, 0, 0x3F, 0]);
+    jpeg.extend_from_slice(&[0x12, 0x34, 0xFF, 0xD9]);
+
+    let analysis = analyze_bytes("photo.jpg".to_string(), &jpeg).unwrap();
+    assert_eq!(analysis.format, ImageFormat::Jpeg);
+    assert_eq!((analysis.width, analysis.height), (6000, 4000));
+    assert_eq!(analysis.color_type, ColorType::YCbCr);
+    assert_eq!(analysis.bit_depth, 8);
+    assert!(analysis.has_icc_profile);
+
+    let exif = analysis.exif.unwrap();
+    assert_eq!(exif.orientation, Some(8));
+    assert_eq!(exif.camera_make.as_deref(), Some("NIKON CORPORATION"));
+    assert_eq!(exif.date_time.as_deref(), Some("2023:12:24 18:30:00"));
+}
+
+#[test]
+fn test_image_errors() {
+    assert!(matches!(
+        analyze_bytes("notes.txt".to_string(), b"plain text"),
+        Err(ImageError::Unsupported)
+    ));
+    assert!(matches!(
+        analyze_bytes(
+            "cut.png".to_string(),
+            b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0"
+        ),
+        Err(ImageError::Malformed(_))
+    ));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("broken.jpg");
+    std::fs::write(&path, [0xFF, 0xD8, 0xFF, 0xD9]).unwrap();
+    let err = analyze_image(&path).unwrap_err();
+    assert_eq!(err.kind(), "invalid_image");
+
+    let path = dir.path().join("notes.png");
+    std::fs::write(&path, b"plain text").unwrap();
+    let err = analyze_image(&path).unwrap_err();
+    assert!(matches!(err, AnalysisError::Image(ImageError::Unsupported)));
+    assert_eq!(err.kind(), "unsupported_format");
+    assert!(matches!(
+        analyze_image(&dir.path().join("missing.png")),
+        Err(AnalysisError::NotFound)
+    ));
+}
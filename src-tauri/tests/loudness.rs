@@ -0,0 +1,140 @@
+use byte_me_lib::analysis::{analyze_loudness, AnalysisError};
+use byte_me_lib::loudness::parse_metadata;
+use byte_me_lib::tools::{Tool, ToolError};
+
+mod common;
+use common::*;
+
+const METADATA: &str = "\
+frame:0    pts:0       pts_time:0
+lavfi.r128.M=-120.691
+lavfi.r128.S=-120.691
+lavfi.r128.I=-70.000
+lavfi.r128.LRA=0.000
+lavfi.r128.LRA.low=0.000
+lavfi.r128.LRA.high=0.000
+lavfi.r128.true_peaks_ch0=0.000
+lavfi.r128.true_peak=0.000
+frame:1    pts:4800    pts_time:0.1
+lavfi.r128.M=-inf
+lavfi.r128.S=-24.500
+lavfi.r128.I=-70.000
+lavfi.r128.LRA=0.000
+lavfi.r128.true_peak=0.250
+frame:2    pts:9600    pts_time:0.2
+lavfi.r128.M=-22.104
+lavfi.r128.S=-23.870
+lavfi.r128.I=-23.012
+lavfi.r128.LRA=6.400
+lavfi.r128.true_peaks_ch0=0.501
+lavfi.r128.true_peaks_ch1=0.398
+lavfi.r128.true_peak=0.501
+";
+
+#[test]
+fn test_parse_metadata() {
+    let analysis = parse_metadata("mix.wav".to_string(), 1, METADATA);
+    assert_eq!(analysis.id, "mix.wav");
+    assert_eq!(analysis.audio_stream, 1);
+    assert_eq!(analysis.integrated, Some(-23.012));
+    assert_eq!(analysis.loudness_range, Some(6.4));
+    // 0.501 linear is about -6 dBTP
+    let true_peak = analysis.true_peak.unwrap();
+    assert!((true_peak + 6.003).abs() < 0.01, "{true_peak}");
+
+    let points: Vec<_> = analysis
+        .points
+        .iter()
+        .map(|point| (point.time, point.momentary, point.short_term))
+        .collect();
+    assert_eq!(
+        points,
+        [
+            (0.0, Some(-120.691), Some(-120.691)),
+            (0.1, None, Some(-24.5)),
+            (0.2, Some(-22.104), Some(-23.87)),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_silence() {
+    let silence = METADATA.split("frame:1").next().unwrap();
+    let analysis = parse_metadata("silence.wav".to_string(), 0, silence);
+    // Everything was gated out and nothing peaked
+    assert_eq!(analysis.integrated, None);
+    assert_eq!(analysis.true_peak, None);
+    assert_eq!(analysis.points.len(), 1);
+
+    assert!(parse_metadata("empty.wav".to_string(), 0, "")
+        .points
+        .is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_analyze_with_ffmpeg() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("mix.wav");
+    std::fs::write(&input, b"RIFF").unwrap();
+    let output = dir.path().join("metadata.txt");
+    std::fs::write(&output, METADATA).unwrap();
+    let args = dir.path().join("args.txt");
+
+    let ffmpeg = fake_ffmpeg(
+        dir.path(),
+        &format!(
+            "echo \"$@\" > '{}'\ncat '{}'",
+            args.display(),
+            output.display()
+        ),
+    );
+    let analysis = analyze_loudness(&ffmpeg, &input, 1).unwrap();
+    assert_eq!(analysis.points.len(), 3);
+    assert_eq!(analysis.integrated, Some(-23.012));
+
+    let args = std::fs::read_to_string(&args).unwrap();
+    assert!(args.contains("-map 0:a:1"), "{args}");
+    assert!(args.contains("ebur128=metadata=1:peak=true"), "{args}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ffmpeg_failures() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("video_only.mp4");
+    std::fs::write(&input, b"\0\0\0\x18ftyp").unwrap();
+
+    let failing = fake_ffmpeg(
+        dir.path(),
+        "echo \"Stream map '0:a:0' matches no streams.\" >&2\nexit 1",
+    );
+    let err = analyze_loudness(&failing, &input, 0).unwrap_err();
+    assert!(matches!(err, AnalysisError::Decode(_)));
+    assert_eq!(err.kind(), "analysis_failed");
+    assert!(err.to_string().contains("matches no streams"));
+
+    let silent = fake_ffmpeg(dir.path(), "exit 0");
+    assert!(matches!(
+        analyze_loudness(&silent, &input, 0),
+        Err(AnalysisError::NoFrames)
+    ));
+    assert!(matches!(
+        analyze_loudness(&silent, &dir.path().join("missing.wav"), 0),
+        Err(AnalysisError::NotFound)
+    ));
+}
+
+#[test]
+fn test_missing_ffmpeg_kind() {
+    let err = AnalysisError::from(ToolError::NotFound {
+        tool: Tool::Ffmpeg,
+        tried: Vec::new(),
+    });
+    assert_eq!(err.kind(), "ffmpeg_unavailable");
+    let err = AnalysisError::from(ToolError::NotFound {
+        tool: Tool::Ffprobe,
+        tried: Vec::new(),
+    });
+    assert_eq!(err.kind(), "ffprobe_unavailable");
+}
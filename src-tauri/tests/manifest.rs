@@ -0,0 +1,330 @@
+use byte_me_lib::manifest::{analyze, ManifestError};
+use byte_me_lib::models::{ManifestKind, VariantAnalysis};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+mod common;
+use common::*;
+
+/// Samples per segment: 2 seconds at 25 fps.
+const SAMPLES: usize = 50;
+
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        let target = to.join(entry.file_name());
+        if entry.file_type().unwrap().is_dir() {
+            copy_dir(&entry.path(), &target);
+        } else {
+            fs::copy(entry.path(), target).unwrap();
+        }
+    }
+}
+
+/// Copies a fixture directory from `tests/fixtures/manifest` into a temporary directory.
+fn fixture(name: &str) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/manifest")
+        .join(name);
+    copy_dir(&source, dir.path());
+    dir
+}
+
+/// One fragmented-MP4 media segment; segment `index` starts at `index * 2` seconds.
+fn media_segment(index: usize, sample_size: u32, starts_on_keyframe: bool) -> Vec<u8> {
+    fragment(
+        index as u32 + 1,
+        index as u64 * 2000,
+        &[sample_size; SAMPLES],
+        starts_on_keyframe,
+    )
+}
+
+/// Writes `init.mp4` and the named media segments into `dir`.
+fn write_rendition(dir: &Path, names: &[&str], sample_size: u32, unaligned: Option<usize>) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("init.mp4"), init_segment(1000, 40)).unwrap();
+    for (index, name) in names.iter().enumerate() {
+        let segment = media_segment(index, sample_size, unaligned != Some(index));
+        fs::write(dir.join(name), segment).unwrap();
+    }
+}
+
+fn variant<'a>(variants: &'a [VariantAnalysis], id: &str) -> &'a VariantAnalysis {
+    variants.iter().find(|variant| variant.id == id).unwrap()
+}
+
+fn file_size(path: PathBuf) -> u64 {
+    fs::metadata(path).unwrap().len()
+}
+
+const HLS_SEGMENTS: [&str; 3] = ["seg0.m4s", "seg1.m4s", "seg2.m4s"];
+
+#[test]
+fn test_hls_master_playlist() {
+    let dir = fixture("hls");
+    write_rendition(&dir.path().join("low"), &HLS_SEGMENTS, 1000, None);
+    write_rendition(&dir.path().join("high"), &HLS_SEGMENTS, 3000, None);
+
+    let analysis = analyze(&dir.path().join("master.m3u8"), None).unwrap();
+    assert_eq!(analysis.id, "master.m3u8");
+    assert_eq!(analysis.kind, ManifestKind::Hls);
+    assert_eq!(analysis.variants.len(), 3);
+    assert_eq!(analysis.keyframes_aligned, Some(true));
+    assert!(analysis.misaligned_segments.is_empty());
+
+    let low = variant(&analysis.variants, "low/playlist.m3u8");
+    assert_eq!(low.declared_bandwidth, Some(150_000));
+    assert_eq!(low.average_bandwidth, Some(120_000));
+    assert_eq!(low.resolution.as_deref(), Some("640x360"));
+    assert_eq!(low.codecs.as_deref(), Some("avc1.42c01e,mp4a.40.2"));
+    assert_eq!(low.segments.len(), 3);
+
+    for (index, segment) in low.segments.iter().enumerate() {
+        let size = file_size(dir.path().join("low").join(HLS_SEGMENTS[index]));
+        assert_eq!(segment.uri, HLS_SEGMENTS[index]);
+        assert_eq!(segment.start, index as f64 * 2.0);
+        assert_eq!(segment.duration, 2.0);
+        assert_eq!(segment.size, size);
+        assert_eq!(segment.bitrate, Some(size as f64 * 8.0 / 2.0));
+        assert_eq!(segment.starts_with_keyframe, Some(true));
+        assert_eq!(segment.first_keyframe, Some(index as f64 * 2.0));
+        assert_eq!(segment.error, None);
+    }
+
+    // 50 kB every 2 seconds is over the declared 150 kbit/s
+    assert!(low.bandwidth_ratio.unwrap() > 1.0);
+    let high = variant(&analysis.variants, "high/playlist.m3u8");
+    assert!(high.bandwidth_ratio.unwrap() < 1.0);
+    assert!(high.measured_average.unwrap() > low.measured_average.unwrap());
+
+    let missing = variant(&analysis.variants, "missing/playlist.m3u8");
+    assert!(missing.segments.is_empty());
+    assert!(missing.error.is_some());
+}
+
+#[test]
+fn test_hls_misaligned_keyframes() {
+    let dir = fixture("hls");
+    write_rendition(&dir.path().join("low"), &HLS_SEGMENTS, 1000, None);
+    write_rendition(&dir.path().join("high"), &HLS_SEGMENTS, 3000, Some(1));
+
+    let analysis = analyze(&dir.path().join("master.m3u8"), None).unwrap();
+    assert_eq!(analysis.keyframes_aligned, Some(false));
+    assert_eq!(analysis.misaligned_segments, vec![1]);
+
+    let high = variant(&analysis.variants, "high/playlist.m3u8");
+    assert_eq!(high.segments[1].starts_with_keyframe, Some(false));
+}
+
+#[test]
+fn test_hls_missing_segment() {
+    let dir = fixture("hls");
+    write_rendition(&dir.path().join("low"), &HLS_SEGMENTS, 1000, None);
+    fs::remove_file(dir.path().join("low/seg2.m4s")).unwrap();
+
+    let analysis = analyze(&dir.path().join("low/playlist.m3u8"), None).unwrap();
+    assert_eq!(analysis.variants.len(), 1);
+    let segments = &analysis.variants[0].segments;
+    assert!(segments[2].error.is_some());
+    assert_eq!(segments[2].bitrate, None);
+
+    // Only readable segments count towards the measured average
+    let expected = (segments[0].size + segments[1].size) as f64 * 8.0 / 4.0;
+    assert_eq!(analysis.variants[0].measured_average, Some(expected));
+}
+
+#[test]
+fn test_hls_byte_ranges() {
+    let dir = tempfile::tempdir().unwrap();
+    let init = init_segment(1000, 40);
+    let segments: Vec<Vec<u8>> = (0..2).map(|index| media_segment(index, 500, true)).collect();
+    fs::write(
+        dir.path().join("media.mp4"),
+        [init.clone(), segments.concat()].concat(),
+    )
+    .unwrap();
+
+    // The second range continues from the end of the first
+    let playlist = format!(
+        "#EXTM3U\n#EXT-X-VERSION:7\n#EXT-X-TARGETDURATION:2\n\
+         #EXT-X-MAP:URI=\"media.mp4\",BYTERANGE=\"{}@0\"\n\
+         #EXTINF:2.0,\n#EXT-X-BYTERANGE:{}@{}\nmedia.mp4\n\
+         #EXTINF:2.0,\n#EXT-X-BYTERANGE:{}\nmedia.mp4\n#EXT-X-ENDLIST\n",
+        init.len(),
+        segments[0].len(),
+        init.len(),
+        segments[1].len(),
+    );
+    let path = dir.path().join("single.m3u8");
+    fs::write(&path, playlist).unwrap();
+
+    let analysis = analyze(&path, None).unwrap();
+    let segments_analysis = &analysis.variants[0].segments;
+    assert_eq!(segments_analysis.len(), 2);
+    for (index, segment) in segments_analysis.iter().enumerate() {
+        assert_eq!(segment.size, segments[index].len() as u64);
+        assert_eq!(segment.starts_with_keyframe, Some(true));
+        assert_eq!(segment.first_keyframe, Some(index as f64 * 2.0));
+    }
+}
+
+#[test]
+fn test_dash_segment_template() {
+    let dir = fixture("dash");
+    let names = ["seg001.m4s", "seg002.m4s", "seg003.m4s"];
+    write_rendition(&dir.path().join("low"), &names, 1000, None);
+    write_rendition(&dir.path().join("high"), &names, 3000, None);
+
+    let analysis = analyze(&dir.path().join("manifest.mpd"), None).unwrap();
+    assert_eq!(analysis.kind, ManifestKind::Dash);
+    assert_eq!(analysis.keyframes_aligned, Some(true));
+
+    // The audio adaptation set is left out of the video ladder
+    let ids: Vec<&str> = analysis
+        .variants
+        .iter()
+        .map(|variant| variant.id.as_str())
+        .collect();
+    assert_eq!(ids, vec!["low", "high"]);
+
+    let high = variant(&analysis.variants, "high");
+    assert_eq!(high.declared_bandwidth, Some(800_000));
+    assert_eq!(high.resolution.as_deref(), Some("1280x720"));
+    assert_eq!(high.codecs.as_deref(), Some("avc1.4d401f"));
+
+    let uris: Vec<&str> = high
+        .segments
+        .iter()
+        .map(|segment| segment.uri.as_str())
+        .collect();
+    assert_eq!(
+        uris,
+        vec!["high/seg001.m4s", "high/seg002.m4s", "high/seg003.m4s"]
+    );
+    for (index, segment) in high.segments.iter().enumerate() {
+        assert_eq!(segment.start, index as f64 * 2.0);
+        assert_eq!(segment.duration, 2.0);
+        assert_eq!(
+            segment.size,
+            file_size(dir.path().join("high").join(names[index]))
+        );
+        assert_eq!(segment.first_keyframe, Some(index as f64 * 2.0));
+    }
+}
+
+#[test]
+fn test_dash_numbered_template_without_duration() {
+    let dir = tempfile::tempdir().unwrap();
+    let names = ["chunk-1.m4s", "chunk-2.m4s"];
+    write_rendition(&dir.path().join("video"), &names, 800, None);
+
+    // No presentation duration: segments are enumerated until one is missing
+    let mpd = r#"<?xml version="1.0"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static">
+  <BaseURL>video/</BaseURL>
+  <Period>
+    <AdaptationSet mimeType="video/mp4">
+      <Representation id="v" bandwidth="300000" width="320" height="240">
+        <SegmentTemplate timescale="90000" duration="180000" initialization="init.mp4" media="chunk-$Number$.m4s"/>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+    let path = dir.path().join("stream.mpd");
+    fs::write(&path, mpd).unwrap();
+
+    let analysis = analyze(&path, None).unwrap();
+    let segments = &analysis.variants[0].segments;
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[1].uri, "chunk-2.m4s");
+    assert_eq!(segments[1].start, 2.0);
+    assert_eq!(segments[1].duration, 2.0);
+    // A single rendition can't be checked for alignment
+    assert_eq!(analysis.keyframes_aligned, None);
+}
+
+#[test]
+fn test_hostile_manifests() {
+    let dir = tempfile::tempdir().unwrap();
+    // Every S element repeats up to the cap, and times and padding run off the scale
+    let mpd = r#"<?xml version="1.0"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static">
+  <Period>
+    <AdaptationSet mimeType="video/mp4">
+      <Representation id="v" bandwidth="300000">
+        <SegmentTemplate timescale="1000" media="$Number%0999999999d$-$Time$.m4s">
+          <SegmentTimeline>
+            <S t="18446744073709551000" d="400" r="3"/>
+            <S d="1" r="99999"/>
+            <S d="1" r="99999"/>
+          </SegmentTimeline>
+        </SegmentTemplate>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+    let path = dir.path().join("hostile.mpd");
+    fs::write(&path, mpd).unwrap();
+
+    let analysis = analyze(&path, None).unwrap();
+    let segments = &analysis.variants[0].segments;
+    // The timeline stops where its time would overflow
+    assert_eq!(segments.len(), 2);
+    assert_eq!(
+        segments[0].uri,
+        "00000000000000000001-18446744073709551000.m4s"
+    );
+
+    let mpd = mpd.replace(r#"<S t="18446744073709551000" d="400" r="3"/>"#, "");
+    fs::write(&path, mpd).unwrap();
+    let analysis = analyze(&path, None).unwrap();
+    assert_eq!(analysis.variants[0].segments.len(), 100_000);
+
+    // Byte ranges reaching the end of the address space
+    fs::write(dir.path().join("media.mp4"), init_segment(1000, 40)).unwrap();
+    let mpd = r#"<?xml version="1.0"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static">
+  <Period>
+    <AdaptationSet mimeType="video/mp4">
+      <Representation id="v" bandwidth="300000">
+        <BaseURL>media.mp4</BaseURL>
+        <SegmentList duration="2">
+          <SegmentURL mediaRange="0-18446744073709551615"/>
+        </SegmentList>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+    fs::write(&path, mpd).unwrap();
+    let analysis = analyze(&path, None).unwrap();
+    assert_eq!(analysis.variants[0].segments[0].size, u64::MAX);
+
+    let path = dir.path().join("hostile.m3u8");
+    fs::write(
+        &path,
+        "#EXTM3U\n#EXT-X-TARGETDURATION:2\n\
+         #EXTINF:2.0,\n#EXT-X-BYTERANGE:18446744073709551615@1\nmedia.mp4\n\
+         #EXTINF:2.0,\n#EXT-X-BYTERANGE:10\nmedia.mp4\n#EXT-X-ENDLIST\n",
+    )
+    .unwrap();
+    let analysis = analyze(&path, None).unwrap();
+    assert_eq!(analysis.variants[0].segments.len(), 2);
+}
+
+#[test]
+fn test_not_a_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.txt");
+    fs::write(&path, "just some text").unwrap();
+    assert!(matches!(
+        analyze(&path, None),
+        Err(ManifestError::Invalid(_))
+    ));
+
+    let path = dir.path().join("broken.mpd");
+    fs::write(&path, "<MPD><Period>").unwrap();
+    assert!(matches!(analyze(&path, None), Err(ManifestError::Xml(_))));
+}
@@ -0,0 +1,121 @@
+#![cfg(unix)]
+
+use byte_me_lib::process::{run, stream, ProcessError, ProcessLimits};
+use byte_me_lib::settings::Settings;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+fn sh(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.args(["-c", script]);
+    command
+}
+
+#[test]
+fn test_collects_output() {
+    let output = run(
+        sh("printf hello; printf oops >&2"),
+        &ProcessLimits::default(),
+    )
+    .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"hello");
+    assert_eq!(output.stderr, "oops");
+
+    let output = run(sh("exit 3"), &ProcessLimits::default()).unwrap();
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_timeout_kills_process() {
+    let limits = ProcessLimits {
+        timeout_secs: 1,
+        ..ProcessLimits::default()
+    };
+
+    let started = Instant::now();
+    let err = run(sh("exec sleep 30"), &limits).unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert!(matches!(err, ProcessError::Timeout { .. }));
+    assert_eq!(err.to_string(), "sh timed out after 1 seconds");
+}
+
+#[test]
+fn test_output_limit() {
+    let limits = ProcessLimits {
+        max_output_bytes: 1024,
+        ..ProcessLimits::default()
+    };
+
+    // `yes` never stops on its own, so this only returns if the process is killed
+    let err = run(Command::new("yes"), &limits).unwrap_err();
+    assert!(matches!(
+        err,
+        ProcessError::OutputTooLarge { limit: 1024, .. }
+    ));
+}
+
+#[test]
+fn test_stderr_is_truncated() {
+    let limits = ProcessLimits {
+        max_stderr_bytes: 16,
+        ..ProcessLimits::default()
+    };
+
+    // Far more stderr than a pipe buffer holds; the process must still finish
+    let output = run(
+        sh("head -c 1000000 /dev/zero | tr '\\0' e >&2; printf done"),
+        &limits,
+    )
+    .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stderr, "e".repeat(16));
+    assert_eq!(output.stdout, b"done");
+}
+
+#[test]
+fn test_stream_chunks() {
+    let mut total = 0;
+    let (status, _) = stream(
+        sh("head -c 300000 /dev/zero"),
+        &ProcessLimits::default(),
+        |chunk| {
+            total += chunk.len();
+            Ok(())
+        },
+    )
+    .unwrap();
+    assert!(status.success());
+    assert_eq!(total, 300000);
+}
+
+#[test]
+fn test_spawn_failure() {
+    let err = run(
+        Command::new("/nonexistent/ffprobe"),
+        &ProcessLimits::default(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ProcessError::Spawn { ref program, .. } if program == "ffprobe"));
+}
+
+#[test]
+fn test_decode_limits_are_separate() {
+    let settings = Settings::default();
+    assert_eq!(
+        settings.process_limits.timeout(),
+        Some(Duration::from_secs(600))
+    );
+    // Decoding a feature-length encode can take hours
+    assert_eq!(settings.ffmpeg_limits.timeout(), None);
+    assert_eq!(
+        settings.ffmpeg_limits.max_output_bytes,
+        settings.process_limits.max_output_bytes
+    );
+
+    // Settings saved before ffmpeg had its own limits don't inherit the probe timeout
+    let settings: Settings =
+        serde_json::from_str(r#"{"process_limits":{"timeout_secs":30}}"#).unwrap();
+    assert_eq!(settings.process_limits.timeout_secs, 30);
+    assert_eq!(settings.ffmpeg_limits, ProcessLimits::decode());
+}
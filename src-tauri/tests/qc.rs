@@ -0,0 +1,142 @@
+use byte_me_lib::analysis::check_quality;
+use byte_me_lib::qc::{parse_black, parse_silence, QcSettings};
+use std::fs;
+
+mod common;
+use common::*;
+
+const BLACK: &str = "\
+frame:0    pts:0       pts_time:0
+lavfi.black_start=0
+frame:60   pts:60      pts_time:2.5
+lavfi.black_end=2.5
+frame:100  pts:100     pts_time:4.16667
+lavfi.black_start=4.16667
+frame:112  pts:112     pts_time:4.66667
+lavfi.black_end=4.66667
+frame:200  pts:200     pts_time:8.33333
+lavfi.black_start=8.33333
+";
+
+const SILENCE: &str = "\
+frame:10   pts:10240   pts_time:0.213333
+lavfi.silence_start=0.213333
+frame:150  pts:153600  pts_time:3.2
+lavfi.silence_end=3.2
+lavfi.silence_duration=2.98667
+frame:200  pts:204800  pts_time:4.26667
+lavfi.silence_start=4.26667
+frame:210  pts:215040  pts_time:4.48
+lavfi.silence_end=4.48
+lavfi.silence_duration=0.213333
+";
+
+#[test]
+fn test_black_intervals() {
+    let settings = QcSettings::default();
+    let intervals = parse_black(BLACK, &settings, Some(12.0));
+
+    // The half-second run is below the minimum duration
+    assert_eq!(intervals.len(), 2);
+    assert_eq!(intervals[0].start, 0.0);
+    assert_eq!(intervals[0].end, 2.5);
+    assert_eq!(intervals[0].duration, 2.5);
+    assert_eq!(intervals[0].start_frame, Some(0));
+    assert_eq!(intervals[0].end_frame, Some(60));
+
+    // Still black when the stream ends
+    assert_eq!(intervals[1].start_frame, Some(200));
+    assert_eq!(intervals[1].end, 12.0);
+    assert_eq!(intervals[1].end_frame, None);
+
+    let lenient = QcSettings {
+        black_min_duration: 0.1,
+        ..settings
+    };
+    assert_eq!(parse_black(BLACK, &lenient, Some(12.0)).len(), 3);
+
+    // Without a known duration an open run can't be measured, so it never qualifies
+    assert_eq!(parse_black(BLACK, &lenient, None).len(), 2);
+}
+
+#[test]
+fn test_silence_intervals() {
+    let settings = QcSettings::default();
+    let intervals = parse_silence(SILENCE, &settings, Some(12.0));
+    assert_eq!(intervals.len(), 1);
+    assert_eq!(intervals[0].start, 0.213333);
+    assert_eq!(intervals[0].end, 3.2);
+    assert_eq!(intervals[0].start_frame, None);
+
+    let settings = QcSettings {
+        silence_min_duration: 0.0,
+        ..settings
+    };
+    assert_eq!(parse_silence(SILENCE, &settings, Some(12.0)).len(), 2);
+    assert!(parse_silence("", &settings, Some(12.0)).is_empty());
+}
+
+#[test]
+fn test_settings_defaults() {
+    let settings: QcSettings = serde_json::from_str(r#"{"silence_noise_db":-50}"#).unwrap();
+    assert_eq!(settings.silence_noise_db, -50.0);
+    assert_eq!(settings.black_min_duration, 2.0);
+    assert_eq!(settings.black_pixel_threshold, 0.1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_check_quality_with_ffmpeg() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("clip.mp4");
+    fs::write(&input, b"\0\0\0\x18ftyp").unwrap();
+    let black = dir.path().join("black.txt");
+    fs::write(&black, BLACK).unwrap();
+    let silence = dir.path().join("silence.txt");
+    fs::write(&silence, SILENCE).unwrap();
+    let args = dir.path().join("args.txt");
+
+    let ffprobe = fake_ffprobe(
+        dir.path(),
+        r#"echo '{"streams":[{"codec_type":"video"},{"codec_type":"audio"}],"format":{"format_name":"mov,mp4","duration":"12.000000"}}'"#,
+    );
+    let ffmpeg = fake_ffmpeg(
+        dir.path(),
+        &format!(
+            "echo \"$@\" >> '{}'\ncase \"$*\" in\n  *blackdetect*) cat '{}' ;;\n  *) cat '{}' ;;\nesac",
+            args.display(),
+            black.display(),
+            silence.display()
+        ),
+    );
+
+    let report = check_quality(&ffprobe, &ffmpeg, &input, &QcSettings::default()).unwrap();
+    assert_eq!(report.id, "clip.mp4");
+    assert_eq!(report.thresholds, QcSettings::default());
+    assert_eq!(report.black.len(), 2);
+    assert_eq!(report.black[1].end, 12.0);
+    assert_eq!(report.silence.len(), 1);
+
+    let args = fs::read_to_string(&args).unwrap();
+    assert!(
+        args.contains("-map 0:v:0 -vf blackdetect=d=2:pic_th=0.98:pix_th=0.1,metadata="),
+        "{args}"
+    );
+    assert!(
+        args.contains("-map 0:a:0 -af silencedetect=n=-60dB:d=2,ametadata="),
+        "{args}"
+    );
+
+    // Audio-only files skip black detection
+    fs::remove_file(dir.path().join("args.txt")).unwrap();
+    let ffprobe = fake_ffprobe(
+        dir.path(),
+        r#"echo '{"streams":[{"codec_type":"audio"}],"format":{"format_name":"wav"}}'"#,
+    );
+    let report = check_quality(&ffprobe, &ffmpeg, &input, &QcSettings::default()).unwrap();
+    assert!(report.black.is_empty());
+    assert_eq!(report.silence.len(), 1);
+    assert!(!fs::read_to_string(dir.path().join("args.txt"))
+        .unwrap()
+        .contains("blackdetect"));
+}
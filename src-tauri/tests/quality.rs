@@ -0,0 +1,169 @@
+use byte_me_lib::analysis::compare_quality;
+use byte_me_lib::ff::escape_filter_value;
+use byte_me_lib::quality::{parse_scores, parse_vmaf_log, summarize};
+use std::fs;
+
+mod common;
+use common::*;
+
+const METADATA: &str = "\
+frame:0    pts:0       pts_time:0
+lavfi.psnr.mse_avg=0.00
+lavfi.psnr.psnr_avg=inf
+lavfi.ssim.Y=1.000000
+lavfi.ssim.All=1.000000
+lavfi.ssim.dB=inf
+frame:1    pts:1001    pts_time:0.0417083
+lavfi.psnr.mse_avg=3.21
+lavfi.psnr.psnr_avg=43.06
+lavfi.ssim.All=0.985000
+frame:2    pts:2002    pts_time:0.0834167
+lavfi.psnr.psnr_avg=37.50
+lavfi.ssim.All=0.955000
+";
+
+const VMAF_LOG: &str = r#"{
+  "version": "2.3.1",
+  "frames": [
+    {"frameNum": 0, "metrics": {"integer_motion": 0.0, "vmaf": 100.0}},
+    {"frameNum": 1, "metrics": {"integer_motion": 1.2, "vmaf": 95.5}},
+    {"frameNum": 2, "metrics": {"integer_motion": 2.4, "vmaf": 88.0}}
+  ],
+  "pooled_metrics": {"vmaf": {"min": 88.0, "max": 100.0, "mean": 94.5}}
+}"#;
+
+#[test]
+fn test_parse_scores() {
+    let frames = parse_scores(METADATA);
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames[1].frame_num, 1);
+    assert_eq!(frames[1].timestamp, Some(0.0417083));
+    assert_eq!(frames[1].psnr, Some(43.06));
+    assert_eq!(frames[1].ssim, Some(0.985));
+    assert_eq!(frames[1].vmaf, None);
+
+    // Identical frames have infinite PSNR, which is left out rather than plotted
+    assert_eq!(frames[0].psnr, None);
+    assert_eq!(frames[0].ssim, Some(1.0));
+
+    let psnr = summarize(frames.iter().map(|frame| frame.psnr)).unwrap();
+    assert_eq!(psnr.min, 37.5);
+    assert_eq!(psnr.max, 43.06);
+    assert!((psnr.mean - 40.28).abs() < 1e-9);
+    assert_eq!(summarize([None, None].into_iter()), None);
+}
+
+#[test]
+fn test_parse_vmaf_log() {
+    assert_eq!(
+        parse_vmaf_log(VMAF_LOG).unwrap(),
+        [(0, 100.0), (1, 95.5), (2, 88.0)]
+    );
+    assert!(parse_vmaf_log("not json").is_err());
+}
+
+#[test]
+fn test_escape_filter_value() {
+    assert_eq!(escape_filter_value("/tmp/vmaf.json"), "/tmp/vmaf.json");
+    assert_eq!(
+        escape_filter_value(r"C:\Temp\vmaf.json"),
+        r"C\\:\\\\Temp\\\\vmaf.json"
+    );
+    assert_eq!(escape_filter_value("a,b'c"), r"a\,b\\\'c");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_compare_without_vmaf() {
+    let dir = tempfile::tempdir().unwrap();
+    let distorted = dir.path().join("encode.mp4");
+    let reference = dir.path().join("source.mov");
+    fs::write(&distorted, b"\0\0\0\x18ftyp").unwrap();
+    fs::write(&reference, b"\0\0\0\x14ftypqt  ").unwrap();
+    let output = dir.path().join("metadata.txt");
+    fs::write(&output, METADATA).unwrap();
+    let args = dir.path().join("args.txt");
+
+    let ffmpeg = fake_ffmpeg(
+        dir.path(),
+        &format!(
+            "case \"$*\" in\n  *-filters*) echo ' ... psnr  VV->V  Calculate the PSNR.' ;;\n  \
+             *) echo \"$@\" > '{}'; cat '{}' ;;\nesac",
+            args.display(),
+            output.display()
+        ),
+    );
+
+    let analysis = compare_quality(&ffmpeg, &distorted, &reference).unwrap();
+    assert_eq!(analysis.id, "encode.mp4");
+    assert_eq!(analysis.reference, "source.mov");
+    assert_eq!(analysis.frames.len(), 3);
+    assert_eq!(analysis.ssim.unwrap().min, 0.955);
+    assert_eq!(analysis.vmaf, None);
+    assert_eq!(
+        analysis.vmaf_unavailable.as_deref(),
+        Some("ffmpeg was built without libvmaf")
+    );
+
+    // The distorted file is input 0, so frames line up with its bitrate data
+    let args = fs::read_to_string(&args).unwrap();
+    assert!(args.contains(&format!(
+        "-i {} -i {} -lavfi [0:v:0][1:v:0]scale2ref",
+        distorted.display(),
+        reference.display()
+    )));
+    assert!(
+        args.contains("[scored][ref2]ssim,metadata=mode=print"),
+        "{args}"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_compare_with_vmaf() {
+    let dir = tempfile::tempdir().unwrap();
+    let distorted = dir.path().join("encode.mp4");
+    let reference = dir.path().join("source.mov");
+    fs::write(&distorted, b"\0\0\0\x18ftyp").unwrap();
+    fs::write(&reference, b"\0\0\0\x14ftypqt  ").unwrap();
+    let output = dir.path().join("metadata.txt");
+    fs::write(&output, METADATA).unwrap();
+    let log = dir.path().join("vmaf.json");
+    fs::write(&log, VMAF_LOG).unwrap();
+
+    // Copies the prepared log to wherever libvmaf was asked to write it
+    let ffmpeg = fake_ffmpeg(
+        dir.path(),
+        &format!(
+            "case \"$*\" in\n  *-filters*) echo ' ... libvmaf  VV->V  Calculate the VMAF.' ;;\n  \
+             *log_path=*) cp '{}' \"$(echo \"$*\" | sed 's/.*log_path=\\([^ ]*\\).*/\\1/')\" ;;\n  \
+             *) cat '{}' ;;\nesac",
+            log.display(),
+            output.display()
+        ),
+    );
+
+    let analysis = compare_quality(&ffmpeg, &distorted, &reference).unwrap();
+    assert_eq!(analysis.vmaf_unavailable, None);
+    let vmaf: Vec<_> = analysis.frames.iter().map(|frame| frame.vmaf).collect();
+    assert_eq!(vmaf, [Some(100.0), Some(95.5), Some(88.0)]);
+    assert_eq!(analysis.vmaf.unwrap().mean, 94.5);
+
+    // A failing VMAF pass degrades to PSNR and SSIM only
+    let broken = fake_ffmpeg(
+        dir.path(),
+        &format!(
+            "case \"$*\" in\n  *-filters*) echo ' ... libvmaf  VV->V  Calculate the VMAF.' ;;\n  \
+             *log_path=*) echo 'Error loading model' >&2; exit 1 ;;\n  \
+             *) cat '{}' ;;\nesac",
+            output.display()
+        ),
+    );
+    let analysis = compare_quality(&broken, &distorted, &reference).unwrap();
+    assert_eq!(analysis.vmaf, None);
+    assert!(analysis
+        .vmaf_unavailable
+        .unwrap()
+        .contains("Error loading model"));
+    assert!(analysis.psnr.is_some());
+}
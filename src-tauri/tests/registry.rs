@@ -0,0 +1,120 @@
+use byte_me_lib::media::{detect_from_bytes, detect_media_type};
+use byte_me_lib::models::MediaType;
+use byte_me_lib::registry::{self, MediaTypeRegistry, MediaTypeSettings, BUILTIN};
+use std::collections::HashMap;
+
+#[test]
+fn test_mime_and_extension_paths_agree() {
+    let registry = MediaTypeRegistry::default();
+    for entry in BUILTIN {
+        for mime_type in entry.mime_types {
+            assert_eq!(
+                registry.from_mime(mime_type),
+                Some(entry.media_type.clone()),
+                "{mime_type}"
+            );
+        }
+        for extension in entry.extensions {
+            assert_eq!(
+                registry.from_extension(extension),
+                Some(entry.media_type.clone()),
+                "{extension}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_no_duplicate_mappings() {
+    let mut mime_types = HashMap::new();
+    let mut extensions = HashMap::new();
+    for entry in BUILTIN {
+        for mime_type in entry.mime_types {
+            assert!(
+                mime_types.insert(*mime_type, &entry.media_type).is_none(),
+                "{mime_type} listed twice"
+            );
+        }
+        for extension in entry.extensions {
+            assert_eq!(*extension, extension.to_lowercase());
+            assert!(
+                extensions.insert(*extension, &entry.media_type).is_none(),
+                "{extension} listed twice"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_previous_disagreements() {
+    let registry = MediaTypeRegistry::default();
+
+    // Says nothing about the content, so the extension decides
+    assert_eq!(registry.from_mime("application/octet-stream"), None);
+    assert_eq!(registry.from_mime("text/plain"), Some(MediaType::Document));
+    assert_eq!(
+        registry.from_mime("text/markdown"),
+        Some(MediaType::Document)
+    );
+    assert_eq!(registry.from_extension("md"), Some(MediaType::Document));
+
+    // Magic numbers without a usable MIME type fall back to the name
+    assert_eq!(
+        detect_from_bytes(b"plain text", "notes.md").media_type,
+        MediaType::Document
+    );
+    assert_eq!(
+        detect_from_bytes(b"plain text", "notes").media_type,
+        MediaType::Unknown
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("NOTES.TXT");
+    std::fs::write(&notes, b"call the client").unwrap();
+    assert_eq!(detect_media_type(&notes), MediaType::Document);
+}
+
+#[test]
+fn test_normalization() {
+    let registry = MediaTypeRegistry::default();
+    assert_eq!(
+        registry.from_mime("Text/Plain; charset=utf-8"),
+        Some(MediaType::Document)
+    );
+    assert_eq!(registry.from_extension(".MKV"), Some(MediaType::Video));
+    assert_eq!(registry.from_extension("z"), Some(MediaType::Archive));
+    assert_eq!(registry.from_extension("braw"), None);
+}
+
+#[test]
+fn test_user_mappings() {
+    let settings = MediaTypeSettings {
+        mime_types: [("video/x-braw".to_string(), MediaType::Video)].into(),
+        extensions: [
+            (".BRAW".to_string(), MediaType::Video),
+            ("txt".to_string(), MediaType::Unknown),
+        ]
+        .into(),
+    };
+
+    let registry = MediaTypeRegistry::with_settings(&settings);
+    assert_eq!(registry.from_mime("video/x-braw"), Some(MediaType::Video));
+    assert_eq!(registry.from_extension("braw"), Some(MediaType::Video));
+    // User mappings take precedence over the built-in ones
+    assert_eq!(registry.from_extension("txt"), Some(MediaType::Unknown));
+    assert_eq!(registry.from_extension("mp4"), Some(MediaType::Video));
+
+    registry::configure(&MediaTypeSettings {
+        extensions: [("braw".to_string(), MediaType::Video)].into(),
+        ..MediaTypeSettings::default()
+    });
+    assert_eq!(
+        detect_from_bytes(&[0; 16], "A001_C003.braw").media_type,
+        MediaType::Video
+    );
+    registry::configure(&MediaTypeSettings::default());
+    assert_eq!(
+        detect_from_bytes(&[0; 16], "A001_C003.braw").media_type,
+        MediaType::Unknown
+    );
+}
@@ -0,0 +1,278 @@
+use byte_me_lib::analysis::{analyze_url, classify_url};
+use byte_me_lib::capabilities::{Capabilities, FfmpegVersion};
+use byte_me_lib::models::{FileCandidacy, MediaType};
+use byte_me_lib::remote::{
+    fetch_header, is_url, parse_url, url_file_name, NetworkSettings, HEADER_LEN,
+};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone)]
+enum Route {
+    /// Serves the body, honoring `Range` requests when `ranges` is set.
+    Body {
+        body: Vec<u8>,
+        ranges: bool,
+    },
+    Status(u16),
+    /// Accepts the request and never answers.
+    Stall,
+}
+
+fn handle(stream: TcpStream, routes: &HashMap<&'static str, Route>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let path = target.split('?').next().unwrap_or(target);
+
+    let mut range = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("range") {
+                range = value.trim().strip_prefix("bytes=").map(str::to_string);
+            }
+        }
+    }
+
+    let mut stream = stream;
+    let response = match routes.get(path) {
+        Some(Route::Stall) => {
+            thread::sleep(Duration::from_secs(5));
+            return;
+        }
+        Some(Route::Status(status)) => {
+            format!("HTTP/1.1 {status} Nope\r\nContent-Length: 0\r\n\r\n").into_bytes()
+        }
+        Some(Route::Body { body, ranges: true }) if range.is_some() => {
+            let range = range.unwrap();
+            let (start, end) = range.split_once('-').unwrap();
+            let start: usize = start.parse().unwrap();
+            let end = end.parse::<usize>().unwrap().min(body.len() - 1);
+            let head = format!(
+                "HTTP/1.1 206 Partial Content\r\nContent-Type: application/octet-stream\r\n\
+                 Content-Range: bytes {start}-{end}/{}\r\nContent-Length: {}\r\n\r\n",
+                body.len(),
+                end - start + 1
+            );
+            [head.into_bytes(), body[start..=end].to_vec()].concat()
+        }
+        Some(Route::Body { body, .. }) => {
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len());
+            [head.into_bytes(), body.clone()].concat()
+        }
+        None => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_vec(),
+    };
+    // Clients hang up once they have the bytes they asked for
+    let _ = stream.write_all(&response);
+}
+
+/// Starts an HTTP server on a free local port, returning its base URL.
+fn serve(routes: Vec<(&'static str, Route)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let routes = Arc::new(routes.into_iter().collect::<HashMap<_, _>>());
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let routes = Arc::clone(&routes);
+            thread::spawn(move || handle(stream, &routes));
+        }
+    });
+    base
+}
+
+fn png(len: usize) -> Vec<u8> {
+    let mut body = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+    body.resize(len, 0xAB);
+    body
+}
+
+#[test]
+fn test_is_url() {
+    assert!(is_url("http://example.com/clip.mp4"));
+    assert!(is_url("HTTPS://example.com/clip.mp4"));
+    assert!(!is_url("/home/user/clip.mp4"));
+    assert!(!is_url("C:\\Videos\\clip.mp4"));
+    assert!(!is_url("file:///home/user/clip.mp4"));
+    assert!(!is_url("ftp://example.com/clip.mp4"));
+}
+
+#[test]
+fn test_url_file_name() {
+    let url = parse_url("https://cdn.example.com/videos/My%20Clip.mp4?token=abc#t=10").unwrap();
+    assert_eq!(url_file_name(&url), "My Clip.mp4");
+
+    let url = parse_url("https://cdn.example.com/videos/").unwrap();
+    assert_eq!(url_file_name(&url), "videos");
+
+    let url = parse_url("https://cdn.example.com").unwrap();
+    assert_eq!(url_file_name(&url), "cdn.example.com");
+
+    let err = parse_url("http://").unwrap_err();
+    assert_eq!(err.kind(), "invalid_url");
+}
+
+#[test]
+fn test_ranged_header() {
+    let body = png(4096);
+    let base = serve(vec![(
+        "/image.png",
+        Route::Body {
+            body: body.clone(),
+            ranges: true,
+        },
+    )]);
+
+    let url = parse_url(&format!("{base}/image.png")).unwrap();
+    let header = fetch_header(&url, &NetworkSettings::default()).unwrap();
+    assert_eq!(header.bytes, body[..HEADER_LEN as usize]);
+    assert_eq!(header.size, Some(4096));
+    assert_eq!(
+        header.content_type.as_deref(),
+        Some("application/octet-stream")
+    );
+}
+
+#[test]
+fn test_server_without_range_support() {
+    let body = png(100_000);
+    let base = serve(vec![(
+        "/image.png",
+        Route::Body {
+            body: body.clone(),
+            ranges: false,
+        },
+    )]);
+
+    // The full body is sent, but only the header is read
+    let url = parse_url(&format!("{base}/image.png")).unwrap();
+    let header = fetch_header(&url, &NetworkSettings::default()).unwrap();
+    assert_eq!(header.bytes, body[..HEADER_LEN as usize]);
+    assert_eq!(header.size, Some(100_000));
+}
+
+#[test]
+fn test_detection() {
+    let base = serve(vec![
+        (
+            "/download",
+            Route::Body {
+                body: png(2048),
+                ranges: true,
+            },
+        ),
+        (
+            "/clip.mkv",
+            Route::Body {
+                body: vec![0x5A; 2048],
+                ranges: true,
+            },
+        ),
+        (
+            "/notes.txt",
+            Route::Body {
+                body: b"just some notes".to_vec(),
+                ranges: true,
+            },
+        ),
+    ]);
+    let network = NetworkSettings::default();
+
+    // No extension: the magic number decides
+    let file = analyze_url(&format!("{base}/download?id=7"), &network);
+    assert_eq!(file.filename, "download");
+    assert_eq!(file.size, 2048);
+    assert!(matches!(
+        file.candidacy,
+        FileCandidacy::Success {
+            file_type: MediaType::Image,
+            ..
+        }
+    ));
+
+    // Unrecognized content falls back to the extension in the URL path
+    let (media_type, _) = classify_url(&format!("{base}/clip.mkv?session=1"), &network).unwrap();
+    assert!(matches!(media_type, MediaType::Video));
+
+    let err = classify_url(&format!("{base}/notes.txt"), &network).unwrap_err();
+    assert_eq!(err.kind(), "not_media");
+}
+
+#[test]
+fn test_http_status_error() {
+    let base = serve(vec![("/gone.mp4", Route::Status(410))]);
+    let network = NetworkSettings::default();
+
+    let err = classify_url(&format!("{base}/missing.mp4"), &network).unwrap_err();
+    assert_eq!(err.kind(), "http_error");
+    assert!(err.to_string().contains("404"), "{err}");
+
+    let file = analyze_url(&format!("{base}/gone.mp4"), &network);
+    assert_eq!(file.filename, "gone.mp4");
+    assert!(
+        matches!(file.candidacy, FileCandidacy::Error { ref reason } if reason.contains("410"))
+    );
+}
+
+#[test]
+fn test_connection_refused() {
+    // Bind and immediately release a port so nothing is listening on it
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let err = classify_url(
+        &format!("http://{addr}/clip.mp4"),
+        &NetworkSettings::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), "network_error");
+}
+
+#[test]
+fn test_timeout() {
+    let base = serve(vec![("/slow.mp4", Route::Stall)]);
+    let network = NetworkSettings {
+        timeout_secs: 1,
+        ..NetworkSettings::default()
+    };
+
+    let err = classify_url(&format!("{base}/slow.mp4"), &network).unwrap_err();
+    assert_eq!(err.kind(), "network_timeout");
+}
+
+#[test]
+fn test_ffprobe_network_args() {
+    let network = NetworkSettings::default();
+
+    let modern = Capabilities::new(FfmpegVersion::parse("6.1"), Vec::new());
+    let args = modern.network_args(&network);
+    assert_eq!(args[..2], ["-rw_timeout", "15000000"]);
+    assert!(args.contains(&"-reconnect_streamed".to_string()));
+    assert!(args.contains(&"-reconnect_on_network_error".to_string()));
+
+    // Options added in 4.4 would make older releases refuse to start
+    let old = Capabilities::new(FfmpegVersion::parse("4.2.7"), Vec::new());
+    let args = old.network_args(&network);
+    assert!(args.contains(&"-reconnect".to_string()));
+    assert!(!args.contains(&"-reconnect_on_network_error".to_string()));
+
+    let without_reconnect = NetworkSettings {
+        reconnect: false,
+        ..network
+    };
+    assert_eq!(
+        modern.network_args(&without_reconnect),
+        ["-rw_timeout", "15000000"]
+    );
+}
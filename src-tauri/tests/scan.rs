@@ -0,0 +1,203 @@
+use byte_me_lib::analysis::analyze_directory;
+use byte_me_lib::models::{FileCandidacy, MediaType};
+use byte_me_lib::scan::{walk_files, ScanSettings, SymlinkPolicy};
+use std::fs;
+use std::path::Path;
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+fn write(path: &Path, contents: &[u8]) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+/// A shoot folder with nested, hidden, non-media and linked entries, next to a
+/// directory outside of it that links point into.
+fn shoot() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("shoot");
+
+    write(&root.join("a.png"), PNG);
+    write(&root.join("notes.txt"), b"call the client");
+    write(&root.join("clip.mkv"), &[0x5A; 64]);
+    write(&root.join(".preview.png"), PNG);
+    write(&root.join(".cache/thumb.png"), PNG);
+    write(&root.join("sub/c.png"), PNG);
+    write(&root.join("sub/deep/d.png"), PNG);
+    write(&root.join("sub/deep/deeper/e.png"), PNG);
+    write(&root.join("proxies/p.png"), PNG);
+    write(&dir.path().join("outside/o.png"), PNG);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::symlink;
+        symlink(dir.path().join("outside/o.png"), root.join("linked.png")).unwrap();
+        symlink(dir.path().join("outside"), root.join("linked_dir")).unwrap();
+    }
+
+    dir
+}
+
+fn relative(dir: &Path, settings: &ScanSettings) -> Vec<String> {
+    let root = dir.join("shoot");
+    analyze_directory(&root, settings)
+        .iter()
+        .map(|file| {
+            let relative = Path::new(&file.path).strip_prefix(&root).unwrap();
+            relative.to_string_lossy().replace('\\', "/")
+        })
+        .collect()
+}
+
+#[test]
+#[cfg(unix)]
+fn test_default_expansion() {
+    let dir = shoot();
+    assert_eq!(
+        relative(dir.path(), &ScanSettings::default()),
+        [
+            "a.png",
+            "clip.mkv",
+            "linked.png",
+            "proxies/p.png",
+            "sub/c.png",
+            "sub/deep/d.png",
+            "sub/deep/deeper/e.png",
+        ]
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_depth_limit() {
+    let dir = shoot();
+    let settings = |max_depth| ScanSettings {
+        max_depth,
+        include: vec!["**/*.png".to_string()],
+        ..ScanSettings::default()
+    };
+
+    assert_eq!(relative(dir.path(), &settings(0)), ["a.png", "linked.png"]);
+    assert_eq!(
+        relative(dir.path(), &settings(1)),
+        ["a.png", "linked.png", "proxies/p.png", "sub/c.png"]
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_hidden_files() {
+    let dir = shoot();
+    let settings = ScanSettings {
+        include_hidden: true,
+        max_depth: 1,
+        ..ScanSettings::default()
+    };
+
+    let files = relative(dir.path(), &settings);
+    assert!(files.contains(&".preview.png".to_string()));
+    assert!(files.contains(&".cache/thumb.png".to_string()));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_include_and_exclude_patterns() {
+    let dir = shoot();
+    let settings = ScanSettings {
+        include: vec!["*.png".to_string()],
+        exclude: vec!["proxies".to_string(), "**/deeper/**".to_string()],
+        ..ScanSettings::default()
+    };
+
+    assert_eq!(
+        relative(dir.path(), &settings),
+        ["a.png", "linked.png", "sub/c.png", "sub/deep/d.png"]
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_symlink_policies() {
+    let dir = shoot();
+    let settings = |symlinks| ScanSettings {
+        symlinks,
+        max_depth: 0,
+        ..ScanSettings::default()
+    };
+
+    assert_eq!(
+        relative(dir.path(), &settings(SymlinkPolicy::Skip)),
+        ["a.png", "clip.mkv"]
+    );
+    assert_eq!(
+        relative(dir.path(), &settings(SymlinkPolicy::Files)),
+        ["a.png", "clip.mkv", "linked.png"]
+    );
+
+    let follow = ScanSettings {
+        max_depth: 1,
+        include: vec!["linked*".to_string()],
+        ..settings(SymlinkPolicy::Follow)
+    };
+    assert_eq!(
+        relative(dir.path(), &follow),
+        ["linked.png", "linked_dir/o.png"]
+    );
+}
+
+#[test]
+fn test_file_limit_and_invalid_patterns() {
+    let dir = shoot();
+    let root = dir.path().join("shoot");
+
+    let limited = ScanSettings {
+        max_files: 2,
+        ..ScanSettings::default()
+    };
+    assert_eq!(analyze_directory(&root, &limited).len(), 2);
+
+    let invalid = ScanSettings {
+        include: vec!["[unclosed".to_string()],
+        ..ScanSettings::default()
+    };
+    assert!(walk_files(&root, &invalid, |_| true).is_err());
+
+    let entries = analyze_directory(&root, &invalid);
+    assert_eq!(entries.len(), 1);
+    assert!(
+        matches!(&entries[0].candidacy, FileCandidacy::Error { reason } if reason.starts_with("Could not search directory"))
+    );
+}
+
+#[test]
+fn test_analyze_directory() {
+    let dir = shoot();
+    let root = dir.path().join("shoot");
+    let settings = ScanSettings {
+        max_depth: 0,
+        symlinks: SymlinkPolicy::Skip,
+        ..ScanSettings::default()
+    };
+
+    let entries = analyze_directory(&root, &settings);
+    let names: Vec<&str> = entries.iter().map(|file| file.filename.as_str()).collect();
+    assert_eq!(names, ["a.png", "clip.mkv"]);
+    assert_eq!(entries[0].path, root.join("a.png").to_string_lossy());
+    assert!(matches!(
+        entries[1].candidacy,
+        FileCandidacy::Success {
+            file_type: MediaType::Video,
+            ..
+        }
+    ));
+
+    // An empty drop still shows up, as an error
+    let empty = dir.path().join("empty");
+    fs::create_dir(&empty).unwrap();
+    let entries = analyze_directory(&empty, &ScanSettings::default());
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].filename, "empty");
+    assert!(
+        matches!(&entries[0].candidacy, FileCandidacy::Error { reason } if reason == "No media files found in directory")
+    );
+}
@@ -0,0 +1,87 @@
+use byte_me_lib::analysis::{score_scenes, AnalysisError};
+use byte_me_lib::ff::parse_frame_metadata;
+use byte_me_lib::scene::{detect, parse_scores, DEFAULT_THRESHOLD};
+use std::fs;
+
+mod common;
+use common::*;
+
+const METADATA: &str = "\
+frame:0    pts:0       pts_time:0
+lavfi.scene_score=0.000000
+frame:1    pts:1001    pts_time:0.0417083
+lavfi.scene_score=0.012000
+frame:2    pts:2002    pts_time:0.0834167
+lavfi.scene_score=0.870000
+frame:3    pts:3003    pts_time:0.125125
+lavfi.scene_score=0.410000
+frame:4    pts:N/A     pts_time:N/A
+";
+
+#[test]
+fn test_frame_metadata() {
+    let frames = parse_frame_metadata(METADATA);
+    assert_eq!(frames.len(), 5);
+    assert_eq!(frames[2].frame, 2);
+    assert_eq!(frames[2].pts_time, Some(0.0834167));
+    assert_eq!(frames[2].get("lavfi.scene_score"), Some("0.870000"));
+    assert_eq!(frames[2].number("lavfi.scene_score"), Some(0.87));
+    assert_eq!(frames[4].pts_time, None);
+    assert!(frames[4].entries.is_empty());
+
+    // Stray lines before the first frame are ignored
+    assert!(parse_frame_metadata("key=value\n").is_empty());
+}
+
+#[test]
+fn test_cuts_above_threshold() {
+    let scores = parse_scores(METADATA);
+    let summary: Vec<_> = scores
+        .iter()
+        .map(|score| (score.frame_num, score.score))
+        .collect();
+    assert_eq!(
+        summary,
+        [(0, 0.0), (1, 0.012), (2, 0.87), (3, 0.41), (4, 0.0)]
+    );
+
+    let analysis = detect("clip.mp4".to_string(), scores.clone(), DEFAULT_THRESHOLD);
+    assert_eq!(analysis.threshold, 0.4);
+    assert_eq!(analysis.scores.len(), 5);
+    let cuts: Vec<_> = analysis.cuts.iter().map(|cut| cut.frame_num).collect();
+    assert_eq!(cuts, [2, 3]);
+    assert_eq!(analysis.cuts[0].timestamp, Some(0.0834167));
+
+    let strict = detect("clip.mp4".to_string(), scores, 0.5);
+    assert_eq!(strict.cuts.len(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_score_with_ffmpeg() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("clip.mp4");
+    fs::write(&input, b"\0\0\0\x18ftyp").unwrap();
+    let output = dir.path().join("metadata.txt");
+    fs::write(&output, METADATA).unwrap();
+    let args = dir.path().join("args.txt");
+
+    let ffmpeg = fake_ffmpeg(
+        dir.path(),
+        &format!(
+            "echo \"$@\" > '{}'\ncat '{}'",
+            args.display(),
+            output.display()
+        ),
+    );
+    assert_eq!(score_scenes(&ffmpeg, &input).unwrap().len(), 5);
+    let args = fs::read_to_string(&args).unwrap();
+    assert!(args.contains("-map 0:v:0"), "{args}");
+    assert!(args.contains(r"select=gte(scene\,0),metadata=mode=print:file=pipe\:1"));
+
+    let audio_only = fake_ffmpeg(dir.path(), "exit 0");
+    assert!(matches!(
+        score_scenes(&audio_only, &input),
+        Err(AnalysisError::NoFrames)
+    ));
+}
@@ -0,0 +1,287 @@
+use byte_me_lib::analysis::{keyframe_thumbnails, thumbnail_at, AnalysisError};
+use byte_me_lib::models::{BitrateData, BitrateFrame, ThumbnailFormat};
+use byte_me_lib::thumbnails;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+mod common;
+use common::*;
+
+fn frame(frame_num: u32, timestamp: f64, keyframe: bool) -> BitrateFrame {
+    BitrateFrame {
+        frame_num,
+        packet_size: 1000,
+        timestamp: Some(timestamp),
+        keyframe,
+    }
+}
+
+fn calls(log: &Path) -> usize {
+    fs::read_to_string(log).map_or(0, |log| log.lines().count())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_thumbnail_at_timestamp() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("clip.mp4");
+    fs::write(&input, b"\0\0\0\x18ftyp").unwrap();
+    let root = dir.path().join("thumbnails");
+    let log = dir.path().join("calls.txt");
+
+    // Writes a fake image to the last argument
+    let ffmpeg = fake_ffmpeg(
+        dir.path(),
+        &format!(
+            "echo \"$@\" >> '{}'\nfor last; do :; done\nprintf 'image' > \"$last\"",
+            log.display()
+        ),
+    );
+    let thumbnail = thumbnail_at(&ffmpeg, &input, &root, 12.5, ThumbnailFormat::Jpeg, 160).unwrap();
+    assert_eq!(thumbnail.frame_num, None);
+    assert_eq!(thumbnail.timestamp, Some(12.5));
+    assert!(thumbnail.path.ends_with("at-12500-160.jpg"));
+    assert!(thumbnail.path.starts_with(root.to_str().unwrap()));
+    assert_eq!(fs::read(&thumbnail.path).unwrap(), b"image");
+    let args = fs::read_to_string(&log).unwrap();
+    assert!(
+        args.starts_with("-hide_banner -nostdin -v error -ss 12.500 -i"),
+        "{args}"
+    );
+    assert!(args.contains("scale=160:-2"), "{args}");
+
+    // Rendered once, then served from disk
+    let again = thumbnail_at(&ffmpeg, &input, &root, 12.5, ThumbnailFormat::Jpeg, 160).unwrap();
+    assert_eq!(again, thumbnail);
+    assert_eq!(calls(&log), 1);
+
+    let mut png = thumbnail_at(&ffmpeg, &input, &root, 12.5, ThumbnailFormat::Png, 160).unwrap();
+    assert!(png.path.ends_with(".png"));
+    assert_eq!(calls(&log), 2);
+    thumbnails::inline(&mut png, ThumbnailFormat::Png).unwrap();
+    assert_eq!(
+        png.data_url.as_deref(),
+        Some("data:image/png;base64,aW1hZ2U=")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_past_the_end() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("clip.mp4");
+    fs::write(&input, b"\0\0\0\x18ftyp").unwrap();
+
+    let ffmpeg = fake_ffmpeg(dir.path(), "exit 0");
+    assert!(matches!(
+        thumbnail_at(
+            &ffmpeg,
+            &input,
+            dir.path(),
+            9999.0,
+            ThumbnailFormat::Jpeg,
+            160
+        ),
+        Err(AnalysisError::NoFrames)
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_keyframe_thumbnails() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("clip.mkv");
+    fs::write(&input, [0x1A, 0x45, 0xDF, 0xA3]).unwrap();
+    let root = dir.path().join("thumbnails");
+    let log = dir.path().join("calls.txt");
+
+    // Writes one image per keyframe next to the output pattern, named by pts in ms
+    let ffmpeg = fake_ffmpeg(
+        dir.path(),
+        &format!(
+            "echo \"$@\" >> '{}'\nfor last; do :; done\nout=$(dirname \"$last\")\n\
+             printf a > \"$out/0.jpg\"\nprintf b > \"$out/2000.jpg\"",
+            log.display()
+        ),
+    );
+    let bitrate = BitrateData {
+        id: "clip.mkv".to_string(),
+        frames: vec![
+            frame(0, 0.0, true),
+            frame(1, 0.04, false),
+            frame(2, 0.08, false),
+            frame(3, 2.0, true),
+        ],
+    };
+
+    let rendered =
+        keyframe_thumbnails(&ffmpeg, &input, &root, &bitrate, ThumbnailFormat::Jpeg, 160).unwrap();
+    let summary: Vec<_> = rendered
+        .iter()
+        .map(|thumbnail| (thumbnail.frame_num, thumbnail.timestamp))
+        .collect();
+    assert_eq!(summary, [(Some(0), Some(0.0)), (Some(3), Some(2.0))]);
+    assert_eq!(fs::read(&rendered[1].path).unwrap(), b"b");
+    let args = fs::read_to_string(&log).unwrap();
+    assert!(args.starts_with("-hide_banner -nostdin -v error -skip_frame nokey -i"));
+    assert!(args.contains("-fps_mode passthrough"), "{args}");
+    assert!(args.contains("-frame_pts 1"), "{args}");
+
+    let again =
+        keyframe_thumbnails(&ffmpeg, &input, &root, &bitrate, ThumbnailFormat::Jpeg, 160).unwrap();
+    assert_eq!(again, rendered);
+    assert_eq!(calls(&log), 1);
+
+    let removed = thumbnails::clear(&root).unwrap();
+    assert_eq!(removed.entries, 2);
+    assert!(!root.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_keyframe_flags_disagree() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("clip.ts");
+    fs::write(&input, [0x47; 188]).unwrap();
+    let root = dir.path().join("thumbnails");
+
+    // The decoder finds a keyframe the container didn't flag, and one past the data
+    let ffmpeg = fake_ffmpeg(
+        dir.path(),
+        "for last; do :; done\nout=$(dirname \"$last\")\n\
+         printf a > \"$out/1400.jpg\"\nprintf b > \"$out/1480.jpg\"\n\
+         printf c > \"$out/3400.jpg\"\nprintf d > \"$out/9000.jpg\"",
+    );
+    let bitrate = BitrateData {
+        id: "clip.ts".to_string(),
+        frames: vec![
+            frame(0, 1.4, true),
+            frame(1, 1.44, false),
+            frame(2, 1.48, false),
+            frame(3, 3.4, true),
+        ],
+    };
+
+    let rendered =
+        keyframe_thumbnails(&ffmpeg, &input, &root, &bitrate, ThumbnailFormat::Jpeg, 160).unwrap();
+    let summary: Vec<_> = rendered
+        .iter()
+        .map(|thumbnail| (thumbnail.frame_num, thumbnail.timestamp))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (Some(0), Some(1.4)),
+            (Some(2), Some(1.48)),
+            (Some(3), Some(3.4)),
+            (None, Some(9.0))
+        ]
+    );
+    assert_eq!(fs::read(&rendered[2].path).unwrap(), b"c");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_keyframes_on_older_ffmpeg() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("clip.mkv");
+    fs::write(&input, [0x1A, 0x45, 0xDF, 0xA3]).unwrap();
+    let log = dir.path().join("calls.txt");
+
+    // `-fps_mode` only arrived in 5.1
+    let ffmpeg = fake_ffmpeg_version(
+        dir.path(),
+        "4.4.2",
+        &format!(
+            "echo \"$@\" >> '{}'\nfor last; do :; done\nprintf a > \"$(dirname \"$last\")/0.jpg\"",
+            log.display()
+        ),
+    );
+    let bitrate = BitrateData {
+        id: "clip.mkv".to_string(),
+        frames: vec![frame(0, 0.0, true)],
+    };
+    let root = dir.path().join("thumbnails");
+    keyframe_thumbnails(&ffmpeg, &input, &root, &bitrate, ThumbnailFormat::Jpeg, 160).unwrap();
+    let args = fs::read_to_string(&log).unwrap();
+    assert!(args.contains("-vsync passthrough"), "{args}");
+    assert!(!args.contains("-fps_mode"), "{args}");
+}
+
+#[test]
+fn test_eviction() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("thumbnails");
+    // One directory per input file, each used a minute after the one before
+    let now = SystemTime::now();
+    for (index, name) in ["old", "recent", "current"].into_iter().enumerate() {
+        let images = root.join(name).join("keyframes-pts-160-jpg");
+        fs::create_dir_all(&images).unwrap();
+        for image in ["0.jpg", "2000.jpg"] {
+            let path = images.join(image);
+            fs::write(&path, [0; 100]).unwrap();
+            let used = now - Duration::from_secs(60 * (3 - index as u64));
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(used)
+                .unwrap();
+        }
+    }
+
+    // Within the limit, nothing goes
+    let keep = root.join("old");
+    assert_eq!(thumbnails::evict(&root, 600, &keep).unwrap().entries, 0);
+
+    // The oldest is spared when it is the one in use
+    let removed = thumbnails::evict(&root, 400, &keep).unwrap();
+    assert_eq!((removed.entries, removed.bytes), (2, 200));
+    assert!(keep.exists());
+    assert!(!root.join("recent").exists());
+
+    let removed = thumbnails::evict(&root, 0, &root.join("current")).unwrap();
+    assert_eq!(removed.entries, 2);
+    assert!(root.join("current").exists());
+    assert!(thumbnails::evict(&dir.path().join("missing"), 0, &keep).is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_interrupted_render_is_discarded() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("clip.mkv");
+    fs::write(&input, [0x1A, 0x45, 0xDF, 0xA3]).unwrap();
+    let root = dir.path().join("thumbnails");
+
+    let failing = fake_ffmpeg(
+        dir.path(),
+        "for last; do :; done\nprintf a > \"$(dirname \"$last\")/0.jpg\"\nexit 1",
+    );
+    let bitrate = BitrateData {
+        id: "clip.mkv".to_string(),
+        frames: vec![frame(0, 0.0, true)],
+    };
+    assert!(matches!(
+        keyframe_thumbnails(
+            &failing,
+            &input,
+            &root,
+            &bitrate,
+            ThumbnailFormat::Jpeg,
+            160
+        ),
+        Err(AnalysisError::Decode(_))
+    ));
+
+    // The half-written directory isn't treated as a finished render
+    let ffmpeg = fake_ffmpeg(dir.path(), "exit 0");
+    assert!(matches!(
+        keyframe_thumbnails(&ffmpeg, &input, &root, &bitrate, ThumbnailFormat::Jpeg, 160),
+        Err(AnalysisError::NoFrames)
+    ));
+    assert_eq!(fs::read_dir(&root).unwrap().count(), 1);
+    let file_dir = fs::read_dir(&root).unwrap().next().unwrap().unwrap().path();
+    assert_eq!(fs::read_dir(file_dir).unwrap().count(), 0);
+}
@@ -0,0 +1,66 @@
+use byte_me_lib::tools::{parse_version, resolve, Tool, ToolError, ToolSource};
+
+#[test]
+fn test_parse_version() {
+    assert_eq!(
+        parse_version(
+            Tool::Ffprobe,
+            "ffprobe version 6.1.1-3ubuntu5 Copyright (c) 2007-2023 the FFmpeg developers\nbuilt with gcc 13"
+        ),
+        Some("6.1.1-3ubuntu5".to_string())
+    );
+    assert_eq!(
+        parse_version(Tool::Ffmpeg, "ffmpeg version n7.0 Copyright (c) 2000-2024"),
+        Some("n7.0".to_string())
+    );
+
+    // Wrong tool or unrelated output
+    assert_eq!(parse_version(Tool::Ffprobe, "ffmpeg version 6.0"), None);
+    assert_eq!(parse_version(Tool::Ffprobe, "command not found"), None);
+    assert_eq!(parse_version(Tool::Ffprobe, ""), None);
+}
+
+#[cfg(unix)]
+fn write_script(dir: &std::path::Path, name: &str, body: &str) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = dir.join(name);
+    std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[cfg(unix)]
+#[test]
+fn test_resolve_configured_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake = write_script(
+        dir.path(),
+        "ffprobe-custom",
+        "echo 'ffprobe version 6.0 Copyright (c) 2007-2023 the FFmpeg developers'",
+    );
+
+    let resolved = resolve(Tool::Ffprobe, Some(&fake)).unwrap();
+    assert_eq!(resolved.path, fake);
+    assert_eq!(resolved.version, "6.0");
+    assert_eq!(resolved.source, ToolSource::Setting);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_resolve_rejects_invalid_binary() {
+    let dir = tempfile::tempdir().unwrap();
+    let broken = write_script(dir.path(), "ffprobe-broken", "exit 1");
+    let impostor = write_script(dir.path(), "ffprobe-impostor", "echo 'hello world'");
+
+    for candidate in [broken, impostor] {
+        match resolve(Tool::Ffprobe, Some(&candidate)) {
+            // Discovery may still find a real ffprobe further down the chain
+            Ok(resolved) => assert_ne!(resolved.source, ToolSource::Setting),
+            Err(ToolError::NotFound { tried, .. }) => {
+                assert!(tried[0].starts_with(&candidate.display().to_string()));
+            }
+            Err(err) => panic!("unexpected error: {err}"),
+        }
+    }
+}
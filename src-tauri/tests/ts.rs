@@ -0,0 +1,281 @@
+use byte_me_lib::media::detect_media_type;
+use byte_me_lib::models::MediaType;
+use byte_me_lib::ts::{analyze, detect_layout, TsLayout, PACKET_SIZE};
+use std::path::{Path, PathBuf};
+
+const PMT_PID: u16 = 0x100;
+const VIDEO_PID: u16 = 0x101;
+const AUDIO_PID: u16 = 0x102;
+const NULL_PID: u16 = 0x1FFF;
+
+/// Builds a packet, with an adaptation field carrying `pcr` (in 27 MHz ticks) if given.
+fn packet(pid: u16, unit_start: bool, cc: u8, pcr: Option<u64>, payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![
+        0x47,
+        (if unit_start { 0x40 } else { 0 }) | (pid >> 8) as u8,
+        pid as u8,
+        0x10 | (if pcr.is_some() { 0x20 } else { 0 }) | (cc & 0x0F),
+    ];
+    if let Some(pcr) = pcr {
+        let (base, extension) = (pcr / 300, pcr % 300);
+        out.extend_from_slice(&[
+            7,
+            0x10,
+            (base >> 25) as u8,
+            (base >> 17) as u8,
+            (base >> 9) as u8,
+            (base >> 1) as u8,
+            ((base & 1) << 7) as u8 | 0x7E | (extension >> 8) as u8,
+            extension as u8,
+        ]);
+    }
+    out.extend_from_slice(payload);
+    out.resize(PACKET_SIZE, 0xFF);
+    out
+}
+
+/// Wraps a PSI section body in a table header and a (dummy) CRC.
+fn section(table_id: u8, id: u16, body: &[u8]) -> Vec<u8> {
+    let length = 5 + body.len() + 4;
+    let mut out = vec![0x00, table_id, 0xB0 | (length >> 8) as u8, length as u8];
+    out.extend_from_slice(&id.to_be_bytes());
+    out.extend_from_slice(&[0xC1, 0x00, 0x00]);
+    out.extend_from_slice(body);
+    out.extend_from_slice(&[0; 4]);
+    out
+}
+
+fn pat() -> Vec<u8> {
+    let body = [0x00, 0x01, 0xE0 | (PMT_PID >> 8) as u8, PMT_PID as u8];
+    packet(0, true, 0, None, &section(0x00, 1, &body))
+}
+
+fn pmt() -> Vec<u8> {
+    let body = [
+        0xE0 | (VIDEO_PID >> 8) as u8,
+        VIDEO_PID as u8,
+        0xF0,
+        0x00,
+        0x1B,
+        0xE0 | (VIDEO_PID >> 8) as u8,
+        VIDEO_PID as u8,
+        0xF0,
+        0x00,
+        0x06,
+        0xE0 | (AUDIO_PID >> 8) as u8,
+        AUDIO_PID as u8,
+        0xF0,
+        0x03,
+        0x6A,
+        0x01,
+        0x00,
+    ];
+    packet(PMT_PID, true, 0, None, &section(0x02, 1, &body))
+}
+
+/// Two seconds of a 1.504 Mbit/s mux: one packet per millisecond, a PCR every ten.
+fn transport_stream(skip_audio_cc_at: Option<usize>) -> Vec<Vec<u8>> {
+    let mut packets = vec![pat(), pmt()];
+    let (mut video_cc, mut audio_cc) = (0u8, 0u8);
+
+    for index in 0..2000usize {
+        let packet = match index % 10 {
+            0 => {
+                let pcr = index as u64 * 27_000;
+                video_cc = video_cc.wrapping_add(1);
+                packet(VIDEO_PID, false, video_cc, Some(pcr), &[0; 16])
+            }
+            1..=6 => {
+                video_cc = video_cc.wrapping_add(1);
+                packet(VIDEO_PID, false, video_cc, None, &[0; 184])
+            }
+            7 | 8 => {
+                audio_cc = audio_cc.wrapping_add(1);
+                if skip_audio_cc_at == Some(index) {
+                    audio_cc = audio_cc.wrapping_add(1);
+                }
+                packet(AUDIO_PID, false, audio_cc, None, &[0; 184])
+            }
+            _ => packet(NULL_PID, false, 0, None, &[]),
+        };
+        packets.push(packet);
+    }
+    packets
+}
+
+fn write_file(dir: &Path, name: &str, packets: &[Vec<u8>]) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, packets.concat()).unwrap();
+    path
+}
+
+#[test]
+fn test_detect_layout() {
+    let plain = transport_stream(None).concat();
+    assert_eq!(
+        detect_layout(&plain[..512]),
+        Some(TsLayout {
+            packet_size: 188,
+            sync_offset: 0
+        })
+    );
+
+    let timecoded: Vec<u8> = transport_stream(None)
+        .iter()
+        .flat_map(|packet| [vec![0; 4], packet.clone()].concat())
+        .collect();
+    assert_eq!(
+        detect_layout(&timecoded[..512]),
+        Some(TsLayout {
+            packet_size: 192,
+            sync_offset: 4
+        })
+    );
+
+    assert_eq!(detect_layout(b"GIF89a and nothing else"), None);
+}
+
+#[test]
+fn test_transport_stream_is_video() {
+    let dir = tempfile::tempdir().unwrap();
+    // No helpful extension: detection must come from the packet sync bytes
+    let path = write_file(dir.path(), "capture.bin", &transport_stream(None));
+    assert!(matches!(detect_media_type(&path), MediaType::Video));
+
+    let empty = dir.path().join("empty.m2ts");
+    std::fs::write(&empty, b"").unwrap();
+    assert!(matches!(detect_media_type(&empty), MediaType::Video));
+}
+
+#[test]
+fn test_programs_and_pids() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_file(dir.path(), "capture.ts", &transport_stream(None));
+
+    let analysis = analyze(&path, 1.0).unwrap();
+    assert_eq!(analysis.id, "capture.ts");
+    assert_eq!(analysis.packet_size, 188);
+    assert_eq!(analysis.packet_count, 2002);
+    assert_eq!(analysis.continuity_errors, 0);
+    assert_eq!(analysis.sync_losses, 0);
+    assert!((analysis.null_ratio - 200.0 / 2002.0).abs() < 1e-9);
+
+    assert_eq!(analysis.programs.len(), 1);
+    let program = &analysis.programs[0];
+    assert_eq!(program.program_number, 1);
+    assert_eq!(program.pmt_pid, PMT_PID);
+    assert_eq!(program.pcr_pid, Some(VIDEO_PID));
+    let codecs: Vec<(u16, &str)> = program
+        .streams
+        .iter()
+        .map(|stream| (stream.pid, stream.codec.as_str()))
+        .collect();
+    assert_eq!(codecs, vec![(VIDEO_PID, "H.264"), (AUDIO_PID, "AC-3")]);
+
+    let labels: Vec<(u16, &str, u64)> = analysis
+        .pids
+        .iter()
+        .map(|pid| (pid.pid, pid.label.as_str(), pid.packet_count))
+        .collect();
+    assert_eq!(
+        labels,
+        vec![
+            (0, "PAT", 1),
+            (PMT_PID, "PMT (program 1)", 1),
+            (VIDEO_PID, "H.264", 1400),
+            (AUDIO_PID, "AC-3", 400),
+            (NULL_PID, "Null", 200),
+        ]
+    );
+}
+
+#[test]
+fn test_timing_and_bitrate() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_file(dir.path(), "capture.ts", &transport_stream(None));
+
+    let analysis = analyze(&path, 1.0).unwrap();
+    let duration = analysis.duration.unwrap();
+    assert!((duration - 1.99).abs() < 1e-6, "duration {duration}");
+    let bitrate = analysis.bitrate.unwrap();
+    assert!((bitrate - 1_504_000.0).abs() < 1.0, "bitrate {bitrate}");
+
+    let pcr = analysis.pcr.unwrap();
+    assert_eq!(pcr.pid, VIDEO_PID);
+    assert_eq!(pcr.count, 200);
+    assert_eq!(pcr.discontinuities, 0);
+    assert!((pcr.max_interval_ms - 10.0).abs() < 1e-6);
+    assert!(pcr.max_jitter_ms < 1e-6);
+
+    // 700 video packets per second
+    let video = analysis
+        .pids
+        .iter()
+        .find(|pid| pid.pid == VIDEO_PID)
+        .unwrap();
+    assert_eq!(video.bitrate.len(), 2);
+    for bits in &video.bitrate {
+        assert!((bits - 700.0 * 188.0 * 8.0).abs() / bits < 0.01, "{bits}");
+    }
+}
+
+#[test]
+fn test_continuity_errors_and_resync() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut packets = transport_stream(Some(507));
+    // Garbage between packets forces a resync
+    packets.insert(100, vec![0x00; 50]);
+    let path = write_file(dir.path(), "damaged.ts", &packets);
+
+    let analysis = analyze(&path, 1.0).unwrap();
+    assert_eq!(analysis.sync_losses, 1);
+    assert_eq!(analysis.packet_count, 2002);
+    assert_eq!(analysis.continuity_errors, 1);
+    let audio = analysis
+        .pids
+        .iter()
+        .find(|pid| pid.pid == AUDIO_PID)
+        .unwrap();
+    assert_eq!(audio.continuity_errors, 1);
+}
+
+#[test]
+fn test_discontinuity_after_short_segment() {
+    let dir = tempfile::tempdir().unwrap();
+    // Two PCRs a packet apart, a long run without any, then a jump in the clock
+    let mut packets = vec![
+        pat(),
+        pmt(),
+        packet(VIDEO_PID, false, 0, Some(0), &[0; 16]),
+        packet(VIDEO_PID, false, 1, Some(5_400_000), &[0; 16]),
+    ];
+    packets.extend((0..5000).map(|_| packet(NULL_PID, false, 0, None, &[])));
+    packets.push(packet(VIDEO_PID, false, 2, Some(2_700_000_000), &[0; 16]));
+    let path = write_file(dir.path(), "spliced.ts", &packets);
+
+    // The gap is bridged by at most a second, not by the rate of a 0.2 s segment
+    let analysis = analyze(&path, 1.0).unwrap();
+    assert_eq!(analysis.pcr.unwrap().discontinuities, 1);
+    let duration = analysis.duration.unwrap();
+    assert!(duration <= 1.2 + 1e-9, "duration {duration}");
+    assert!(analysis.pids.iter().all(|pid| pid.bitrate.len() <= 2));
+}
+
+#[test]
+fn test_invalid_interval() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_file(dir.path(), "capture.ts", &transport_stream(None));
+
+    for interval in [0.0, -1.0, 1e-9, f64::NAN, f64::INFINITY] {
+        assert!(analyze(&path, interval).is_err(), "{interval}");
+    }
+}
+
+#[test]
+fn test_not_a_transport_stream() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.ts");
+    std::fs::write(&path, b"export const answer = 42;\n").unwrap();
+
+    assert!(analyze(&path, 1.0).is_err());
+}
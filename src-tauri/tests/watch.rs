@@ -0,0 +1,164 @@
+use byte_me_lib::scan::ScanSettings;
+use byte_me_lib::state::AppState;
+use byte_me_lib::watch::{
+    analyze_settled, FolderWatcher, Snapshot, StabilityTracker, WatchError, WatchSettings,
+};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+fn snapshot(size: u64) -> Snapshot {
+    Snapshot {
+        size,
+        modified: None,
+    }
+}
+
+#[test]
+fn test_tracker_waits_for_stable_size() {
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+    let clip = PathBuf::from("/farm/clip.mp4");
+    let mut tracker = StabilityTracker::new(Duration::from_millis(100));
+
+    assert!(tracker
+        .update([(clip.clone(), snapshot(10))], at(0))
+        .is_empty());
+    // Still growing, so the timer restarts
+    assert!(tracker
+        .update([(clip.clone(), snapshot(20))], at(80))
+        .is_empty());
+    assert!(tracker
+        .update([(clip.clone(), snapshot(20))], at(150))
+        .is_empty());
+    assert_eq!(
+        tracker.update([(clip.clone(), snapshot(20))], at(180)),
+        std::slice::from_ref(&clip)
+    );
+    // Reported once only
+    assert!(tracker
+        .update([(clip.clone(), snapshot(20))], at(400))
+        .is_empty());
+
+    // Overwritten in place, so it is reported again once settled
+    assert!(tracker
+        .update([(clip.clone(), snapshot(30))], at(500))
+        .is_empty());
+    assert_eq!(
+        tracker.update([(clip.clone(), snapshot(30))], at(600)),
+        [clip]
+    );
+}
+
+#[test]
+fn test_tracker_baseline_and_removal() {
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+    let old = PathBuf::from("/farm/old.mp4");
+    let new = PathBuf::from("/farm/new.mp4");
+    let mut tracker = StabilityTracker::new(Duration::ZERO);
+
+    tracker.baseline([(old.clone(), snapshot(5))], at(0));
+    assert_eq!(
+        tracker.update(
+            [(old.clone(), snapshot(5)), (new.clone(), snapshot(7))],
+            at(10)
+        ),
+        [new]
+    );
+
+    // Deleted and rendered again
+    assert!(tracker.update([], at(20)).is_empty());
+    assert_eq!(tracker.update([(old.clone(), snapshot(5))], at(30)), [old]);
+}
+
+#[test]
+fn test_folder_watcher_reports_new_files() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("existing.mp4"), b"old render").unwrap();
+
+    let settings = WatchSettings {
+        poll_interval_ms: 20,
+        stable_for_ms: 100,
+        ..WatchSettings::default()
+    };
+    let (sender, settled) = mpsc::channel();
+    let watcher =
+        FolderWatcher::start(dir.path(), settings, ScanSettings::default(), move |path| {
+            sender.send(path.to_path_buf()).unwrap();
+        })
+        .unwrap();
+
+    let render = dir.path().join("render.mp4");
+    fs::write(&render, b"first chunk").unwrap();
+    let reported = settled.recv_timeout(Duration::from_secs(3)).unwrap();
+    assert_eq!(reported, render);
+
+    watcher.stop();
+    // Existing files were left alone and nothing else was reported
+    assert!(settled.try_iter().next().is_none());
+}
+
+#[test]
+fn test_include_existing_and_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let existing = dir.path().join("existing.mp4");
+    fs::write(&existing, b"old render").unwrap();
+
+    let settings = WatchSettings {
+        poll_interval_ms: 20,
+        stable_for_ms: 0,
+        include_existing: true,
+        ..WatchSettings::default()
+    };
+    let (sender, settled) = mpsc::channel();
+    let watcher =
+        FolderWatcher::start(dir.path(), settings, ScanSettings::default(), move |path| {
+            let _ = sender.send(path.to_path_buf());
+        })
+        .unwrap();
+    assert_eq!(
+        settled.recv_timeout(Duration::from_secs(3)).unwrap(),
+        existing
+    );
+    watcher.stop();
+
+    let missing = FolderWatcher::start(
+        &dir.path().join("missing"),
+        settings,
+        ScanSettings::default(),
+        |_| {},
+    );
+    assert!(matches!(missing, Err(WatchError::NotDirectory(_))));
+}
+
+#[test]
+fn test_app_state_watchers() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = AppState::load(None, None);
+
+    let folder = state.start_watching(dir.path(), |_, _| {}).unwrap();
+    assert_eq!(state.watched_folders(), std::slice::from_ref(&folder));
+    assert!(matches!(
+        state.start_watching(dir.path(), |_, _| {}),
+        Err(WatchError::AlreadyWatching(_))
+    ));
+
+    state.stop_watching(dir.path()).unwrap();
+    assert!(state.watched_folders().is_empty());
+    assert!(matches!(
+        state.stop_watching(&folder),
+        Err(WatchError::NotWatching(_))
+    ));
+}
+
+#[test]
+fn test_non_media_files_are_ignored() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes.txt");
+    fs::write(&notes, b"render log").unwrap();
+
+    let state = AppState::load(None, None);
+    assert!(analyze_settled(&state, dir.path(), &notes, true).is_none());
+}
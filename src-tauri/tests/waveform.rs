@@ -0,0 +1,115 @@
+use byte_me_lib::analysis::{extract_waveform, AnalysisError};
+use byte_me_lib::models::WaveformPeak;
+use byte_me_lib::waveform::{PeakAccumulator, SAMPLE_RATE};
+
+mod common;
+use common::*;
+
+fn peak(min: f32, max: f32) -> WaveformPeak {
+    WaveformPeak { min, max }
+}
+
+fn pcm(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect()
+}
+
+#[test]
+fn test_buckets() {
+    let mut accumulator = PeakAccumulator::new(3);
+    for sample in [0.1, -0.5, 0.3, 0.9, 0.2, -0.1, 0.4] {
+        accumulator.push_sample(sample);
+    }
+    // The trailing partial bucket is kept
+    assert_eq!(
+        accumulator.finish(),
+        [peak(-0.5, 0.3), peak(-0.1, 0.9), peak(0.4, 0.4)]
+    );
+
+    assert!(PeakAccumulator::new(3).finish().is_empty());
+}
+
+#[test]
+fn test_samples_split_across_chunks() {
+    let bytes = pcm(&[0.25, -0.75, 0.5, -0.25]);
+    let mut accumulator = PeakAccumulator::new(2);
+    for chunk in [
+        &bytes[..3],
+        &bytes[3..5],
+        &bytes[5..6],
+        &bytes[6..13],
+        &bytes[13..],
+    ] {
+        accumulator.push_bytes(chunk);
+    }
+    assert_eq!(accumulator.finish(), [peak(-0.75, 0.25), peak(-0.25, 0.5)]);
+}
+
+#[test]
+fn test_out_of_range_samples() {
+    let mut accumulator = PeakAccumulator::new(4);
+    accumulator.push_bytes(&pcm(&[1.5, f32::NAN, -3.0, 0.0]));
+    assert_eq!(accumulator.finish(), [peak(-1.0, 1.0)]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_with_ffmpeg() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("mix.flac");
+    std::fs::write(&input, b"fLaC").unwrap();
+
+    // A quarter second: a loud burst, then silence
+    let quarter = (SAMPLE_RATE / 4) as usize;
+    let mut samples = vec![0.0f32; quarter];
+    samples[10] = -0.8;
+    samples[20] = 0.6;
+    let decoded = dir.path().join("decoded.pcm");
+    std::fs::write(&decoded, pcm(&samples)).unwrap();
+    let args = dir.path().join("args.txt");
+
+    let ffmpeg = fake_ffmpeg(
+        dir.path(),
+        &format!(
+            "echo \"$@\" > '{}'\ncat '{}'",
+            args.display(),
+            decoded.display()
+        ),
+    );
+    let waveform = extract_waveform(&ffmpeg, &input, 2, 20).unwrap();
+    assert_eq!(waveform.id, "mix.flac");
+    assert_eq!(waveform.audio_stream, 2);
+    assert_eq!(waveform.resolution, 20);
+    assert_eq!(waveform.peaks.len(), 5);
+    assert_eq!(waveform.peaks[0], peak(-0.8, 0.6));
+    assert_eq!(waveform.peaks[4], peak(0.0, 0.0));
+
+    let args = std::fs::read_to_string(&args).unwrap();
+    assert!(args.contains("-map 0:a:2"), "{args}");
+    assert!(args.contains("-f f32le"), "{args}");
+
+    // Resolutions are clamped to what the decode rate supports
+    let waveform = extract_waveform(&ffmpeg, &input, 0, 1_000_000).unwrap();
+    assert_eq!(waveform.resolution, 1000);
+    assert_eq!(waveform.peaks.len(), 250);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_no_audio() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("silent.mp4");
+    std::fs::write(&input, b"\0\0\0\x18ftyp").unwrap();
+
+    let ffmpeg = fake_ffmpeg(dir.path(), "exit 0");
+    assert!(matches!(
+        extract_waveform(&ffmpeg, &input, 0, 50),
+        Err(AnalysisError::NoFrames)
+    ));
+
+    let failing = fake_ffmpeg(dir.path(), "echo 'Invalid data' >&2\nexit 1");
+    let err = extract_waveform(&failing, &input, 0, 50).unwrap_err();
+    assert!(err.to_string().contains("Invalid data"));
+}
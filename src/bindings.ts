@@ -3,11 +3,60 @@ import type { StreamResult } from "@/bindings/StreamResult";
 import type { StreamDetail } from "@/bindings/StreamDetail";
 import type { StreamResultError } from "@/bindings/StreamResultError";
 import type { MediaType } from "@/bindings/MediaType";
+import type { Detection } from "@/bindings/Detection";
+import type { DetectionMethod } from "@/bindings/DetectionMethod";
+import type { Confidence } from "@/bindings/Confidence";
 import type { File } from "@/bindings/File";
 import type { FileCandidacy } from "@/bindings/FileCandidacy";
 import type { BitrateData } from "@/bindings/BitrateData";
 import type { BitrateFrame } from "@/bindings/BitrateFrame";
-export type { StreamResult, StreamDetail, StreamResultError, MediaType, File, FileCandidacy, BitrateData, BitrateFrame };
+import type { EfficiencyAnalysis } from "@/bindings/EfficiencyAnalysis";
+import type { EfficiencyFrame } from "@/bindings/EfficiencyFrame";
+import type { FfprobeInfo } from "@/bindings/FfprobeInfo";
+import type { ToolSource } from "@/bindings/ToolSource";
+import type { Settings } from "@/bindings/Settings";
+import type { ProcessLimits } from "@/bindings/ProcessLimits";
+import type { CacheSettings } from "@/bindings/CacheSettings";
+import type { NetworkSettings } from "@/bindings/NetworkSettings";
+import type { ScanSettings } from "@/bindings/ScanSettings";
+import type { SymlinkPolicy } from "@/bindings/SymlinkPolicy";
+import type { CacheStats } from "@/bindings/CacheStats";
+import type { TsAnalysis } from "@/bindings/TsAnalysis";
+import type { TsProgram } from "@/bindings/TsProgram";
+import type { TsStream } from "@/bindings/TsStream";
+import type { TsPid } from "@/bindings/TsPid";
+import type { PcrStats } from "@/bindings/PcrStats";
+import type { ManifestAnalysis } from "@/bindings/ManifestAnalysis";
+import type { ManifestKind } from "@/bindings/ManifestKind";
+import type { VariantAnalysis } from "@/bindings/VariantAnalysis";
+import type { SegmentAnalysis } from "@/bindings/SegmentAnalysis";
+import type { WatchSettings } from "@/bindings/WatchSettings";
+import type { WatchEvent } from "@/bindings/WatchEvent";
+import type { MediaTypeSettings } from "@/bindings/MediaTypeSettings";
+import type { ImageAnalysis } from "@/bindings/ImageAnalysis";
+import type { ImageFormat } from "@/bindings/ImageFormat";
+import type { ColorType } from "@/bindings/ColorType";
+import type { ExifInfo } from "@/bindings/ExifInfo";
+import type { Animation } from "@/bindings/Animation";
+import type { AnimationFrame } from "@/bindings/AnimationFrame";
+import type { LoudnessAnalysis } from "@/bindings/LoudnessAnalysis";
+import type { LoudnessPoint } from "@/bindings/LoudnessPoint";
+import type { Waveform } from "@/bindings/Waveform";
+import type { WaveformPeak } from "@/bindings/WaveformPeak";
+import type { Thumbnail } from "@/bindings/Thumbnail";
+import type { ThumbnailFormat } from "@/bindings/ThumbnailFormat";
+import type { SceneAnalysis } from "@/bindings/SceneAnalysis";
+import type { SceneScore } from "@/bindings/SceneScore";
+import type { QualityAnalysis } from "@/bindings/QualityAnalysis";
+import type { QualityFrame } from "@/bindings/QualityFrame";
+import type { QualitySummary } from "@/bindings/QualitySummary";
+import type { QcReport } from "@/bindings/QcReport";
+import type { QcInterval } from "@/bindings/QcInterval";
+import type { QcSettings } from "@/bindings/QcSettings";
+export type { StreamResult, StreamDetail, StreamResultError, MediaType, Detection, DetectionMethod, Confidence, File, FileCandidacy, BitrateData, BitrateFrame, EfficiencyAnalysis, EfficiencyFrame, FfprobeInfo, ToolSource, Settings, ProcessLimits, CacheSettings, NetworkSettings, ScanSettings, SymlinkPolicy, CacheStats, TsAnalysis, TsProgram, TsStream, TsPid, PcrStats, ManifestAnalysis, ManifestKind, VariantAnalysis, SegmentAnalysis, WatchSettings, WatchEvent, MediaTypeSettings, ImageAnalysis, ImageFormat, ColorType, ExifInfo, Animation, AnimationFrame, LoudnessAnalysis, LoudnessPoint, Waveform, WaveformPeak, Thumbnail, ThumbnailFormat, SceneAnalysis, SceneScore, QualityAnalysis, QualityFrame, QualitySummary, QcReport, QcInterval, QcSettings };
+
+// Event emitted by the backend for every file analyzed in a watched folder
+export const WATCH_EVENT = "watch-result";
 
 // Tauri invoke wrapper
 import { invoke } from "@tauri-apps/api/core";
@@ -31,7 +80,87 @@ export const commands = {
     return await invoke<File[]>("analyze_files", { paths });
   },
 
+  async pasteFiles(): Promise<File[]> {
+    return await invoke<File[]>("paste_files");
+  },
+
   async extractBitrateData(path: string): Promise<BitrateData> {
     return await invoke<BitrateData>("extract_bitrate_data", { path });
+  },
+
+  async analyzeEfficiency(path: string): Promise<EfficiencyAnalysis> {
+    return await invoke<EfficiencyAnalysis>("analyze_efficiency", { path });
+  },
+
+  // Renders the frame at `timestamp`, or every keyframe when it is omitted
+  async extractThumbnails(
+    path: string,
+    options: { timestamp?: number; width?: number; format?: ThumbnailFormat; inline?: boolean } = {},
+  ): Promise<Thumbnail[]> {
+    return await invoke<Thumbnail[]>("extract_thumbnails", { path, ...options });
+  },
+
+  async detectScenes(path: string, threshold?: number): Promise<SceneAnalysis> {
+    return await invoke<SceneAnalysis>("detect_scenes", { path, threshold });
+  },
+
+  async runQc(path: string, thresholds?: QcSettings): Promise<QcReport> {
+    return await invoke<QcReport>("run_qc", { path, thresholds });
+  },
+
+  async compareQuality(path: string, reference: string): Promise<QualityAnalysis> {
+    return await invoke<QualityAnalysis>("compare_quality", { path, reference });
+  },
+
+  async analyzeTransportStream(path: string): Promise<TsAnalysis> {
+    return await invoke<TsAnalysis>("analyze_transport_stream", { path });
+  },
+
+  async analyzeImage(path: string): Promise<ImageAnalysis> {
+    return await invoke<ImageAnalysis>("analyze_image", { path });
+  },
+
+  async analyzeLoudness(path: string, audioStream?: number): Promise<LoudnessAnalysis> {
+    return await invoke<LoudnessAnalysis>("analyze_loudness", { path, audioStream });
+  },
+
+  async extractWaveform(
+    path: string,
+    audioStream?: number,
+    resolution?: number,
+  ): Promise<Waveform> {
+    return await invoke<Waveform>("extract_waveform", { path, audioStream, resolution });
+  },
+
+  async analyzeManifest(path: string): Promise<ManifestAnalysis> {
+    return await invoke<ManifestAnalysis>("analyze_manifest", { path });
+  },
+
+  async startWatching(path: string): Promise<string> {
+    return await invoke<string>("start_watching", { path });
+  },
+
+  async stopWatching(path: string): Promise<void> {
+    return await invoke<void>("stop_watching", { path });
+  },
+
+  async listWatched(): Promise<string[]> {
+    return await invoke<string[]>("list_watched");
+  },
+
+  async getFfprobeInfo(): Promise<FfprobeInfo> {
+    return await invoke<FfprobeInfo>("get_ffprobe_info");
+  },
+
+  async clearCache(): Promise<CacheStats> {
+    return await invoke<CacheStats>("clear_cache");
+  },
+
+  async getSettings(): Promise<Settings> {
+    return await invoke<Settings>("get_settings");
+  },
+
+  async updateSettings(settings: Settings): Promise<void> {
+    return await invoke<void>("update_settings", { settings });
   }
 };
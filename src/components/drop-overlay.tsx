@@ -1,16 +1,18 @@
 import { type ReactNode, useEffect, useState } from "react";
 import { match, P } from "ts-pattern";
 import {
+	Captions,
 	File as FileIcon,
 	FileText,
 	Film,
 	Image,
+	ListVideo,
 	Loader2,
 	Music,
 	XCircle,
 } from "lucide-react";
 import { commands } from "@/bindings";
-import type { File, FileCandidacy, MediaType } from "@/bindings";
+import type { Detection, File, FileCandidacy, MediaType } from "@/bindings";
 
 type DropOverlayProps = {
 	paths: string[];
@@ -38,6 +40,10 @@ const getFileIcon = (candidacy: FileCandidacy): ReactNode => {
 					return <Film className="w-5 h-5 text-purple-400" />;
 				case "Image":
 					return <Image className="w-5 h-5 text-pink-400" />;
+				case "Subtitle":
+					return <Captions className="w-5 h-5 text-cyan-400" />;
+				case "Playlist":
+					return <ListVideo className="w-5 h-5 text-teal-400" />;
 				case "Document":
 					return <FileText className="w-5 h-5 text-green-400" />;
 				case "Executable":
@@ -57,10 +63,21 @@ const getStatusColor = (candidacy: FileCandidacy): string => {
 	return match(candidacy)
 		.with("Loading", () => "border-blue-500/50")
 		.with({ Error: P._ }, () => "border-red-500/50")
+		.with(
+			{ Success: { detection: { mismatch: P.string } } },
+			() => "border-yellow-500/50",
+		)
 		.with({ Success: P._ }, () => "border-green-500/50")
 		.exhaustive();
 };
 
+// Flags types that were only guessed, or whose content contradicts the extension
+const getDetectionNote = (detection: Detection): string | null => {
+	if (detection.mismatch !== null) return detection.mismatch;
+	if (detection.confidence === "Low") return "guessed from extension";
+	return null;
+};
+
 const FileItem = ({ file }: { file: File }) => {
 	const icon = getFileIcon(file.candidacy);
 	const statusColor = getStatusColor(file.candidacy);
@@ -77,6 +94,10 @@ const FileItem = ({ file }: { file: File }) => {
 					return "Video file";
 				case "Image":
 					return "Image file";
+				case "Subtitle":
+					return "Subtitle file";
+				case "Playlist":
+					return "Playlist file";
 				case "Document":
 					return "Document file";
 				case "Executable":
@@ -90,6 +111,9 @@ const FileItem = ({ file }: { file: File }) => {
 			}
 		})
 		.exhaustive();
+	const detectionNote = match(file.candidacy)
+		.with({ Success: { detection: P.select() } }, getDetectionNote)
+		.otherwise(() => null);
 
 	return (
 		<div
@@ -107,6 +131,11 @@ const FileItem = ({ file }: { file: File }) => {
 				<div className="truncate text-neutral-400 text-sm mt-1">
 					{fileSize} • {subtitle}
 				</div>
+				{detectionNote && (
+					<div className="truncate text-yellow-400 text-xs mt-1">
+						{detectionNote}
+					</div>
+				)}
 			</div>
 		</div>
 	);
@@ -130,6 +159,7 @@ const DropOverlay = ({ paths }: DropOverlayProps) => {
 		const loadingFiles: File[] = paths.map((path) => {
 			const filename = path.split(/[/\\]/).pop() || "unknown";
 			return {
+				path,
 				filename,
 				size: 0,
 				candidacy: "Loading" as const,
@@ -150,6 +180,7 @@ const DropOverlay = ({ paths }: DropOverlayProps) => {
 				const errorFiles: File[] = paths.map((path) => {
 					const filename = path.split(/[/\\]/).pop() || "unknown";
 					return {
+						path,
 						filename,
 						size: 0,
 						candidacy: {